@@ -71,5 +71,30 @@ fn bench_serialize(c: &mut Criterion) {
     group.finish();
 }
 
-criterion_group!(benches, bench_parse, bench_serialize);
+fn bench_serialize_flat(c: &mut Criterion) {
+    let flat: serde_json::Value = (0..1000)
+        .map(|i| (format!("key_{i}"), format!("translated text number {i}").into()))
+        .collect::<serde_json::Map<_, _>>()
+        .into();
+
+    // One non-string value sends the same map through the general path.
+    let mut mixed = flat.clone();
+    mixed["zz_count"] = 1000.into();
+
+    let mut group = c.benchmark_group("serialize_flat_1000");
+
+    group.bench_with_input(BenchmarkId::new("jhon", "flat"), &flat, |b, value| {
+        b.iter(|| serialize(black_box(value)));
+    });
+    group.bench_with_input(BenchmarkId::new("jhon", "general"), &mixed, |b, value| {
+        b.iter(|| serialize(black_box(value)));
+    });
+    group.bench_with_input(BenchmarkId::new("json", "flat"), &flat, |b, value| {
+        b.iter(|| serde_json::to_string(black_box(value)).unwrap());
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_parse, bench_serialize, bench_serialize_flat);
 criterion_main!(benches);
@@ -4,7 +4,11 @@ use serde::{
 };
 use serde_json::Value;
 use serde_json::{Map, Number};
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fmt::Write as _;
+use std::time::Duration;
 
 // =============================================================================
 // Error Type
@@ -31,6 +35,36 @@ pub enum JhonError {
         col: usize,
         key: String,
     },
+    /// A bare key was used while [`ParseOptions::require_quoted_keys`] is set.
+    UnquotedKey {
+        line: usize,
+        col: usize,
+        key: String,
+    },
+    /// A `key=` was followed by a separator, closing delimiter, or the end
+    /// of input instead of a value.
+    MissingValue {
+        line: usize,
+        col: usize,
+        key: String,
+    },
+    /// A config value at `key` is missing or could not be interpreted, e.g. by
+    /// [`get_duration`] or [`get_bytesize`].
+    InvalidValue { key: String, msg: String },
+    /// The value at `key` exists but could not be deserialized into the
+    /// requested type by [`get_typed`]; `msg` is the serde error.
+    WrongType { key: String, msg: String },
+    /// Serialized output grew past the byte budget given to
+    /// [`serialize_bounded`].
+    OutputTooLarge { limit: usize },
+    /// A string literal starting at `line`:`col` holds more than
+    /// [`ParseOptions::max_string_len`] bytes.
+    StringTooLong { line: usize, col: usize, max: usize },
+    /// A document broke one of the [`Limits`]; `name` is the field
+    /// (`"max_depth"`, `"max_keys"` or `"max_len"`) and `max` its value.
+    LimitExceeded { name: &'static str, max: usize },
+    /// Writing to an output sink failed; wraps the I/O error message.
+    Io(String),
     /// A serde (de)serialization error wrapping the underlying message.
     Serde(String),
 }
@@ -47,6 +81,32 @@ impl std::fmt::Display for JhonError {
             JhonError::DuplicateKey { line, col, key } => {
                 write!(f, "duplicate key at {}:{}: {:?}", line, col, key)
             }
+            JhonError::UnquotedKey { line, col, key } => {
+                write!(
+                    f,
+                    "unquoted key at {}:{}: {:?} must be quoted",
+                    line, col, key
+                )
+            }
+            JhonError::MissingValue { line, col, key } => {
+                write!(f, "missing value at {}:{}: for key {:?}", line, col, key)
+            }
+            JhonError::InvalidValue { key, msg } => {
+                write!(f, "invalid value for {:?}: {}", key, msg)
+            }
+            JhonError::WrongType { key, msg } => {
+                write!(f, "wrong type for {:?}: {}", key, msg)
+            }
+            JhonError::OutputTooLarge { limit } => {
+                write!(f, "serialized output exceeds {} bytes", limit)
+            }
+            JhonError::StringTooLong { line, col, max } => {
+                write!(f, "string at {}:{} exceeds {} bytes", line, col, max)
+            }
+            JhonError::LimitExceeded { name, max } => {
+                write!(f, "document exceeds {} of {}", name, max)
+            }
+            JhonError::Io(msg) => write!(f, "I/O error: {}", msg),
             JhonError::Serde(msg) => write!(f, "deserialization error: {}", msg),
         }
     }
@@ -60,6 +120,12 @@ impl From<serde_json::Error> for JhonError {
     }
 }
 
+impl From<std::io::Error> for JhonError {
+    fn from(e: std::io::Error) -> Self {
+        JhonError::Io(e.to_string())
+    }
+}
+
 /// Convenience alias used throughout the crate.
 pub type Result<T> = std::result::Result<T, JhonError>;
 
@@ -80,2548 +146,11107 @@ macro_rules! syntax_err {
 ///
 /// let result = parse(r#"name="John",age=30"#).unwrap();
 /// ```
+///
+/// # Decimal numbers
+///
+/// By default a number with a fraction or exponent is stored as an `f64`,
+/// so `99.99` is the nearest binary float. With the `decimal` cargo feature,
+/// which turns on serde_json's `arbitrary_precision`, a fractional literal
+/// without an exponent is kept as its exact decimal text inside the
+/// [`Number`] (underscores and redundant leading zeros removed) and the
+/// serializers write that text back unchanged, except that a whole value
+/// such as `5.0` is written as `5`, as in the default build. Read it with
+/// `Number::as_str` and hand it to a decimal type such as `rust_decimal`'s
+/// `Decimal`; `as_f64` still rounds. Integers and exponent forms are
+/// unaffected.
+///
+/// The feature is called `decimal` rather than `rust_decimal` because it
+/// adds no decimal crate: the exact text lives in `serde_json`'s own
+/// `Number`, and a `rust_decimal` feature would suggest an optional
+/// dependency of that name. Cargo unifies features, so enabling it turns on
+/// `arbitrary_precision` for every user of `serde_json` in the build. Under
+/// that feature, `Number` equality compares text, so `1.5e3` and `1500.0`
+/// are unequal values.
 #[inline]
 pub fn parse(text: &str) -> Result<Value> {
-    // Empty input (including whitespace-only and comments-only) → JSON null.
-    // Per SPEC.md §2, this is the "Empty" form. The document has no elements,
-    // so it cannot be object mode or array mode.
-    {
-        let mut probe = Parser::new(text.as_bytes());
-        probe.skip_ws_and_comments();
-        if probe.current().is_none() {
-            return Ok(Value::Null);
+    parse_with_options(text, &ParseOptions::DEFAULT)
+}
+
+/// Opt-in parser extensions. Every option defaults to off, which is the
+/// plain SPEC.md grammar that [`parse`] accepts.
+///
+/// `indent_blocks` enables YAML-style nesting for objects: a key alone on
+/// its line, followed by lines indented deeper than the key, becomes a
+/// nested object built from those lines.
+///
+/// ```text
+/// server
+///   host = "localhost"
+///   port = 8080
+/// name = "app"
+/// ```
+///
+/// is equivalent to `server = { host = "localhost", port = 8080 }`, `name = "app"`.
+/// Limitations: blocks only produce objects (use `[...]` for arrays); the
+/// block key may be followed only by a comment on its line; indentation is
+/// measured in leading space/tab bytes, so mixing tabs and spaces is not
+/// normalized; every entry of a block must start at the same indentation.
+///
+/// `require_quoted_keys` rejects bare keys with [`JhonError::UnquotedKey`],
+/// for teams that want every key written as `"name" = ...`.
+///
+/// `bare_keys_are_true` reads a key with no `=` as a flag set to `true`, so
+/// `verbose, name = "x"` gives `{"verbose": true, "name": "x"}`. The flag
+/// must still be separated from the next item by a comma, newline, or the
+/// closing brace; `verbose debug` on one line remains an error. A document
+/// whose first item is a value literal (`1`, `true`, `"s"`) stays in array
+/// mode.
+///
+/// `bareword_values` reads an unquoted word in value position as a string,
+/// so `path = C:\Users\me` and `root = /usr/local/bin` need no quotes. A
+/// bareword is a maximal run of characters other than whitespace, `,`, `=`,
+/// brackets and braces (and `)` when `paren_tuples` is on); it also ends
+/// before `//` or `/*`, which start a comment. A quote starts a string
+/// rather than a bareword, but inside one it is kept, so `q = it's` works.
+/// Backslashes are kept literally. Values that begin with a digit or `-` are still numbers, and a
+/// bareword spelling exactly `true`, `false`, or `null` is still that literal.
+///
+/// `lowercase_keys` lowercases every key, quoted or bare, as it is read,
+/// for case-insensitive lookups. Keys that differ only in case then collide
+/// and fail with [`JhonError::DuplicateKey`] like any other repeated key.
+///
+/// `coerce_bool_strings` turns a string value that is exactly `"true"` or
+/// `"false"` (case-sensitive, in any string form) into a boolean, for input
+/// generated by tools that stringify booleans. It is lossy — such a string
+/// can no longer be told apart from a boolean — so it is off by default.
+/// Keys are never coerced.
+///
+/// `paren_tuples` accepts `(1, 2, 3)` wherever a value may appear, as
+/// another spelling of the array `[1, 2, 3]`: elements follow the same
+/// separator rules, and tuples nest. The result is an ordinary array, so it
+/// serializes back with brackets.
+///
+/// `plus_sign_numbers` accepts a leading `+` on a number (`+5`, `+0.5`,
+/// `+1e3`), which SPEC.md otherwise rejects. [`parse`]-style results drop the
+/// sign, so `+5` reads as `5`; [`parse_spanned_with_options`] remembers it
+/// for [`serialize_spanned`]. A `+` with no digits after it is still an
+/// error. This stays opt-in by design: accepting `+` by default was declined
+/// to keep one spelling per number (SPEC.md §9, decision 3).
+///
+/// `heredoc_strings` accepts a heredoc as a string value, for embedding
+/// scripts and templates:
+///
+/// ```text
+/// script = <<END
+/// echo "building"
+/// make all
+/// END
+/// ```
+///
+/// The marker (ASCII letters, digits, `_`) must end its line. The lines
+/// after it, up to the first line that is exactly the marker, are taken
+/// verbatim — no escapes, no comments — minus the newline before the
+/// terminator. The marker elsewhere in a line does not end the heredoc, so
+/// a following `,` or `]` goes on the next line.
+///
+/// `reject_nul` makes a raw NUL byte outside a string literal a syntax
+/// error (`unexpected NUL byte`). By default NUL is an ordinary bare-key
+/// character (SPEC.md §3.3), so binary input can slip into keys unnoticed.
+/// Inside regular strings NUL is always an error, as are all raw control
+/// characters; raw strings keep it verbatim either way.
+///
+/// `max_string_len` caps the content of each string literal — quoted,
+/// raw, heredoc, and quoted keys alike — at that many bytes after escapes
+/// are decoded. A longer string fails with [`JhonError::StringTooLong`] as
+/// soon as the cap is passed, without scanning on to its closing quote, so
+/// one huge value in untrusted input cannot exhaust memory.
+///
+/// `empty_document` decides what a document with no items — empty,
+/// whitespace-only, or comments-only — parses to: `null` per SPEC.md §2
+/// ([`EmptyBehavior::Null`], the default), an empty object, or a
+/// [`JhonError::Eof`] for callers that treat an empty file as truncated.
+///
+/// `reject_top_level_values` fails an array-mode document (SPEC.md §2) at
+/// its first element, for config files that must be `key = value` pairs. A
+/// lone `"hello"` or `42` then reports that a key is missing, instead of
+/// parsing as a one-element array. Ignored with `single_root_value`.
+///
+/// `nested_block_comments` lets `/* ... */` nest as in Rust, so
+/// `/* outer /* inner */ still a comment */` is one comment and a region
+/// that already contains block comments can be commented out whole. Each
+/// `/*` inside a comment must then be closed; an unterminated comment is a
+/// syntax error (`Unterminated block comment`).
+///
+/// `semicolon_comments` also starts a line comment at `;`, as in INI files:
+/// `port = 80 ; default` ignores everything from the `;` to the end of the
+/// line. A `;` inside a quoted, raw or heredoc string is text as usual, and
+/// so is one inside a bare key. A bareword value ends at `;`.
+///
+/// `tagged_objects` accepts a variant tag before an object value, for
+/// tagged unions: `mode = @enabled { level = "high" }` reads as
+/// `{"__tag__": "enabled", "level": "high"}`, with the tag first, and a tag
+/// alone, `mode = @off`, as `{"__tag__": "off"}`. The object must start on
+/// the tag's line; a `{` on the next line is a separate value. That is the shape serde
+/// expects for an enum marked `#[serde(tag = "__tag__")]`. A tag is a run of
+/// ASCII letters, digits, `_` and `-`; an object that already has a
+/// `__tag__` key is a syntax error.
+///
+/// `on_int_overflow` decides what an integer literal outside the `i64` and
+/// `u64` ranges becomes: the nearest `f64`, losing precision
+/// ([`OverflowPolicy::Float`], the default), a syntax error, or the nearest
+/// bound, `i64::MIN` or `u64::MAX` ([`OverflowPolicy::Saturate`]). It
+/// applies to decimal and radix-prefixed literals alike; literals with a
+/// fraction or exponent are floats and unaffected.
+///
+/// `dotted_keys_to_nested` splits keys on `.` after parsing and nests the
+/// values, as TOML does: `server.host="h", server.port=80` becomes
+/// `{"server": {"host": "h", "port": 80}}`. Entries sharing a prefix merge
+/// with each other and with a plain `server = {...}` object. A key whose
+/// prefix is already a non-object value, a key set twice, or an empty
+/// segment (`a..b`) is a [`JhonError::InvalidValue`]. Quoted keys are split
+/// too, since the distinction is gone once parsed.
+///
+/// `lossy_utf8` only affects byte input ([`parse_reader`]): invalid UTF-8
+/// is replaced with U+FFFD instead of being an error.
+///
+/// The remaining options narrow the grammar toward JSON and are combined by
+/// [`ParseOptions::json_strict`]:
+/// - `key_value_separator` selects `=` (default), `:`, or either.
+/// - `require_commas` stops newlines from separating items.
+/// - `reject_comments`, `reject_single_quotes`, `reject_raw_strings`, and
+///   `reject_trailing_commas` turn those constructs into syntax errors.
+/// - `single_root_value` reads the document as exactly one value, so a
+///   top-level `{...}` is the object itself rather than an array element.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub indent_blocks: bool,
+    pub require_quoted_keys: bool,
+    pub bare_keys_are_true: bool,
+    pub lowercase_keys: bool,
+    pub bareword_values: bool,
+    pub coerce_bool_strings: bool,
+    pub paren_tuples: bool,
+    pub plus_sign_numbers: bool,
+    pub heredoc_strings: bool,
+    pub reject_nul: bool,
+    pub max_string_len: Option<usize>,
+    pub empty_document: EmptyBehavior,
+    pub reject_top_level_values: bool,
+    pub nested_block_comments: bool,
+    pub semicolon_comments: bool,
+    pub tagged_objects: bool,
+    pub on_int_overflow: OverflowPolicy,
+    pub dotted_keys_to_nested: bool,
+    pub lossy_utf8: bool,
+    pub key_value_separator: KeyValueSeparator,
+    pub require_commas: bool,
+    pub reject_comments: bool,
+    pub reject_single_quotes: bool,
+    pub reject_raw_strings: bool,
+    pub reject_trailing_commas: bool,
+    pub single_root_value: bool,
+}
+
+impl ParseOptions {
+    const DEFAULT: ParseOptions = ParseOptions {
+        indent_blocks: false,
+        require_quoted_keys: false,
+        bare_keys_are_true: false,
+        lowercase_keys: false,
+        bareword_values: false,
+        coerce_bool_strings: false,
+        paren_tuples: false,
+        plus_sign_numbers: false,
+        heredoc_strings: false,
+        reject_nul: false,
+        max_string_len: None,
+        empty_document: EmptyBehavior::Null,
+        reject_top_level_values: false,
+        nested_block_comments: false,
+        semicolon_comments: false,
+        tagged_objects: false,
+        on_int_overflow: OverflowPolicy::Float,
+        dotted_keys_to_nested: false,
+        lossy_utf8: false,
+        key_value_separator: KeyValueSeparator::Equals,
+        require_commas: false,
+        reject_comments: false,
+        reject_single_quotes: false,
+        reject_raw_strings: false,
+        reject_trailing_commas: false,
+        single_root_value: false,
+    };
+
+    /// A JSON-compatible subset: one root value, quoted keys, `:` between
+    /// keys and values, mandatory commas, double-quoted strings only, and no
+    /// comments, raw strings, or trailing commas. Number syntax stays JHON's
+    /// (radix prefixes and `_` separators are still accepted).
+    ///
+    /// ```
+    /// use jhon::{parse_with_options, ParseOptions};
+    /// use serde_json::json;
+    ///
+    /// let strict = ParseOptions::json_strict();
+    /// let value = parse_with_options(r#"{"a": [1, 2], "b": null}"#, &strict).unwrap();
+    /// assert_eq!(value, json!({"a": [1, 2], "b": null}));
+    /// assert!(parse_with_options("{'a': 1}", &strict).is_err());
+    /// ```
+    pub fn json_strict() -> Self {
+        ParseOptions {
+            require_quoted_keys: true,
+            key_value_separator: KeyValueSeparator::Colon,
+            require_commas: true,
+            reject_comments: true,
+            reject_single_quotes: true,
+            reject_raw_strings: true,
+            reject_trailing_commas: true,
+            single_root_value: true,
+            ..Self::DEFAULT
         }
     }
+}
 
-    let input = text.trim();
+/// Which byte may sit between a key and its value; see [`ParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum KeyValueSeparator {
+    /// `key = value` (SPEC.md)
+    #[default]
+    Equals,
+    /// `key: value`
+    Colon,
+    /// Either form, per entry.
+    Either,
+}
 
-    // Mode detection (SPEC.md §2): the first top-level element decides whether
-    // the document is parsed as an object (key=value pairs) or as an implicit
-    // array (bare values). `{...}` and `[...]` always begin array mode since
-    // they cannot start a `key=` pair. For anything else, attempt to parse a
-    // key and look ahead for `=`.
-    let mut detector = Parser::new(input.as_bytes());
-    detector.skip_ws_and_comments();
-    let first_byte = detector.current();
-    let object_mode = match first_byte {
-        None | Some(b'{') | Some(b'[') => false,
-        Some(_) => {
-            let mut probe = detector;
-            match probe.parse_key() {
-                Ok(_) => {
-                    probe.skip_ws_and_comments();
-                    probe.current() == Some(b'=')
-                }
-                Err(_) => false,
-            }
+impl KeyValueSeparator {
+    fn accepts(self, b: u8) -> bool {
+        match self {
+            KeyValueSeparator::Equals => b == b'=',
+            KeyValueSeparator::Colon => b == b':',
+            KeyValueSeparator::Either => b == b'=' || b == b':',
         }
-    };
+    }
+}
 
-    if object_mode {
-        parse_jhon_object(input)
-    } else {
-        parse_jhon_array(input)
+impl std::fmt::Display for KeyValueSeparator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            KeyValueSeparator::Equals => "'='",
+            KeyValueSeparator::Colon => "':'",
+            KeyValueSeparator::Either => "'=' or ':'",
+        })
     }
 }
 
-/// Serialize a JSON Value into a compact JHON string
+/// What an empty document parses to; see [`ParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmptyBehavior {
+    /// `null` (SPEC.md §2)
+    #[default]
+    Null,
+    /// `{}`
+    EmptyObject,
+    /// [`JhonError::Eof`]
+    Error,
+}
+
+/// What an integer literal too large for `i64`/`u64` parses to; see
+/// [`ParseOptions`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy {
+    /// The nearest `f64` (lossy)
+    #[default]
+    Float,
+    /// [`JhonError::Syntax`]
+    Error,
+    /// `i64::MIN` for negative literals, `u64::MAX` otherwise
+    Saturate,
+}
+
+/// Key holding the variant name of a `@tag { ... }` value; see
+/// [`ParseOptions`].
+const TAG_KEY: &str = "__tag__";
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Parse with the extensions enabled in [``ParseOptions``].
 ///
 /// # Examples
 ///
 /// ```
-/// use jhon::serialize;
+/// use jhon::{parse_with_options, ParseOptions};
 /// use serde_json::json;
 ///
-/// let value = json!({"name": "John", "age": 30});
-/// let jhon_string = serialize(&value);
-/// assert_eq!(jhon_string, r#"name="John",age=30"#);
+/// let opts = ParseOptions { indent_blocks: true, ..Default::default() };
+/// let value = parse_with_options("server\n  port = 80", &opts).unwrap();
+/// assert_eq!(value, json!({"server": {"port": 80}}));
 /// ```
-#[inline]
-pub fn serialize(value: &Value) -> String {
-    let mut result = String::new();
-    serialize_top_compact(value, &mut result);
-    result
+pub fn parse_with_options(text: &str, opts: &ParseOptions) -> Result<Value> {
+    parse_document(text, opts, None)
 }
 
-/// Top-level dispatch. Per SPEC.md §2: empty containers and `null` serialize
-/// to empty string (the "Empty" form); top-level arrays emit bare (no `[]`).
-/// Nested values fall through to `serialize_compact` which preserves `[]` for
-/// nested arrays and `null` text for nested nulls.
-#[inline(always)]
-fn serialize_top_compact(value: &Value, result: &mut String) {
+/// [`parse_with_options`], reporting recoverable problems to `warnings`
+/// instead of failing when it is given; see [`parse_with_warnings`].
+fn parse_document(
+    text: &str,
+    opts: &ParseOptions,
+    warnings: Option<&RefCell<Vec<Warning>>>,
+) -> Result<Value> {
+    check_lexical_restrictions(text, opts)?;
+
+    // An unterminated nested block comment swallows the rest of the input,
+    // so it outranks whatever the parser made of what was left.
+    let unterminated_comment = Cell::new(None);
+    let check_comments = || match unterminated_comment.get() {
+        Some(pos) => {
+            let (line, col) = Parser::new(text.as_bytes()).line_col(pos);
+            Err(JhonError::Syntax {
+                line,
+                col,
+                msg: "Unterminated block comment".to_string(),
+            })
+        }
+        None => Ok(()),
+    };
+
+    // Empty input (including whitespace-only and comments-only) → JSON null.
+    // Per SPEC.md §2, this is the "Empty" form. The document has no elements,
+    // so it cannot be object mode or array mode.
+    let mut probe = Parser {
+        unterminated_comment: Some(&unterminated_comment),
+        ..Parser::with_options(text.as_bytes(), opts)
+    };
+    probe.skip_ws_and_comments();
+    if probe.current().is_none() {
+        check_comments()?;
+        return match opts.empty_document {
+            EmptyBehavior::Null => Ok(Value::Null),
+            EmptyBehavior::EmptyObject => Ok(Value::Object(Map::new())),
+            EmptyBehavior::Error => Err(JhonError::Eof {
+                line: probe.line,
+                col: probe.col,
+                msg: "empty document".to_string(),
+            }),
+        };
+    }
+
+    // Whitespace at either end, Unicode included, is ignored as by
+    // `str::trim`. The parser still starts from the untrimmed text so that
+    // error positions match the source.
+    let trimmed = text.trim();
+    let start = text.len() - text.trim_start().len();
+    let parser = Parser::with_options(&text.as_bytes()[..start + trimmed.len()], opts);
+    let (line, col) = parser.line_col(start);
+    let parser = Parser {
+        pos: start,
+        line,
+        col,
+        warnings,
+        unterminated_comment: Some(&unterminated_comment),
+        ..parser
+    };
+    let value = if opts.single_root_value {
+        parse_single_root(parser)
+    } else if is_object_mode(trimmed, opts) {
+        parse_jhon_object(parser)
+    } else if opts.reject_top_level_values {
+        Err(JhonError::Syntax {
+            line: probe.line,
+            col: probe.col,
+            msg: "top-level value found; did you mean to add a key, as in `key = value`?"
+                .to_string(),
+        })
+    } else {
+        parse_jhon_array(parser)
+    };
+    check_comments()?;
+    let value = value?;
+    if opts.dotted_keys_to_nested {
+        nest_dotted_keys(value)
+    } else {
+        Ok(value)
+    }
+}
+
+/// Rebuild every object in `value` with its dotted keys split into nested
+/// objects (`dotted_keys_to_nested`).
+fn nest_dotted_keys(value: Value) -> Result<Value> {
     match value {
-        Value::Array(arr) if arr.is_empty() => {}
-        Value::Array(arr) => serialize_array_contents_compact(arr, result),
-        Value::Object(map) if map.is_empty() => {}
-        Value::Null => {}
-        _ => serialize_compact(value, result),
+        Value::Object(map) => {
+            let mut nested = Map::new();
+            for (key, value) in map {
+                let segments: Vec<&str> = key.split('.').collect();
+                if segments.iter().any(|s| s.is_empty()) {
+                    return Err(JhonError::InvalidValue {
+                        key,
+                        msg: "empty segment in dotted key".to_string(),
+                    });
+                }
+                insert_nested(&mut nested, &segments, nest_dotted_keys(value)?, &key)?;
+            }
+            Ok(Value::Object(nested))
+        }
+        Value::Array(arr) => arr
+            .into_iter()
+            .map(nest_dotted_keys)
+            .collect::<Result<_>>()
+            .map(Value::Array),
+        other => Ok(other),
     }
 }
 
-/// Serialize a JSON Value into a pretty-printed JHON string with custom indentation
+/// Insert `value` into `map` under the path `segments`, creating objects
+/// along the way and merging into objects already there. `key` is the
+/// dotted key being inserted, for errors.
+fn insert_nested(
+    map: &mut Map<String, Value>,
+    segments: &[&str],
+    value: Value,
+    key: &str,
+) -> Result<()> {
+    let (first, rest) = segments.split_first().expect("keys have a segment");
+    if rest.is_empty() {
+        match (map.get_mut(*first), value) {
+            (None, value) => {
+                map.insert(first.to_string(), value);
+            }
+            (Some(Value::Object(existing)), Value::Object(inner)) => {
+                for (k, v) in inner {
+                    insert_nested(existing, &[&k], v, key)?;
+                }
+            }
+            _ => {
+                return Err(JhonError::InvalidValue {
+                    key: key.to_string(),
+                    msg: format!("`{}` is set more than once", first),
+                });
+            }
+        }
+        return Ok(());
+    }
+    match map
+        .entry(first.to_string())
+        .or_insert_with(|| Value::Object(Map::new()))
+    {
+        Value::Object(inner) => insert_nested(inner, rest, value, key),
+        _ => Err(JhonError::InvalidValue {
+            key: key.to_string(),
+            msg: format!("`{}` is already set to a non-object value", first),
+        }),
+    }
+}
+
+/// A non-fatal problem reported by [`parse_with_warnings`], at 1-based
+/// `line`:`col`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Warning {
+    pub line: usize,
+    pub col: usize,
+    pub msg: String,
+}
+
+/// Parse leniently for linting: problems that have an obvious recovery are
+/// returned as [`Warning`]s next to the value instead of failing the parse.
+///
+/// - A duplicate key keeps its first position and its last value.
+/// - An unknown escape such as `\q` is kept as written, backslash included.
+/// - A leading byte order mark (U+FEFF) is skipped instead of becoming part
+///   of the first key. Columns on line 1 are then counted after it.
+///
+/// Everything else is an error as in [`parse`].
 ///
 /// # Examples
 ///
 /// ```
-/// use jhon::serialize_pretty;
+/// use jhon::parse_with_warnings;
 /// use serde_json::json;
 ///
-/// let value = json!({"name": "John", "age": 30});
-/// let jhon_string = serialize_pretty(&value, "  "); // 2-space indent
-/// assert_eq!(jhon_string, "name = \"John\"\nage = 30");
+/// let (value, warnings) = parse_with_warnings("a = 1\na = 2").unwrap();
+/// assert_eq!(value, json!({"a": 2}));
+/// assert_eq!(warnings[0].line, 2);
 /// ```
-pub fn serialize_pretty(value: &Value, indent: &str) -> String {
-    serialize_pretty_with_options(
-        value,
-        &PrettyOptions {
-            indent: indent.to_string(),
-            max_inline_width: 0,
-        },
-    )
+pub fn parse_with_warnings(text: &str) -> Result<(Value, Vec<Warning>)> {
+    let warnings = RefCell::new(Vec::new());
+    let text = match text.strip_prefix('\u{feff}') {
+        Some(rest) => {
+            warnings.borrow_mut().push(Warning {
+                line: 1,
+                col: 1,
+                msg: "byte order mark skipped".to_string(),
+            });
+            rest
+        }
+        None => text,
+    };
+    let value = parse_document(text, &ParseOptions::DEFAULT, Some(&warnings))?;
+    Ok((value, warnings.into_inner()))
 }
 
-/// Options for [``serialize_pretty_with_options``].
+/// Size and cost figures for one parse, from [`parse_instrumented`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseMetrics {
+    /// Bytes of input scanned.
+    pub bytes: usize,
+    /// `key = value` entries in objects at every depth.
+    pub entries: usize,
+    /// Values in the result, containers and scalars alike (see [`node_count`]).
+    pub values: usize,
+    /// Deepest container nesting reached (see [`max_depth`]).
+    pub max_depth: usize,
+    /// Wall-clock time spent parsing, excluding the counting above.
+    pub elapsed: Duration,
+}
+
+/// [`parse`], also returning [`ParseMetrics`] for profiling config loading
+/// and spotting pathologically large documents. The counts are taken from
+/// the parsed value, so they cost a walk of the tree but nothing during the
+/// parse itself.
 ///
-/// `max_inline_width` controls short-container inlining:
-/// - `0` (default): every non-empty container renders multi-line (legacy behavior).
-/// - `>0`: a container whose single-line form fits within this many characters
-///   is emitted inline as `{ k = v, ... }` / `[ a, b, ... ]`. Containers that
-///   don't fit as a whole but whose joined children do fit use a 3-line
-///   wrapper (`[` / `    a, b, c` / `]`). Otherwise the container expands
-///   multi-line with one child per line.
-#[derive(Debug, Clone)]
-pub struct PrettyOptions {
-    pub indent: String,
-    pub max_inline_width: usize,
+/// # Examples
+///
+/// ```
+/// use jhon::parse_instrumented;
+///
+/// let (_, metrics) = parse_instrumented("a = 1, b = {c = [true]}").unwrap();
+/// assert_eq!((metrics.entries, metrics.values, metrics.max_depth), (3, 5, 3));
+/// ```
+pub fn parse_instrumented(text: &str) -> Result<(Value, ParseMetrics)> {
+    let start = std::time::Instant::now();
+    let value = parse(text)?;
+    let elapsed = start.elapsed();
+    let metrics = ParseMetrics {
+        bytes: text.len(),
+        entries: entry_count(&value),
+        values: node_count(&value),
+        max_depth: max_depth(&value),
+        elapsed,
+    };
+    Ok((value, metrics))
 }
 
-impl Default for PrettyOptions {
-    fn default() -> Self {
-        Self {
-            indent: "  ".to_string(),
-            max_inline_width: 0,
-        }
+/// Object entries in `value` at every depth.
+fn entry_count(value: &Value) -> usize {
+    match value {
+        Value::Array(arr) => arr.iter().map(entry_count).sum(),
+        Value::Object(map) => map.len() + map.values().map(entry_count).sum::<usize>(),
+        _ => 0,
     }
 }
 
-/// Pretty-print with the full [``PrettyOptions``]. See its docs for the
-/// `max_inline_width` mode.
+/// Parse a document holding exactly one value (`single_root_value`).
+fn parse_single_root(mut parser: Parser) -> Result<Value> {
+    let value = parser.parse_value()?.unwrap_or(Value::Null);
+    parser.skip_ws_and_comments();
+    if parser.current().is_some() {
+        let (line, col) = parser.line_col(parser.pos);
+        return Err(JhonError::Syntax {
+            line,
+            col,
+            msg: "expected end of input after the root value".to_string(),
+        });
+    }
+    Ok(value)
+}
+
+/// Parse an object-mode document into its top-level `(key, value)` pairs in
+/// source order, keeping duplicate keys instead of rejecting them.
 ///
-/// Both `max_inline_width == 0` and `max_inline_width > 0` route through the
-/// inline-aware path. At `0`, no container ever fits inline (lengths are
-/// always `> 0`), so every non-empty container lands in `wrapper_multi`,
-/// which produces symmetric multi-line output. The older
-/// `serialize_top_pretty` path had an asymmetric-indent bug for objects
-/// nested in arrays; routing both modes through the inline-aware path
-/// eliminates that bug without requiring a separate legacy fix.
-pub fn serialize_pretty_with_options(value: &Value, opts: &PrettyOptions) -> String {
-    let mut result = String::new();
-    serialize_pretty_inline_top(value, &opts.indent, opts.max_inline_width, &mut result);
-    result
+/// Nested objects are still built as [`Value`]s and reject duplicates as
+/// usual. Array-mode and empty documents have no pairs and return an empty
+/// list.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::parse_pairs;
+/// use serde_json::json;
+///
+/// let pairs = parse_pairs("a=1, a=2, b=3").unwrap();
+/// assert_eq!(
+///     pairs,
+///     [
+///         ("a".to_string(), json!(1)),
+///         ("a".to_string(), json!(2)),
+///         ("b".to_string(), json!(3)),
+///     ]
+/// );
+/// ```
+pub fn parse_pairs(text: &str) -> Result<Vec<(String, Value)>> {
+    let mut pairs = Vec::new();
+    if is_object_mode(text, &ParseOptions::DEFAULT) {
+        parse_jhon_pairs(Parser::new(text.as_bytes()), |_, key, value| {
+            pairs.push((key, value));
+            Ok(())
+        })?;
+    }
+    Ok(pairs)
 }
 
-/// Top-level pretty dispatch. Mirrors `serialize_top_compact`: empty
-/// containers and `null` emit empty string; top-level arrays emit bare.
-#[inline(always)]
-fn serialize_top_pretty(value: &Value, indent: &str, result: &mut String) {
-    match value {
-        Value::Array(arr) if arr.is_empty() => {}
-        Value::Array(arr) => serialize_top_array_pretty(arr, indent, result),
-        Value::Object(map) if map.is_empty() => {}
-        Value::Null => {}
-        _ => serialize_pretty_with_depth(value, indent, 0, false, result),
+/// Iterate over the top-level entries of an object-mode document as they are
+/// parsed, so each `(key, value)` can be processed and dropped before the next
+/// is read. Memory is bounded by one entry's subtree rather than the whole
+/// document.
+///
+/// Entries come in source order and, as with [`parse_pairs`], duplicate keys
+/// are yielded rather than rejected. Array-mode and empty documents yield
+/// nothing. The first error ends the iteration.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::object_entries;
+///
+/// let total: i64 = object_entries("a = {size = 2}\nb = {size = 3}")
+///     .map(|entry| entry.unwrap().1["size"].as_i64().unwrap())
+///     .sum();
+/// assert_eq!(total, 5);
+/// ```
+pub fn object_entries(text: &str) -> ObjectEntries<'_> {
+    let mut parser = Parser::new(text.as_bytes());
+    parser.skip_ws_and_comments();
+    ObjectEntries {
+        parser,
+        separator: None,
+        done: !is_object_mode(text, &ParseOptions::DEFAULT),
     }
 }
 
-/// Emit a top-level implicit array (no surrounding `[]`). Each element appears
-/// on its own line at column 0. Object and array literals as elements keep
-/// their braces/brackets since they're array elements, not the implicit
-/// top-level form.
-fn serialize_top_array_pretty(arr: &[Value], indent: &str, result: &mut String) {
-    let mut first = true;
-    for value in arr {
-        if !first {
-            result.push('\n');
-        }
-        first = false;
-        match value {
-            Value::Object(map) if map.is_empty() => result.push_str("{}"),
-            Value::Object(map) => {
-                // Object element: emit with braces, body at indent 1, no
-                // leading indent on the opening brace (column 0).
-                result.push_str("{\n");
-                let mut first_pair = true;
-                for (k, v) in map {
-                    if !first_pair {
-                        result.push('\n');
+/// Iterator returned by [`object_entries`].
+pub struct ObjectEntries<'a> {
+    parser: Parser<'a>,
+    /// `(saw_newline, saw_comma)` after the last entry, checked before the
+    /// next one so that entry is yielded even if its separator is invalid.
+    separator: Option<(bool, bool)>,
+    done: bool,
+}
+
+impl Iterator for ObjectEntries<'_> {
+    type Item = Result<(String, Value)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let parser = &mut self.parser;
+            if parser.pos >= parser.input.len() {
+                break;
+            }
+            let step = match self.separator.take() {
+                Some((saw_newline, saw_comma)) => {
+                    parser.check_item_separator(saw_newline, saw_comma)
+                }
+                None => Ok(()),
+            }
+            .and_then(|()| parser.parse_pair());
+            match step {
+                Ok((key, value)) => {
+                    self.separator = Some(parser.skip_inter_item_separator());
+                    if let Some(value) = value {
+                        return Some(Ok((key, value)));
                     }
-                    first_pair = false;
-                    result.push_str(indent);
-                    serialize_key(k, result);
-                    result.push_str(" = ");
-                    serialize_pretty_with_depth(v, indent, 1, false, result);
                 }
-                result.push('\n');
-                result.push('}');
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
             }
-            _ => serialize_pretty_with_depth(value, indent, 0, false, result),
         }
+        self.done = true;
+        None
     }
 }
 
-// =============================================================================
-// Serde Support
-// =============================================================================
-
-/// Wrapper type for serializing/deserializing arbitrary types with JHON format.
+/// Parse a document that must be in object mode and return its map.
 ///
-/// # Example
+/// An empty document gives an empty map. An array-mode document — including
+/// a lone top-level `{...}`, which SPEC.md §2 reads as an array element — is
+/// a syntax error pointing at its first element.
+///
+/// # Examples
 ///
 /// ```
-/// use jhon::Jhon;
-/// use serde::{Deserialize, Serialize};
+/// use jhon::parse_object;
 ///
-/// #[derive(Debug, Serialize, Deserialize, PartialEq)]
-/// struct Config {
-///     name: String,
-///     age: u32,
-///     active: bool,
-/// }
+/// let map = parse_object(r#"name="app", port=80"#).unwrap();
+/// assert_eq!(map["port"], 80);
+/// assert!(parse_object("{name=\"app\"}").is_err());
+/// ```
+pub fn parse_object(text: &str) -> Result<Map<String, Value>> {
+    match parse(text)? {
+        Value::Object(map) => Ok(map),
+        Value::Null => Ok(Map::new()),
+        _ => {
+            let mut parser = Parser::new(text.as_bytes());
+            parser.skip_ws_and_comments();
+            let (line, col) = parser.line_col(parser.pos);
+            Err(JhonError::Syntax {
+                line,
+                col,
+                msg: "expected key=value pairs, found an array-mode document".to_string(),
+            })
+        }
+    }
+}
+
+/// Parse exactly one JHON value — a scalar, `[...]`, or `{...}` — for callers
+/// holding a value rather than a document. Unlike [`parse`], a braced object
+/// is the value itself, not an array element, and `a=1` is not accepted.
+/// Surrounding whitespace and comments are allowed; anything else after the
+/// value, or no value at all, is a syntax error.
 ///
-/// let config = Config {
-///     name: "John".to_string(),
-///     age: 30,
-///     active: true,
-/// };
+/// # Examples
 ///
-/// // Serialize to JHON
-/// let jhon_string = Jhon::to_string(&config).unwrap();
+/// ```
+/// use jhon::parse_one_value;
+/// use serde_json::json;
 ///
-/// // Deserialize from JHON
-/// let decoded: Config = Jhon::from_str(&jhon_string).unwrap();
-/// assert_eq!(config, decoded);
+/// assert_eq!(parse_one_value("{a=1}").unwrap(), json!({"a": 1}));
+/// assert_eq!(parse_one_value(" 42 // answer").unwrap(), json!(42));
+/// assert!(parse_one_value("1 2").is_err());
 /// ```
-pub struct Jhon;
+pub fn parse_one_value(text: &str) -> Result<Value> {
+    parse_single_root(Parser::new(text.as_bytes()))
+}
 
-impl Jhon {
-    /// Serialize a type `T` to a JHON string.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if serialization fails.
-    pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
-        let value = serde_json::to_value(value)?;
-        Ok(serialize(&value))
-    }
+/// Parse `fragment` as an object-mode document (see [`parse_object`]) and
+/// deep-merge it into `base` (see [`merge`]), as if the fragment had been
+/// appended to the config `base` came from.
+///
+/// On error `base` is left untouched.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse, parse_into};
+/// use serde_json::json;
+///
+/// let mut config = parse("server={host=\"localhost\", port=80}").unwrap();
+/// parse_into(&mut config, "server={port=8080}, debug=true").unwrap();
+/// assert_eq!(
+///     config,
+///     json!({"server": {"host": "localhost", "port": 8080}, "debug": true})
+/// );
+/// ```
+pub fn parse_into(base: &mut Value, fragment: &str) -> Result<()> {
+    let overlay = parse_object(fragment)?;
+    merge(base, Value::Object(overlay));
+    Ok(())
+}
 
-    /// Serialize a type `T` to a pretty-printed JHON string.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if serialization fails.
-    pub fn to_string_pretty<T: Serialize>(value: &T, indent: &str) -> Result<String> {
-        let value = serde_json::to_value(value)?;
-        Ok(serialize_pretty(&value, indent))
-    }
+/// Parse a document of per-environment sections and return the `common`
+/// section deep-merged (see [`merge`]) with the `profile` section, so
+/// profile values override shared ones. Other sections are dropped.
+///
+/// `common` is optional. A missing `profile` section is
+/// [`JhonError::InvalidValue`], and a section that is not an object is
+/// [`JhonError::WrongType`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::parse_profile;
+/// use serde_json::json;
+///
+/// let text = r#"
+/// common = { port = 80, debug = false }
+/// dev = { debug = true }
+/// prod = { port = 443 }
+/// "#;
+/// assert_eq!(
+///     parse_profile(text, "prod").unwrap(),
+///     json!({"port": 443, "debug": false})
+/// );
+/// assert!(parse_profile(text, "staging").is_err());
+/// ```
+pub fn parse_profile(text: &str, profile: &str) -> Result<Value> {
+    let mut sections = parse_object(text)?;
+    let section = |sections: &mut Map<String, Value>, name: &str| match sections.remove(name) {
+        Some(value @ Value::Object(_)) => Ok(Some(value)),
+        Some(_) => Err(JhonError::WrongType {
+            key: name.to_string(),
+            msg: "expected an object".to_string(),
+        }),
+        None => Ok(None),
+    };
+    let overlay = section(&mut sections, profile)?.ok_or_else(|| JhonError::InvalidValue {
+        key: profile.to_string(),
+        msg: "profile not found".to_string(),
+    })?;
+    let mut merged = section(&mut sections, "common")?.unwrap_or_else(|| Value::Object(Map::new()));
+    merge(&mut merged, overlay);
+    Ok(merged)
+}
 
-    /// Deserialize a type `T` from a JHON string.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if parsing or deserialization fails.
-    #[allow(clippy::should_implement_trait)]
-    pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T> {
-        let value = parse(s)?;
-        T::deserialize(value).map_err(|e| JhonError::Serde(e.to_string()))
-    }
+/// Read a whole document from `reader` and parse it with `opts`.
+///
+/// The bytes must be UTF-8: by default the first invalid sequence is a
+/// [`JhonError::Syntax`] error, `invalid UTF-8 at byte <n>`, positioned at
+/// that byte. With [`ParseOptions::lossy_utf8`] each invalid sequence is
+/// replaced by U+FFFD instead, as [`String::from_utf8_lossy`] does. Read
+/// failures are [`JhonError::Io`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse_reader, ParseOptions};
+///
+/// let bytes: &[u8] = b"name=\"caf\xE9\"";
+/// let err = parse_reader(bytes, &ParseOptions::default()).unwrap_err();
+/// assert_eq!(err.to_string(), "parse error at 1:10: invalid UTF-8 at byte 9");
+///
+/// let lossy = ParseOptions { lossy_utf8: true, ..Default::default() };
+/// assert_eq!(parse_reader(bytes, &lossy).unwrap()["name"], "caf\u{FFFD}");
+/// ```
+pub fn parse_reader<R: std::io::Read>(mut reader: R, opts: &ParseOptions) -> Result<Value> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) if opts.lossy_utf8 => String::from_utf8_lossy(e.as_bytes()).into_owned(),
+        Err(e) => {
+            let at = e.utf8_error().valid_up_to();
+            let (line, col) = Parser::new(e.as_bytes()).line_col(at);
+            return Err(JhonError::Syntax {
+                line,
+                col,
+                msg: format!("invalid UTF-8 at byte {}", at),
+            });
+        }
+    };
+    parse_with_options(&text, opts)
+}
 
-    /// Deserialize a type `T` from a JHON string with a custom deserializer.
-    ///
-    /// This allows for more control over the deserialization process.
-    pub fn from_str_with_deserializer<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
-        deserializer: D,
-    ) -> Result<T> {
-        T::deserialize(deserializer).map_err(|e| JhonError::Serde(e.to_string()))
+/// Mode detection (SPEC.md §2): the first top-level element decides whether
+/// the document is parsed as an object (key=value pairs) or as an implicit
+/// array (bare values). `{...}` and `[...]` always begin array mode since
+/// they cannot start a `key=` pair. For anything else, attempt to parse a
+/// key and look ahead for `=` (or, with `indent_blocks`, an indented block).
+fn is_object_mode(input: &str, opts: &ParseOptions) -> bool {
+    // Probe without `require_quoted_keys`: it rejects keys later with a
+    // precise error, it doesn't change the mode.
+    let probe_opts = ParseOptions {
+        require_quoted_keys: false,
+        ..*opts
+    };
+    let mut detector = Parser::with_options(input.as_bytes(), &probe_opts);
+    detector.skip_ws_and_comments();
+    match detector.current() {
+        None | Some(b'{') | Some(b'[') => false,
+        Some(_) => {
+            let mut probe = detector;
+            match probe.parse_key() {
+                Ok(_) => {
+                    if opts.indent_blocks && probe.at_line_end() {
+                        return true;
+                    }
+                    if opts.bare_keys_are_true && probe.at_bare_flag() {
+                        let mut value_probe = detector;
+                        return value_probe.parse_value().is_err();
+                    }
+                    probe.skip_ws_and_comments();
+                    probe
+                        .current()
+                        .is_some_and(|b| opts.key_value_separator.accepts(b))
+                }
+                Err(_) => false,
+            }
+        }
     }
 }
 
-/// A wrapper that can be used with `#[serde(with = "jhon")]` to serialize/deserialize
-/// fields using JHON format.
+/// Serialize a JSON Value into a compact JHON string
 ///
-/// # Example
+/// # Examples
 ///
 /// ```
-/// use jhon::jhon;
-/// use serde::{Deserialize, Serialize};
+/// use jhon::serialize;
+/// use serde_json::json;
 ///
-/// #[derive(Debug, Serialize, Deserialize)]
-/// struct ConfigData {
-///     name: String,
-///     value: i32,
-/// }
+/// let value = json!({"name": "John", "age": 30});
+/// let jhon_string = serialize(&value);
+/// assert_eq!(jhon_string, r#"name="John",age=30"#);
+/// ```
+#[inline]
+pub fn serialize(value: &Value) -> String {
+    serialize_with(value, &SerializeOptions::DEFAULT)
+}
+
+/// Serialize a single `key=value` fragment, quoting and escaping both sides
+/// exactly as [`serialize`] would, for building JHON text incrementally
+/// (e.g. appending entries to a file). A nested object is written braced.
+///
+/// # Examples
 ///
-/// #[derive(Debug, Serialize, Deserialize)]
-/// struct MyStruct {
-///     #[serde(with = "jhon")]
-///     config: ConfigData,
-/// }
 /// ```
-pub mod jhon {
-    use super::*;
+/// use jhon::entry;
+/// use serde_json::json;
+///
+/// assert_eq!(entry("my key", &json!("a\"b")), r#""my key"="a\"b""#);
+/// assert_eq!(entry("db", &json!({"port": 5432})), "db={port=5432}");
+/// ```
+pub fn entry(key: &str, value: &Value) -> String {
+    let mut result = String::new();
+    serialize_key(key, &mut result);
+    result.push('=');
+    match value {
+        Value::Object(map) if map.is_empty() => result.push_str("{}"),
+        Value::Object(map) => {
+            result.push('{');
+            serialize_object_compact(map, &SerializeOptions::DEFAULT, &mut result);
+            result.push('}');
+        }
+        _ => serialize_compact(value, &SerializeOptions::DEFAULT, &mut result),
+    }
+    result
+}
 
-    pub fn serialize<T: Serialize, S: Serializer>(
-        value: &T,
-        serializer: S,
-    ) -> std::result::Result<S::Ok, S::Error> {
-        let jhon_string = Jhon::to_string(value).map_err(serde::ser::Error::custom)?;
-        jhon_string.serialize(serializer)
+/// Top-level dispatch. Per SPEC.md §2: empty containers and `null` serialize
+/// to empty string (the "Empty" form); top-level arrays emit bare (no `[]`).
+/// Nested values fall through to `serialize_compact` which preserves `[]` for
+/// nested arrays and `null` text for nested nulls.
+#[inline(always)]
+fn serialize_top_compact(value: &Value, opts: &SerializeOptions, result: &mut String) {
+    match value {
+        Value::Array(arr) if arr.is_empty() => {}
+        Value::Array(arr) => serialize_array_contents_compact(arr, opts, result),
+        Value::Object(map) if map.is_empty() => {}
+        Value::Object(map) if is_flat_string_map(map, opts) => serialize_flat_strings(map, result),
+        Value::Null => {}
+        _ => serialize_compact(value, opts, result),
     }
+}
 
-    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
-        deserializer: D,
-    ) -> std::result::Result<T, D::Error> {
-        // First deserialize to a Value (serde_json::Value), then convert to target type
-        let json_value = serde_json::Value::deserialize(deserializer)?;
-        // Convert the JSON Value to our target type
-        T::deserialize(json_value).map_err(de::Error::custom)
+/// Whether `map` can take the [`serialize_flat_strings`] fast path: every
+/// value is a string and no option changes how strings are written.
+fn is_flat_string_map(map: &Map<String, Value>, opts: &SerializeOptions) -> bool {
+    !opts.bareword_strings && opts.escape_js_unsafe && map.values().all(Value::is_string)
+}
+
+/// Fast path for a root object whose values are all strings (translation
+/// tables, env maps): the output is reserved once up front and each value
+/// goes straight to `serialize_string`, with no per-value dispatch. Same
+/// output as `serialize_object_compact`.
+fn serialize_flat_strings(map: &Map<String, Value>, result: &mut String) {
+    let len: usize = map
+        .iter()
+        .map(|(k, v)| k.len() + v.as_str().map_or(0, str::len) + 4)
+        .sum();
+    result.reserve(len);
+    for (i, (key, value)) in map.iter().enumerate() {
+        if i > 0 {
+            result.push(',');
+        }
+        serialize_key(key, result);
+        result.push('=');
+        if let Value::String(s) = value {
+            serialize_string(s, result);
+        }
     }
 }
 
-/// Deserialize a JHON string into any type that implements `Deserialize`.
+/// Serialize into compact JHON with a fixed layout, for reproducible output
+/// (diffable generated files, hashing, snapshot tests).
 ///
-/// This is a convenience function that uses the `Jhon` wrapper internally.
+/// The contract: object keys are sorted ascending by Unicode scalar value,
+/// at every depth, and array elements stay in their original order. Apart
+/// from key order the output is exactly [`serialize`]'s, which instead keeps
+/// keys in insertion order.
 ///
-/// # Example
+/// # Examples
 ///
 /// ```
-/// use jhon::from_str;
-/// use serde::{Deserialize, Serialize};
-///
-/// #[derive(Debug, Deserialize, PartialEq)]
-/// struct Config {
-///     name: String,
-///     age: u32,
-/// }
+/// use jhon::serialize_stable;
+/// use serde_json::json;
 ///
-/// let jhon_str = r#"name="John",age=30"#;
-/// let config: Config = from_str(jhon_str).unwrap();
-/// assert_eq!(config.name, "John");
-/// assert_eq!(config.age, 30);
+/// let value = json!({"b": [3, 1, 2], "a": {"z": 1, "y": 2}});
+/// assert_eq!(serialize_stable(&value), "a={y=2,z=1},b=[3,1,2]");
 /// ```
-pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T> {
-    Jhon::from_str(s)
+pub fn serialize_stable(value: &Value) -> String {
+    serialize(&with_sorted_keys(value))
 }
 
-/// Serialize any type that implements `Serialize` into a JHON string.
+/// Parse JHON text and emit compact JSON with object keys sorted at every
+/// depth, for hashing or cache keys: documents that parse to equal values
+/// give byte-identical output regardless of key order.
 ///
-/// This is a convenience function that uses the `Jhon` wrapper internally.
+/// # Examples
 ///
-/// # Example
+/// ```
+/// use jhon::to_sorted_json;
 ///
+/// assert_eq!(
+///     to_sorted_json("name=\"app\", db={port=1, host=\"h\"}").unwrap(),
+///     r#"{"db":{"host":"h","port":1},"name":"app"}"#
+/// );
 /// ```
-/// use jhon::to_string;
-/// use serde::{Deserialize, Serialize};
+pub fn to_sorted_json(jhon_text: &str) -> Result<String> {
+    Ok(with_sorted_keys(&parse(jhon_text)?).to_string())
+}
+
+/// Rewrite `value` in place into a canonical form, as a preprocessing step
+/// before diffing or hashing: object keys are sorted ascending at every
+/// depth, and floats with no fractional part that fit in an integer become
+/// integers, so `30.0` and `30` compare equal. Array order is kept.
 ///
-/// #[derive(Debug, Serialize, Deserialize, PartialEq)]
-/// struct Config {
-///     name: String,
-///     age: u32,
-/// }
+/// # Examples
 ///
-/// let config = Config { name: "John".to_string(), age: 30 };
-/// let jhon_str = to_string(&config).unwrap();
 /// ```
-pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
-    Jhon::to_string(value)
+/// use jhon::{normalize, parse};
+///
+/// let mut a = parse("timeout = 30.0, name = \"app\"").unwrap();
+/// let mut b = parse("name = \"app\", timeout = 30").unwrap();
+/// normalize(&mut a);
+/// normalize(&mut b);
+/// assert_eq!(a, b);
+/// ```
+pub fn normalize(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.sort_keys();
+            map.values_mut().for_each(normalize);
+        }
+        Value::Array(arr) => arr.iter_mut().for_each(normalize),
+        Value::Number(n) => {
+            if let Some(f) = n.as_f64().filter(|f| n.is_f64() && f.fract() == 0.0) {
+                if f >= i64::MIN as f64 && f < i64::MAX as f64 {
+                    *n = Number::from(f as i64);
+                } else if f >= 0.0 && f < u64::MAX as f64 {
+                    *n = Number::from(f as u64);
+                }
+            }
+        }
+        _ => {}
+    }
 }
 
-/// Serialize any type that implements `Serialize` into a pretty-printed JHON string.
+/// A 64-bit hash of `value`'s content for caching and change detection.
 ///
-/// This is a convenience function that uses the `Jhon` wrapper internally.
-pub fn to_string_pretty<T: Serialize>(value: &T, indent: &str) -> Result<String> {
-    Jhon::to_string_pretty(value, indent)
+/// The hash covers the [`normalize`]d value as written by
+/// [`serialize_stable`], so equal configs hash identically regardless of key
+/// order or of `30` versus `30.0`. It is FNV-1a, which is fixed across
+/// platforms, builds and Rust releases, so hashes can be stored; it is not a
+/// cryptographic hash.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{content_hash, parse};
+///
+/// let a = parse("port = 8080, host = \"h\"").unwrap();
+/// let b = parse("host = \"h\", port = 8080.0").unwrap();
+/// assert_eq!(content_hash(&a), content_hash(&b));
+/// ```
+pub fn content_hash(value: &Value) -> u64 {
+    let mut canonical = value.clone();
+    normalize(&mut canonical);
+    serialize_stable(&canonical)
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, b| {
+            (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+        })
 }
 
-// =============================================================================
-// Static Tables (from serde_json)
-// =============================================================================
-
-const BB: u8 = b'b'; // \\x08
-const TT: u8 = b't'; // \\x09
-const NN: u8 = b'n'; // \\x0A
-const FF: u8 = b'f'; // \\x0C
-const RR: u8 = b'r'; // \\x0D
-const QU: u8 = b'"'; // \\x22
-const BS: u8 = b'\\'; // \\x5C
-const UU: u8 = b'u'; // \\x00...\\x1F except the ones above
-const __: u8 = 0; // No escape needed
+/// A copy of `value` whose objects, at every depth, have their keys in
+/// ascending order.
+fn with_sorted_keys(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<(&String, &Value)> = map.iter().collect();
+            entries.sort_unstable_by_key(|&(k, _)| k);
+            Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.clone(), with_sorted_keys(v)))
+                    .collect(),
+            )
+        }
+        Value::Array(arr) => Value::Array(arr.iter().map(with_sorted_keys).collect()),
+        _ => value.clone(),
+    }
+}
 
-// Lookup table of escape sequences. A value of b'x' at index i means that byte
-// i is escaped as "\x" in JSON. A value of 0 means that byte i is not escaped.
-static ESCAPE: [u8; 256] = [
-    //   1   2   3   4   5   6   7   8   9   A   B   C   D   E   F
-    UU, UU, UU, UU, UU, UU, UU, UU, BB, TT, NN, UU, FF, RR, UU, UU, // 0
-    UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, // 1
-    __, __, QU, __, __, __, __, __, __, __, __, __, __, __, __, __, // 2
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 3
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 4
-    __, __, __, __, __, __, __, __, __, __, __, __, BS, __, __, __, // 5
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 6
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 7
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 8
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 9
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // A
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // B
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // C
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // D
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // E
-    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // F
-];
-
-// Classification table for fast character checking
-// (removed during 2.0 rewrite — correctness over micro-optimization)
-
-// =============================================================================
-// Optimized Parser
-// =============================================================================
+/// Serialize a JSON Value into a pretty-printed JHON string with custom indentation
+///
+/// # Examples
+///
+/// ```
+/// use jhon::serialize_pretty;
+/// use serde_json::json;
+///
+/// let value = json!({"name": "John", "age": 30});
+/// let jhon_string = serialize_pretty(&value, "  "); // 2-space indent
+/// assert_eq!(jhon_string, "name = \"John\"\nage = 30");
+/// ```
+pub fn serialize_pretty(value: &Value, indent: &str) -> String {
+    serialize_pretty_with_options(
+        value,
+        &PrettyOptions {
+            indent: indent.to_string(),
+            ..Default::default()
+        },
+    )
+}
 
-#[derive(Clone, Copy)]
-struct Parser<'a> {
-    input: &'a [u8],
-    pos: usize,
-    line: usize, // 1-based
-    col: usize,  // 1-based
+/// Options for [``serialize_pretty_with_options``].
+///
+/// `max_inline_width` controls short-container inlining:
+/// - `0` (default): every non-empty container renders multi-line (legacy behavior).
+/// - `>0`: a container whose single-line form fits within this many characters
+///   is emitted inline as `{ k = v, ... }` / `[ a, b, ... ]`. Containers that
+///   don't fit as a whole but whose joined children do fit use a 3-line
+///   wrapper (`[` / `    a, b, c` / `]`). Otherwise the container expands
+///   multi-line with one child per line.
+///
+/// `wrap_strings_at` splits string values longer than this many characters
+/// (measured on the escaped form) across lines with `\`-newline continuations,
+/// breaking after a space where possible. `None` (default) never wraps.
+/// Strings inside inline containers are left whole.
+#[derive(Debug, Clone)]
+pub struct PrettyOptions {
+    pub indent: String,
+    pub max_inline_width: usize,
+    pub wrap_strings_at: Option<usize>,
 }
 
-impl<'a> Parser<'a> {
-    fn new(input: &'a [u8]) -> Self {
+impl Default for PrettyOptions {
+    fn default() -> Self {
         Self {
-            input,
-            pos: 0,
-            line: 1,
-            col: 1,
+            indent: "  ".to_string(),
+            max_inline_width: 0,
+            wrap_strings_at: None,
         }
     }
+}
 
-    fn current(&self) -> Option<u8> {
-        self.input.get(self.pos).copied()
+/// Pretty-print with the full [``PrettyOptions``]. See its docs for the
+/// `max_inline_width` mode.
+///
+/// Both `max_inline_width == 0` and `max_inline_width > 0` route through the
+/// inline-aware path. At `0`, no container ever fits inline (lengths are
+/// always `> 0`), so every non-empty container lands in `wrapper_multi`,
+/// which produces symmetric multi-line output. The older depth-based
+/// pretty printer had an asymmetric-indent bug for objects nested in arrays;
+/// routing both modes through the inline-aware path eliminated that bug and
+/// the old path has since been removed.
+pub fn serialize_pretty_with_options(value: &Value, opts: &PrettyOptions) -> String {
+    serialize_with(
+        value,
+        &SerializeOptions {
+            pretty: Some(opts.clone()),
+            ..SerializeOptions::DEFAULT
+        },
+    )
+}
+
+/// Options for [`serialize_with`], covering both layouts.
+///
+/// - `pretty`: `None` (default) emits compact output like [`serialize`];
+///   `Some` emits multi-line output like [`serialize_pretty_with_options`].
+/// - `digit_grouping`: write integers with `_` every three digits
+///   (`1_048_576`), which parses back to the same number (SPEC.md §3.5).
+/// - `exponential_threshold`: with `Some(t)`, floats whose magnitude is at
+///   least `t` or below `1 / t` are written in `e` notation (`1e-9` rather
+///   than `0.000000001`). Integers are always written in full.
+/// - `compact_nested`: in pretty mode, keep each top-level entry on its own
+///   line but write nested objects and arrays on one line, in the inline
+///   form `{ k = v, ... }` / `[ a, b ]`, however long. No effect on compact
+///   output.
+/// - `wrap_root_in_braces`: write a root object as one braced value,
+///   `{a=1}` (an empty one as `{}`), for embedding it where a value is
+///   expected. Under SPEC.md §2 a top-level `{...}` is an array element, so
+///   read such output back with [`ParseOptions::single_root_value`]; plain
+///   [`parse`] gives a one-element array. Other roots are unaffected.
+/// - `array_expand_threshold`: with `n > 0`, in pretty mode, a nested array
+///   of at most `n` elements is written inline (`[ 1, 2, 3 ]`) and a longer
+///   one with one element per line, whatever the elements are. For arrays
+///   this overrides `PrettyOptions::max_inline_width`; `compact_nested`
+///   still keeps everything under a top-level entry on one line. `0`
+///   (default) leaves array layout to the width rules.
+/// - `dotted_keys`: flatten a root object's nested objects into dotted
+///   top-level entries, `server.host="h"`, the form read back by
+///   [`ParseOptions::dotted_keys_to_nested`]. Arrays, and objects inside
+///   them, stay inline values; empty objects are written as `a.b={}`. Keys
+///   that themselves contain `.` do not round-trip.
+/// - `bareword_strings`: write string values unquoted where
+///   [`ParseOptions::bareword_values`] reads them back as the same string
+///   (`root=/usr/local`). Strings spelling a keyword or looking like a number
+///   (`"true"`, `"null"`, `"123"`, `"-x"`), and any holding whitespace,
+///   quotes, brackets, separators, comment openers or control characters,
+///   stay quoted. Keys are unaffected.
+/// - `group_blank_lines`: in pretty mode, put a blank line between
+///   top-level entries whose key prefix, the part before the first `_` or
+///   `.`, differs, so `db_host` and `db_port` sit together apart from
+///   `server_host`. A key without either character is its own prefix.
+///   Nested objects and compact output are unaffected.
+/// - `escape_js_unsafe`: write U+2028 and U+2029 in string values as
+///   `\u2028` / `\u2029`. Both are valid raw in JHON and JSON but end a line
+///   in JavaScript source, so the escapes keep output safe to embed in a
+///   script. On by default; turn it off to keep them as literal characters.
+///   Keys always escape them.
+#[derive(Debug, Clone)]
+pub struct SerializeOptions {
+    pub pretty: Option<PrettyOptions>,
+    pub digit_grouping: bool,
+    pub exponential_threshold: Option<f64>,
+    pub compact_nested: bool,
+    pub wrap_root_in_braces: bool,
+    pub array_expand_threshold: usize,
+    pub dotted_keys: bool,
+    pub bareword_strings: bool,
+    pub group_blank_lines: bool,
+    pub escape_js_unsafe: bool,
+}
+
+impl SerializeOptions {
+    const DEFAULT: Self = Self {
+        pretty: None,
+        digit_grouping: false,
+        exponential_threshold: None,
+        compact_nested: false,
+        wrap_root_in_braces: false,
+        array_expand_threshold: 0,
+        dotted_keys: false,
+        bareword_strings: false,
+        group_blank_lines: false,
+        escape_js_unsafe: true,
+    };
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// Serialize with the full [`SerializeOptions`]. [`serialize`] and
+/// [`serialize_pretty_with_options`] are this with the default options and
+/// with only `pretty` set, respectively.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{serialize_with, SerializeOptions};
+/// use serde_json::json;
+///
+/// let opts = SerializeOptions {
+///     digit_grouping: true,
+///     ..Default::default()
+/// };
+/// assert_eq!(serialize_with(&json!({"size": 1048576}), &opts), "size=1_048_576");
+/// ```
+pub fn serialize_with(value: &Value, opts: &SerializeOptions) -> String {
+    if opts.dotted_keys
+        && let Value::Object(map) = value
+    {
+        let mut flat = Map::new();
+        flatten_dotted(map, "", &mut flat);
+        let opts = SerializeOptions {
+            dotted_keys: false,
+            ..opts.clone()
+        };
+        return serialize_with(&Value::Object(flat), &opts);
+    }
+    let mut result = String::new();
+    let wrap = opts.wrap_root_in_braces && value.is_object();
+    match &opts.pretty {
+        None if wrap => {
+            result.push('{');
+            serialize_compact(value, opts, &mut result);
+            result.push('}');
+        }
+        None => serialize_top_compact(value, opts, &mut result),
+        Some(pretty) if wrap => {
+            render_pretty_inline(value, opts, pretty, 0, &mut Walk::default(), &mut result)
+        }
+        Some(pretty) => {
+            serialize_pretty_inline_top(value, opts, pretty, &mut Walk::default(), &mut result)
+        }
     }
+    result
+}
 
-    fn advance(&mut self) -> Option<u8> {
-        let c = self.current()?;
-        if c == b'\n' {
-            self.line += 1;
-            self.col = 1;
+/// Copy `map`'s entries into `flat`, descending into non-empty objects and
+/// joining their keys onto `prefix` with `.`.
+fn flatten_dotted(map: &Map<String, Value>, prefix: &str, flat: &mut Map<String, Value>) {
+    for (key, value) in map {
+        let path = if prefix.is_empty() {
+            key.clone()
         } else {
-            self.col += 1;
+            format!("{}.{}", prefix, key)
+        };
+        match value {
+            Value::Object(inner) if !inner.is_empty() => flatten_dotted(inner, &path, flat),
+            _ => {
+                flat.insert(path, value.clone());
+            }
         }
-        self.pos += 1;
-        Some(c)
     }
+}
 
-    /// Skip whitespace and comments. Returns whether a newline was consumed.
-    /// Comments are stripped inline so that line information survives for
-    /// separator-rule enforcement (SPEC.md §5.3).
-    fn skip_ws_and_comments(&mut self) -> bool {
-        let mut saw_newline = false;
-        loop {
-            match self.current() {
-                Some(b' ') | Some(b'\t') | Some(b'\r') => {
-                    self.advance();
-                }
-                Some(b'\n') => {
-                    saw_newline = true;
-                    self.advance();
-                }
-                Some(b'/') if self.input.get(self.pos + 1) == Some(&b'/') => {
-                    // Line comment — skip to but not past '\n' so the outer
-                    // loop records the newline.
-                    self.advance();
-                    self.advance();
-                    while let Some(c) = self.current() {
-                        if c == b'\n' {
-                            break;
-                        }
-                        self.advance();
-                    }
+/// Pretty-print for previewing large documents: containers nested more than
+/// `max_depth` levels below the root are elided as `{…}` / `[…]`.
+///
+/// The root's own entries are at depth 0, so `max_depth = 0` shows only the
+/// top-level keys. Empty containers are never elided. The output is meant for
+/// display and does not parse back when anything was elided.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::serialize_preview;
+/// use serde_json::json;
+///
+/// let value = json!({"a": {"b": {"c": 1}}, "n": 1});
+/// assert_eq!(serialize_preview(&value, 1, "  "), "a = {\n  b = {…}\n}\nn = 1");
+/// ```
+pub fn serialize_preview(value: &Value, max_depth: usize, indent: &str) -> String {
+    let pretty = PrettyOptions {
+        indent: indent.to_string(),
+        ..Default::default()
+    };
+    let mut walk = Walk {
+        max_depth: Some(max_depth),
+        ..Default::default()
+    };
+    let mut result = String::new();
+    serialize_pretty_inline_top(
+        value,
+        &SerializeOptions::DEFAULT,
+        &pretty,
+        &mut walk,
+        &mut result,
+    );
+    result
+}
+
+/// Pretty-print the way configs are usually written by hand: the top-level
+/// scalar entries (and empty containers) share one line, comma-separated,
+/// followed by each nested object or array as its own expanded block.
+///
+/// Scalars are moved ahead of blocks; within each group the original key
+/// order is kept. Documents that are not objects fall back to
+/// [`serialize_pretty`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::serialize_grouped;
+/// use serde_json::json;
+///
+/// let value = json!({"name": "app", "db": {"port": 5432}, "debug": false});
+/// assert_eq!(
+///     serialize_grouped(&value, "  "),
+///     "name = \"app\", debug = false\ndb = {\n  port = 5432\n}"
+/// );
+/// ```
+pub fn serialize_grouped(value: &Value, indent: &str) -> String {
+    let Value::Object(map) = value else {
+        return serialize_pretty(value, indent);
+    };
+    let is_block = |v: &Value| match v {
+        Value::Object(m) => !m.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        _ => false,
+    };
+    let pretty = PrettyOptions {
+        indent: indent.to_string(),
+        ..Default::default()
+    };
+
+    let mut result = String::new();
+    for (i, (k, v)) in map.iter().filter(|(_, v)| !is_block(v)).enumerate() {
+        if i > 0 {
+            result.push_str(", ");
+        }
+        serialize_key(k, &mut result);
+        result.push_str(" = ");
+        let mut walk = Walk::default();
+        render_pretty_inline(
+            v,
+            &SerializeOptions::DEFAULT,
+            &pretty,
+            0,
+            &mut walk,
+            &mut result,
+        );
+    }
+    for (k, v) in map.iter().filter(|(_, v)| is_block(v)) {
+        if !result.is_empty() {
+            result.push('\n');
+        }
+        serialize_key(k, &mut result);
+        result.push_str(" = ");
+        let mut walk = Walk::default();
+        render_pretty_inline(
+            v,
+            &SerializeOptions::DEFAULT,
+            &pretty,
+            0,
+            &mut walk,
+            &mut result,
+        );
+    }
+    result
+}
+
+/// Serialize compactly like [`serialize`], but give up with
+/// [`JhonError::OutputTooLarge`] as soon as the output passes `max_bytes`.
+///
+/// The budget is checked after every container element, so at most one
+/// scalar beyond the limit is ever written before bailing out.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{serialize_bounded, JhonError};
+/// use serde_json::json;
+///
+/// assert_eq!(serialize_bounded(&json!({"a": 1}), 16).unwrap(), "a=1");
+/// assert!(matches!(
+///     serialize_bounded(&json!({"a": [1, 2, 3, 4, 5, 6, 7, 8]}), 8),
+///     Err(JhonError::OutputTooLarge { limit: 8 })
+/// ));
+/// ```
+pub fn serialize_bounded(value: &Value, max_bytes: usize) -> Result<String> {
+    let mut result = String::new();
+    let opts = SerializeOptions::DEFAULT;
+    let mut check = |result: &mut String| check_output_budget(result, max_bytes);
+    let mut walk = Walk {
+        after_value: Some(&mut check),
+        ..Default::default()
+    };
+    match value {
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    result.push(',');
                 }
-                Some(b'/') if self.input.get(self.pos + 1) == Some(&b'*') => {
-                    // Block comment — skip to matching '*/'. Newlines inside
-                    // count toward the separator rule.
-                    self.advance();
-                    self.advance();
-                    loop {
-                        match self.current() {
-                            None => return saw_newline,
-                            Some(b'*') if self.input.get(self.pos + 1) == Some(&b'/') => {
-                                self.advance();
-                                self.advance();
-                                break;
-                            }
-                            Some(b'\n') => {
-                                saw_newline = true;
-                                self.advance();
-                            }
-                            Some(_) => {
-                                self.advance();
-                            }
-                        }
-                    }
+                serialize_value_bounded(v, &opts, &mut walk, &mut result);
+                walk.after_value(&mut result);
+            }
+        }
+        Value::Object(map) => {
+            for (k, v) in map {
+                if !result.is_empty() {
+                    result.push(',');
                 }
-                _ => break,
+                serialize_key(k, &mut result);
+                result.push('=');
+                serialize_value_bounded(v, &opts, &mut walk, &mut result);
+                walk.after_value(&mut result);
             }
         }
-        saw_newline
+        Value::Null => {}
+        _ => serialize_compact(value, &opts, &mut result),
+    }
+    if let Some(e) = walk.error {
+        return Err(e);
     }
+    check_output_budget(&result, max_bytes)?;
+    Ok(result)
+}
 
-    /// Skip the separator between two consecutive items in a container.
-    /// Returns `(saw_newline, saw_comma)`. Per SPEC.md §5.3, an item following
-    /// another on the same physical line must be preceded by a comma; a
-    /// newline is also a valid separator.
-    fn skip_inter_item_separator(&mut self) -> (bool, bool) {
-        let mut saw_newline = self.skip_ws_and_comments();
-        let mut saw_comma = false;
-        if self.current() == Some(b',') {
-            saw_comma = true;
-            self.advance();
-            if self.skip_ws_and_comments() {
-                saw_newline = true;
-            }
+/// Compact rendering for [`serialize_bounded`], with `walk` checking the
+/// budget after every container element.
+fn serialize_value_bounded(
+    value: &Value,
+    opts: &SerializeOptions,
+    walk: &mut Walk,
+    result: &mut String,
+) {
+    match value {
+        Value::Array(_) | Value::Object(_) => {
+            walk_container(value, None, 0, walk, result, &mut |v, walk, result| {
+                serialize_value_bounded(v, opts, walk, result)
+            })
         }
-        (saw_newline, saw_comma)
+        _ => serialize_compact(value, opts, result),
     }
+}
 
-    // String parsing. Phase 1 scans byte-by-byte for the closing quote while
-    // rejecting literal C0/DEL control chars; if no escape is seen the slice
-    // is returned directly. Phase 2 processes escapes, accumulating into a
-    // Vec<u8> so non-ASCII content after an escape stays UTF-8-correct.
-    fn parse_string(&mut self, quote: u8) -> Result<String> {
-        self.advance(); // skip opening quote
+fn check_output_budget(result: &str, max_bytes: usize) -> Result<()> {
+    if result.len() > max_bytes {
+        return Err(JhonError::OutputTooLarge { limit: max_bytes });
+    }
+    Ok(())
+}
 
-        let start = self.pos;
+/// Bytes buffered by [`serialize_pretty_writer`] before a write is issued.
+const WRITER_CHUNK: usize = 8 * 1024;
 
-        // Phase 1: scan for closing quote, backslash, or forbidden control byte.
-        while self.pos < self.input.len() {
-            let b = self.input[self.pos];
-            if b < 0x20 || b == 0x7F {
-                return Err(syntax_err!(
-                    "literal control character 0x{:02X} in string; use an escape or a raw string",
+/// Write the same text as [`serialize_pretty`] to `w` without building it in
+/// memory first.
+///
+/// Output goes out in chunks of about 8 KiB, and `w` is flushed after each
+/// top-level entry, so a slow sink sees steady progress. Write errors are
+/// returned as [`JhonError::Io`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{serialize_pretty, serialize_pretty_writer};
+/// use serde_json::json;
+///
+/// let value = json!({"name": "app", "db": {"port": 5432}});
+/// let mut out = Vec::new();
+/// serialize_pretty_writer(&value, "  ", &mut out).unwrap();
+/// assert_eq!(out, serialize_pretty(&value, "  ").into_bytes());
+/// ```
+pub fn serialize_pretty_writer<W: std::io::Write>(
+    value: &Value,
+    indent: &str,
+    mut w: W,
+) -> Result<()> {
+    let pretty = PrettyOptions {
+        indent: indent.to_string(),
+        ..Default::default()
+    };
+    // Render one top-level item, handing `buf` to `w` whenever it grows past
+    // `WRITER_CHUNK`, then write out the rest and flush.
+    let mut write_item = |v: &Value, buf: &mut String| -> Result<()> {
+        let mut drain = |buf: &mut String| {
+            if buf.len() >= WRITER_CHUNK {
+                drain_to_writer(buf, &mut w)?;
+            }
+            Ok(())
+        };
+        let mut walk = Walk {
+            after_value: Some(&mut drain),
+            ..Default::default()
+        };
+        render_pretty_inline(v, &SerializeOptions::DEFAULT, &pretty, 0, &mut walk, buf);
+        if let Some(e) = walk.error {
+            return Err(e);
+        }
+        drain_to_writer(buf, &mut w)?;
+        w.flush()?;
+        Ok(())
+    };
+    let mut buf = String::new();
+    match value {
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                if i > 0 {
+                    buf.push('\n');
+                }
+                write_item(v, &mut buf)?;
+            }
+        }
+        Value::Object(map) => {
+            for (i, (k, v)) in map.iter().enumerate() {
+                if i > 0 {
+                    buf.push('\n');
+                }
+                serialize_key(k, &mut buf);
+                buf.push_str(" = ");
+                write_item(v, &mut buf)?;
+            }
+        }
+        Value::Null => {}
+        _ => write_item(value, &mut buf)?,
+    }
+    Ok(())
+}
+
+fn drain_to_writer<W: std::io::Write>(buf: &mut String, w: &mut W) -> Result<()> {
+    w.write_all(buf.as_bytes())?;
+    buf.clear();
+    Ok(())
+}
+
+// =============================================================================
+// Serde Support
+// =============================================================================
+
+/// Wrapper type for serializing/deserializing arbitrary types with JHON format.
+///
+/// # Example
+///
+/// ```
+/// use jhon::Jhon;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// struct Config {
+///     name: String,
+///     age: u32,
+///     active: bool,
+/// }
+///
+/// let config = Config {
+///     name: "John".to_string(),
+///     age: 30,
+///     active: true,
+/// };
+///
+/// // Serialize to JHON
+/// let jhon_string = Jhon::to_string(&config).unwrap();
+///
+/// // Deserialize from JHON
+/// let decoded: Config = Jhon::from_str(&jhon_string).unwrap();
+/// assert_eq!(config, decoded);
+/// ```
+pub struct Jhon;
+
+impl Jhon {
+    /// Serialize a type `T` to a JHON string.
+    ///
+    /// Maps become objects. Their keys follow serde_json's rules: strings
+    /// and unit enum variants are used as-is; integers, finite floats, bools
+    /// and chars are stringified; any other key type is an error.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
+        let value = serde_json::to_value(value)?;
+        Ok(serialize(&value))
+    }
+
+    /// Serialize a type `T` to a pretty-printed JHON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if serialization fails.
+    pub fn to_string_pretty<T: Serialize>(value: &T, indent: &str) -> Result<String> {
+        let value = serde_json::to_value(value)?;
+        Ok(serialize_pretty(&value, indent))
+    }
+
+    /// Deserialize a type `T` from a JHON string.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if parsing or deserialization fails.
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T> {
+        let value = parse(s)?;
+        T::deserialize(value).map_err(|e| JhonError::Serde(e.to_string()))
+    }
+
+    /// Deserialize a type `T` from a JHON string with a custom deserializer.
+    ///
+    /// This allows for more control over the deserialization process.
+    pub fn from_str_with_deserializer<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<T> {
+        T::deserialize(deserializer).map_err(|e| JhonError::Serde(e.to_string()))
+    }
+}
+
+/// A wrapper that can be used with `#[serde(with = "jhon")]` to serialize/deserialize
+/// fields using JHON format.
+///
+/// # Example
+///
+/// ```
+/// use jhon::jhon;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct ConfigData {
+///     name: String,
+///     value: i32,
+/// }
+///
+/// #[derive(Debug, Serialize, Deserialize)]
+/// struct MyStruct {
+///     #[serde(with = "jhon")]
+///     config: ConfigData,
+/// }
+/// ```
+pub mod jhon {
+    use super::*;
+
+    pub fn serialize<T: Serialize, S: Serializer>(
+        value: &T,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        let jhon_string = Jhon::to_string(value).map_err(serde::ser::Error::custom)?;
+        jhon_string.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<T, D::Error> {
+        // First deserialize to a Value (serde_json::Value), then convert to target type
+        let json_value = serde_json::Value::deserialize(deserializer)?;
+        // Convert the JSON Value to our target type
+        T::deserialize(json_value).map_err(de::Error::custom)
+    }
+}
+
+/// Deserialize a JHON string into any type that implements `Deserialize`.
+///
+/// This is a convenience function that uses the `Jhon` wrapper internally.
+///
+/// # Example
+///
+/// ```
+/// use jhon::from_str;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Config {
+///     name: String,
+///     age: u32,
+/// }
+///
+/// let jhon_str = r#"name="John",age=30"#;
+/// let config: Config = from_str(jhon_str).unwrap();
+/// assert_eq!(config.name, "John");
+/// assert_eq!(config.age, 30);
+/// ```
+pub fn from_str<'de, T: Deserialize<'de>>(s: &'de str) -> Result<T> {
+    Jhon::from_str(s)
+}
+
+/// Serialize any type that implements `Serialize` into a JHON string.
+///
+/// This is a convenience function that uses the `Jhon` wrapper internally.
+///
+/// # Example
+///
+/// ```
+/// use jhon::to_string;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Debug, Serialize, Deserialize, PartialEq)]
+/// struct Config {
+///     name: String,
+///     age: u32,
+/// }
+///
+/// let config = Config { name: "John".to_string(), age: 30 };
+/// let jhon_str = to_string(&config).unwrap();
+/// ```
+pub fn to_string<T: Serialize>(value: &T) -> Result<String> {
+    Jhon::to_string(value)
+}
+
+/// Serialize any type that implements `Serialize` into a pretty-printed JHON string.
+///
+/// This is a convenience function that uses the `Jhon` wrapper internally.
+pub fn to_string_pretty<T: Serialize>(value: &T, indent: &str) -> Result<String> {
+    Jhon::to_string_pretty(value, indent)
+}
+
+/// Deserialize a present value as `Some`, so an explicit `null` becomes
+/// `Some(None)` while an absent key falls back to the field default.
+///
+/// Plain `Option<T>` fields cannot tell `key=null` from a missing key —
+/// both deserialize to `None`. Pair this with `#[serde(default)]` on an
+/// `Option<Option<T>>` field to keep the distinction.
+///
+/// # Example
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Debug, Deserialize, PartialEq)]
+/// struct Patch {
+///     name: String,
+///     #[serde(default, deserialize_with = "jhon::deserialize_some")]
+///     port: Option<Option<u16>>,
+/// }
+///
+/// let absent: Patch = jhon::from_str(r#"name="x""#).unwrap();
+/// assert_eq!(absent.port, None);
+///
+/// let cleared: Patch = jhon::from_str(r#"name="x",port=null"#).unwrap();
+/// assert_eq!(cleared.port, Some(None));
+///
+/// let set: Patch = jhon::from_str(r#"name="x",port=80"#).unwrap();
+/// assert_eq!(set.port, Some(Some(80)));
+/// ```
+pub fn deserialize_some<'de, T: Deserialize<'de>, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+// =============================================================================
+// Static Tables (from serde_json)
+// =============================================================================
+
+const BB: u8 = b'b'; // \\x08
+const TT: u8 = b't'; // \\x09
+const NN: u8 = b'n'; // \\x0A
+const FF: u8 = b'f'; // \\x0C
+const RR: u8 = b'r'; // \\x0D
+const QU: u8 = b'"'; // \\x22
+const BS: u8 = b'\\'; // \\x5C
+const UU: u8 = b'u'; // \\x00...\\x1F except the ones above
+const __: u8 = 0; // No escape needed
+
+// Lookup table of escape sequences. A value of b'x' at index i means that byte
+// i is escaped as "\x" in JSON. A value of 0 means that byte i is not escaped.
+static ESCAPE: [u8; 256] = [
+    //   1   2   3   4   5   6   7   8   9   A   B   C   D   E   F
+    UU, UU, UU, UU, UU, UU, UU, UU, BB, TT, NN, UU, FF, RR, UU, UU, // 0
+    UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, UU, // 1
+    __, __, QU, __, __, __, __, __, __, __, __, __, __, __, __, __, // 2
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 3
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 4
+    __, __, __, __, __, __, __, __, __, __, __, __, BS, __, __, __, // 5
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 6
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 7
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 8
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // 9
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // A
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // B
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // C
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // D
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // E
+    __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, __, // F
+];
+
+// Classification table for fast character checking
+// (removed during 2.0 rewrite — correctness over micro-optimization)
+
+// =============================================================================
+// Optimized Parser
+// =============================================================================
+
+#[derive(Clone, Copy)]
+struct Parser<'a> {
+    input: &'a [u8],
+    pos: usize,
+    line: usize, // 1-based
+    col: usize,  // 1-based
+    opts: &'a ParseOptions,
+    /// Set by [`parse_with_warnings`]: recoverable problems are recorded
+    /// here instead of failing the parse.
+    warnings: Option<&'a RefCell<Vec<Warning>>>,
+    /// Set by [`parse_document`]: the start of an unterminated nested block
+    /// comment is recorded here, since the comment skipper cannot fail.
+    unterminated_comment: Option<&'a Cell<Option<usize>>>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(input: &'a [u8]) -> Self {
+        Self::with_options(input, &ParseOptions::DEFAULT)
+    }
+
+    fn with_options(input: &'a [u8], opts: &'a ParseOptions) -> Self {
+        Self {
+            input,
+            pos: 0,
+            line: 1,
+            col: 1,
+            opts,
+            warnings: None,
+            unterminated_comment: None,
+        }
+    }
+
+    /// Record a warning at `line`:`col` if warnings are being collected.
+    /// Returns `false` when they are not, and the caller should fail instead.
+    fn warn(&self, line: usize, col: usize, msg: String) -> bool {
+        match self.warnings {
+            Some(sink) => {
+                sink.borrow_mut().push(Warning { line, col, msg });
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Insert `key` into `map`, rejecting a duplicate key, or replacing the
+    /// earlier value with a warning when warnings are collected.
+    fn insert_unique(&self, map: &mut Map<String, Value>, key: String, value: Value) -> Result<()> {
+        if map.contains_key(&key)
+            && !self.warn(
+                self.line,
+                self.col,
+                format!("duplicate key `{}`; the last value wins", key),
+            )
+        {
+            return Err(JhonError::DuplicateKey {
+                line: self.line,
+                col: self.col,
+                key,
+            });
+        }
+        map.insert(key, value);
+        Ok(())
+    }
+
+    fn current(&self) -> Option<u8> {
+        self.input.get(self.pos).copied()
+    }
+
+    fn advance(&mut self) -> Option<u8> {
+        let c = self.current()?;
+        if c == b'\n' {
+            self.line += 1;
+            self.col = 1;
+        } else {
+            self.col += 1;
+        }
+        self.pos += 1;
+        Some(c)
+    }
+
+    /// 1-based line and column of byte offset `pos`, counted from the input.
+    /// Columns count characters, not bytes.
+    fn line_col(&self, pos: usize) -> (usize, usize) {
+        let before = &self.input[..pos];
+        let line_start = before
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        let line = 1 + before.iter().filter(|&&b| b == b'\n').count();
+        let col = 1 + before[line_start..]
+            .iter()
+            .filter(|&&b| (b & 0xC0) != 0x80)
+            .count();
+        (line, col)
+    }
+
+    /// End (just past the closing `*/`) of the block comment opening at
+    /// `start`, or `None` if it is unterminated. With
+    /// `ParseOptions::nested_block_comments` an inner `/*` must be closed
+    /// first.
+    fn block_comment_end(&self, start: usize) -> Option<usize> {
+        let mut depth = 0usize;
+        let mut i = start;
+        while i + 1 < self.input.len() {
+            match &self.input[i..i + 2] {
+                b"/*" if depth == 0 || self.opts.nested_block_comments => {
+                    depth += 1;
+                    i += 2;
+                }
+                b"*/" => {
+                    depth -= 1;
+                    i += 2;
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => i += 1,
+            }
+        }
+        None
+    }
+
+    /// Whether a line comment starts at byte `i`: `//`, or `;` with
+    /// `ParseOptions::semicolon_comments`.
+    fn at_line_comment(&self, i: usize) -> bool {
+        match self.input.get(i) {
+            Some(b'/') => self.input.get(i + 1) == Some(&b'/'),
+            Some(b';') => self.opts.semicolon_comments,
+            _ => false,
+        }
+    }
+
+    /// Skip whitespace and comments. Returns whether a newline was consumed.
+    /// Comments are stripped inline so that line information survives for
+    /// separator-rule enforcement (SPEC.md §5.3).
+    fn skip_ws_and_comments(&mut self) -> bool {
+        let mut saw_newline = false;
+        loop {
+            match self.current() {
+                Some(b' ') | Some(b'\t') | Some(b'\r') => {
+                    self.advance();
+                }
+                Some(b'\n') => {
+                    saw_newline = true;
+                    self.advance();
+                }
+                Some(_) if self.at_line_comment(self.pos) => {
+                    // Line comment — skip to but not past '\n' so the outer
+                    // loop records the newline.
+                    self.advance();
+                    while let Some(c) = self.current() {
+                        if c == b'\n' {
+                            break;
+                        }
+                        self.advance();
+                    }
+                }
+                Some(b'/') if self.input.get(self.pos + 1) == Some(&b'*') => {
+                    // Block comment — skip to matching '*/'. Newlines inside
+                    // count toward the separator rule. Unterminated, it runs
+                    // to the end of the input, which is an error only when
+                    // comments nest.
+                    let end = match self.block_comment_end(self.pos) {
+                        Some(end) => end,
+                        None => {
+                            if self.opts.nested_block_comments
+                                && let Some(sink) = self.unterminated_comment
+                                && sink.get().is_none()
+                            {
+                                sink.set(Some(self.pos));
+                            }
+                            self.input.len()
+                        }
+                    };
+                    while self.pos < end {
+                        if self.current() == Some(b'\n') {
+                            saw_newline = true;
+                        }
+                        self.advance();
+                    }
+                }
+                _ => break,
+            }
+        }
+        saw_newline
+    }
+
+    /// Skip the separator between two consecutive items in a container.
+    /// Returns `(saw_newline, saw_comma)`. Per SPEC.md §5.3, an item following
+    /// another on the same physical line must be preceded by a comma; a
+    /// newline is also a valid separator. Tabs are whitespace like spaces,
+    /// never separators, in arrays and objects alike.
+    fn skip_inter_item_separator(&mut self) -> (bool, bool) {
+        let mut saw_newline = self.skip_ws_and_comments();
+        let mut saw_comma = false;
+        if self.current() == Some(b',') {
+            saw_comma = true;
+            self.advance();
+            if self.skip_ws_and_comments() {
+                saw_newline = true;
+            }
+        }
+        (saw_newline, saw_comma)
+    }
+
+    // String parsing. Phase 1 scans byte-by-byte for the closing quote while
+    // rejecting literal C0/DEL control chars; if no escape is seen the slice
+    // is returned directly. Phase 2 processes escapes, accumulating into a
+    // Vec<u8> so non-ASCII content after an escape stays UTF-8-correct.
+    fn parse_string(&mut self, quote: u8) -> Result<String> {
+        self.parse_string_cow(quote).map(Cow::into_owned)
+    }
+
+    /// [`Parser::parse_string`], borrowing from the input when the string
+    /// has no escapes.
+    fn parse_string_cow(&mut self, quote: u8) -> Result<Cow<'a, str>> {
+        self.advance(); // skip opening quote
+
+        let start = self.pos;
+
+        // Phase 1: scan for closing quote, backslash, or forbidden control byte.
+        while self.pos < self.input.len() {
+            let b = self.input[self.pos];
+            if b < 0x20 || b == 0x7F {
+                return Err(syntax_err!(
+                    "literal control character 0x{:02X} in string; use an escape or a raw string",
                     b
                 ));
             }
-            if b == quote {
-                let s = std::str::from_utf8(&self.input[start..self.pos])
-                    .map_err(|_| syntax_err!("Invalid UTF-8 in string"))?;
-                self.pos += 1;
-                return Ok(s.to_string());
+            if b == quote {
+                let s = std::str::from_utf8(&self.input[start..self.pos])
+                    .map_err(|_| syntax_err!("Invalid UTF-8 in string"))?;
+                self.pos += 1;
+                return Ok(Cow::Borrowed(s));
+            }
+            if b == b'\\' {
+                break;
+            }
+            self.check_string_len(self.pos + 1 - start, start)?;
+            self.pos += 1;
+        }
+
+        if self.pos >= self.input.len() {
+            return Err(self.unterminated("Unterminated string", start - 1));
+        }
+
+        // Phase 2: process escapes.
+        let mut bytes: Vec<u8> = self.input[start..self.pos].to_vec();
+
+        while self.pos < self.input.len() {
+            self.check_string_len(bytes.len(), start)?;
+            let b = self
+                .advance()
+                .ok_or_else(|| self.unterminated("Unterminated string", start - 1))?;
+            if b == quote {
+                return String::from_utf8(bytes)
+                    .map(Cow::Owned)
+                    .map_err(|_| syntax_err!("Invalid UTF-8 in string"));
+            }
+            if b == b'\\' {
+                let escaped = self
+                    .advance()
+                    .ok_or_else(|| syntax_err!("Incomplete escape sequence"))?;
+                // `\` before a CRLF line ending continues the line too.
+                let escaped = if escaped == b'\r' && self.current() == Some(b'\n') {
+                    self.advance();
+                    b'\n'
+                } else {
+                    escaped
+                };
+                match escaped {
+                    b'\n' => {
+                        // Line continuation: drop the newline and the next
+                        // line's leading indentation.
+                        while matches!(self.current(), Some(b' ' | b'\t')) {
+                            self.advance();
+                        }
+                    }
+                    b'n' => bytes.push(b'\n'),
+                    b'r' => bytes.push(b'\r'),
+                    b't' => bytes.push(b'\t'),
+                    b'b' => bytes.push(0x08),
+                    b'f' => bytes.push(0x0C),
+                    b'\\' => bytes.push(b'\\'),
+                    b'"' => bytes.push(b'"'),
+                    b'\'' => bytes.push(b'\''),
+                    b'/' => bytes.push(b'/'),
+                    b'x' => {
+                        let v = self.parse_hex_digits(2, "\\x")?;
+                        bytes.push(v as u8);
+                    }
+                    b'u' => {
+                        let code = self.parse_hex_digits(4, "\\u")?;
+                        if (0xD800..=0xDFFF).contains(&code) {
+                            return Err(syntax_err!(
+                                "surrogate code point U+{:04X} requires a pair; \
+                                 surrogate handling is not yet implemented",
+                                code
+                            ));
+                        }
+                        let c = char::from_u32(code).ok_or_else(|| {
+                            syntax_err!("Invalid Unicode code point U+{:04X}", code)
+                        })?;
+                        let mut buf = [0u8; 4];
+                        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+                    }
+                    other => {
+                        let (line, col) = self.line_col(self.pos - 2);
+                        let msg = format!("unknown escape \\{} kept as written", other as char);
+                        if !self.warn(line, col, msg) {
+                            return Err(syntax_err!("Unknown escape \\{}", other as char));
+                        }
+                        bytes.extend_from_slice(&[b'\\', other]);
+                    }
+                }
+            } else {
+                bytes.push(b);
+            }
+        }
+        Err(self.unterminated("Unterminated string", start - 1))
+    }
+
+    /// Syntax error for input that ends inside the construct `what` whose
+    /// opening delimiter sits at byte `start`, e.g. `Unterminated array
+    /// started at line 5, column 9`.
+    fn unterminated(&self, what: &str, start: usize) -> JhonError {
+        let (line, col) = self.line_col(self.pos.min(self.input.len()));
+        let (open_line, open_col) = self.line_col(start);
+        JhonError::Syntax {
+            line,
+            col,
+            msg: format!(
+                "{} started at line {}, column {}",
+                what, open_line, open_col
+            ),
+        }
+    }
+
+    /// Fail with [`JhonError::StringTooLong`] once a string whose content
+    /// starts at `start` has grown to `len` bytes, more than
+    /// `ParseOptions::max_string_len` allows.
+    fn check_string_len(&self, len: usize, start: usize) -> Result<()> {
+        match self.opts.max_string_len {
+            Some(max) if len > max => {
+                let (line, col) = self.line_col(start);
+                Err(JhonError::StringTooLong { line, col, max })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Parse `count` hex digits and return the assembled value.
+    fn parse_hex_digits(&mut self, count: usize, label: &str) -> Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..count {
+            let h = self
+                .advance()
+                .ok_or_else(|| syntax_err!("Incomplete {} escape", label))?;
+            let d = (h as char)
+                .to_digit(16)
+                .ok_or_else(|| syntax_err!("Invalid hex digit in {} escape", label))?;
+            value = (value << 4) | d;
+        }
+        Ok(value)
+    }
+
+    fn parse_raw_string(&mut self) -> Result<String> {
+        self.parse_raw_string_cow().map(Cow::into_owned)
+    }
+
+    fn parse_raw_string_cow(&mut self) -> Result<Cow<'a, str>> {
+        let open = self.pos;
+        self.advance(); // skip 'r'
+
+        let mut hash_count = 0;
+        while self.current() == Some(b'#') {
+            hash_count += 1;
+            self.advance();
+        }
+
+        if self.current() != Some(b'"') {
+            return Err(syntax_err!(
+                "Expected opening quote after r and # symbols in raw string"
+            ));
+        }
+        self.advance(); // skip opening quote
+
+        let start = self.pos;
+
+        while self.pos < self.input.len() {
+            if self.input[self.pos] == b'"' && self.pos + hash_count < self.input.len() {
+                let is_closing =
+                    (1..=hash_count).all(|j| self.input.get(self.pos + j) == Some(&b'#'));
+
+                if is_closing {
+                    let s = std::str::from_utf8(&self.input[start..self.pos])
+                        .map_err(|_| syntax_err!("Invalid UTF-8 in raw string"))?;
+                    self.pos += hash_count + 1;
+                    return Ok(Cow::Borrowed(s));
+                }
+            }
+            self.check_string_len(self.pos + 1 - start, start)?;
+            self.pos += 1;
+        }
+
+        Err(self.unterminated("Unterminated raw string", open))
+    }
+
+    // Number parser — handles decimal, hex, octal, binary, floats, and
+    // underscores per SPEC.md §3.5.
+    fn parse_number(&mut self) -> Result<Value> {
+        let start = self.pos;
+        let negative = self.current() == Some(b'-');
+        if negative || (self.opts.plus_sign_numbers && self.current() == Some(b'+')) {
+            self.advance();
+        }
+
+        // Radix prefix detection. Lowercase only — uppercase variants error.
+        let radix = if self.pos + 1 < self.input.len() && self.input[self.pos] == b'0' {
+            match self.input[self.pos + 1] {
+                b'x' => Some(16u32),
+                b'o' => Some(8u32),
+                b'b' => Some(2u32),
+                b'X' | b'O' | b'B' => {
+                    return Err(syntax_err!(
+                        "uppercase radix prefix 0{} not allowed; use lowercase",
+                        self.input[self.pos + 1] as char
+                    ));
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        // Scan the literal.
+        let literal: String;
+        let mut is_float = false;
+
+        if let Some(rdx) = radix {
+            self.advance(); // '0'
+            self.advance(); // 'x' / 'o' / 'b'
+            literal = self.scan_radix_digits(rdx)?;
+        } else {
+            let int_part = self.scan_dec_digits()?;
+            let mut s = int_part;
+            if self.current() == Some(b'.') {
+                is_float = true;
+                s.push('.');
+                self.advance();
+                s.push_str(&self.scan_dec_digits()?);
+            }
+            if matches!(self.current(), Some(b'e') | Some(b'E')) {
+                is_float = true;
+                s.push('e');
+                self.advance();
+                if matches!(self.current(), Some(b'+') | Some(b'-')) {
+                    s.push(self.current().unwrap() as char);
+                    self.advance();
+                }
+                s.push_str(&self.scan_dec_digits()?);
+            }
+            literal = s;
+        }
+
+        // Reject type suffixes (u8, i32, f64, ...) — next byte looks like u/i/f
+        // followed by alphanumeric.
+        if let Some(b) = self.current()
+            && matches!(b, b'u' | b'i' | b'f')
+            && self
+                .input
+                .get(self.pos + 1)
+                .copied()
+                .filter(|c| c.is_ascii_alphanumeric())
+                .is_some()
+        {
+            return Err(syntax_err!(
+                "number type suffix not allowed (saw '{}{}')",
+                b as char,
+                self.input[self.pos + 1] as char
+            ));
+        }
+
+        // Assemble signed form for parsing.
+        let signed = if negative {
+            format!("-{}", literal)
+        } else {
+            literal
+        };
+
+        // The literal as written, prefix and `_` included, for errors.
+        let source = String::from_utf8_lossy(&self.input[start..self.pos]);
+
+        if let Some(rdx) = radix {
+            return parse_radix_literal(&signed, &source, rdx, self.opts.on_int_overflow);
+        }
+
+        if !is_float {
+            if let Ok(i) = signed.parse::<i64>() {
+                return Ok(Value::Number(Number::from(i)));
+            }
+            if let Ok(u) = signed.parse::<u64>() {
+                return Ok(Value::Number(Number::from(u)));
+            }
+            if let Some(value) = overflowed_int(&source, self.opts.on_int_overflow)? {
+                return Ok(value);
+            }
+            if let Ok(i) = signed.parse::<i128>()
+                && let Some(n) = Number::from_f64(i as f64)
+            {
+                return Ok(Value::Number(n));
+            }
+            if let Ok(u) = signed.parse::<u128>()
+                && let Some(n) = Number::from_f64(u as f64)
+            {
+                return Ok(Value::Number(n));
+            }
+        }
+
+        #[cfg(feature = "decimal")]
+        if !signed.contains('e') {
+            return decimal_number(&signed).map(Value::Number);
+        }
+
+        let f = signed
+            .parse::<f64>()
+            .map_err(|_| syntax_err!("could not parse number: {}", signed))?;
+        Number::from_f64(f)
+            .map(Value::Number)
+            .ok_or_else(|| syntax_err!("invalid number value: {}", signed))
+    }
+
+    /// Scan a run of decimal digits with Rust-style underscore separators.
+    /// Validates that underscores occur only between two digits.
+    fn scan_dec_digits(&mut self) -> Result<String> {
+        let mut s = String::new();
+        let mut last_was_under = false;
+        let mut has_digit = false;
+        while self.pos < self.input.len() {
+            let b = self.input[self.pos];
+            if b.is_ascii_digit() {
+                s.push(b as char);
+                last_was_under = false;
+                has_digit = true;
+                self.pos += 1;
+            } else if b == b'_' {
+                if !has_digit || last_was_under {
+                    return Err(syntax_err!("invalid underscore placement in number"));
+                }
+                last_was_under = true;
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if !has_digit {
+            return Err(syntax_err!("number requires at least one digit"));
+        }
+        if last_was_under {
+            return Err(syntax_err!("number cannot end with underscore"));
+        }
+        Ok(s)
+    }
+
+    /// Scan a run of digits in the given radix with underscore separators.
+    fn scan_radix_digits(&mut self, radix: u32) -> Result<String> {
+        let mut s = String::new();
+        let mut last_was_under = false;
+        let mut has_digit = false;
+        while self.pos < self.input.len() {
+            let b = self.input[self.pos];
+            if (b as char).is_digit(radix) {
+                s.push(b as char);
+                last_was_under = false;
+                has_digit = true;
+                self.pos += 1;
+            } else if b == b'_' {
+                if !has_digit || last_was_under {
+                    return Err(syntax_err!("invalid underscore placement in number"));
+                }
+                last_was_under = true;
+                self.pos += 1;
+            } else {
+                break;
+            }
+        }
+        if !has_digit {
+            return Err(syntax_err!(
+                "number requires at least one digit after radix prefix"
+            ));
+        }
+        if last_was_under {
+            return Err(syntax_err!("number cannot end with underscore"));
+        }
+        Ok(s)
+    }
+
+    fn parse_array(&mut self) -> Result<(Value, usize)> {
+        self.parse_sequence(b']', "Unterminated array")
+    }
+
+    /// Elements of a `[...]` array, or of a `(...)` tuple with
+    /// `ParseOptions::paren_tuples`, up to and including `close`.
+    fn parse_sequence(&mut self, close: u8, unterminated: &str) -> Result<(Value, usize)> {
+        let open = self.pos;
+        self.advance(); // skip '[' or '('
+
+        let mut elements = Vec::new();
+
+        self.skip_ws_and_comments();
+
+        while self.current() != Some(close) {
+            if self.current().is_none() {
+                return Err(self.unterminated(unterminated, open));
+            }
+
+            if let Some(value) = self.parse_value()? {
+                elements.push(value);
+            }
+
+            // Skip separator between items. Per §5.3, an item that follows
+            // another on the same physical line must be preceded by a comma.
+            let (saw_newline, saw_comma) = self.skip_inter_item_separator();
+
+            if self.current() == Some(close) {
+                break;
+            }
+            if self.current().is_none() {
+                return Err(self.unterminated(unterminated, open));
+            }
+            self.check_item_separator(saw_newline, saw_comma)?;
+        }
+
+        self.advance(); // skip ']' or ')'
+        Ok((Value::Array(elements), self.pos))
+    }
+
+    fn parse_nested_object(&mut self) -> Result<(Value, usize)> {
+        let open = self.pos;
+        self.advance(); // skip '{'
+
+        let mut map = Map::new();
+
+        self.skip_ws_and_comments();
+
+        while self.current() != Some(b'}') {
+            if self.current().is_none() {
+                return Err(self.unterminated("Unterminated nested object", open));
+            }
+
+            // Parse key
+            let key_start = self.pos;
+            let key = self.parse_key()?;
+
+            let value = if self.opts.indent_blocks && self.at_line_end() {
+                Some(self.parse_indented_block(self.line_indent(key_start))?)
+            } else if self.opts.bare_keys_are_true && self.at_bare_flag() {
+                Some(Value::Bool(true))
+            } else {
+                // Skip whitespace/comments before '='
+                self.skip_ws_and_comments();
+
+                // Expect '='
+                if !self.eat_key_value_separator() {
+                    return Err(syntax_err!(
+                        "Expected {} after key in nested object",
+                        self.opts.key_value_separator
+                    ));
+                }
+
+                // Skip whitespace/comments before value
+                self.skip_ws_and_comments();
+                self.expect_value_for(&key)?;
+
+                self.parse_value()?
+            };
+
+            if let Some(value) = value {
+                self.insert_unique(&mut map, key, value)?;
+            }
+
+            // Skip separator between pairs.
+            let (saw_newline, saw_comma) = self.skip_inter_item_separator();
+
+            if self.current() == Some(b'}') {
+                break;
+            }
+            if self.current().is_none() {
+                return Err(self.unterminated("Unterminated nested object", open));
+            }
+            self.check_item_separator(saw_newline, saw_comma)?;
+        }
+
+        self.advance(); // skip '}'
+        Ok((Value::Object(map), self.pos))
+    }
+
+    /// One top-level `key = value` pair of an object-mode document, leaving
+    /// the separator after it unread.
+    fn parse_pair(&mut self) -> Result<(String, Option<Value>)> {
+        let key_start = self.pos;
+        let key = self.parse_key()?;
+
+        let value = if self.opts.indent_blocks && self.at_line_end() {
+            Some(self.parse_indented_block(self.line_indent(key_start))?)
+        } else if self.opts.bare_keys_are_true && self.at_bare_flag() {
+            Some(Value::Bool(true))
+        } else {
+            // Skip whitespace/comments before '='
+            self.skip_ws_and_comments();
+
+            // Expect '='
+            if !self.eat_key_value_separator() {
+                return Err(syntax_err!(
+                    "Expected {} after key",
+                    self.opts.key_value_separator
+                ));
+            }
+
+            // Skip whitespace/comments before value
+            self.skip_ws_and_comments();
+            self.expect_value_for(&key)?;
+
+            self.parse_value()?
+        };
+        Ok((key, value))
+    }
+
+    fn parse_key(&mut self) -> Result<String> {
+        let key = self.parse_key_as_written()?;
+        Ok(if self.opts.lowercase_keys {
+            key.to_lowercase()
+        } else {
+            key.into_owned()
+        })
+    }
+
+    fn parse_key_as_written(&mut self) -> Result<Cow<'a, str>> {
+        self.skip_ws_and_comments();
+
+        let quote = self.current();
+
+        if quote == Some(b'"') || quote == Some(b'\'') {
+            self.parse_string_cow(quote.unwrap())
+        } else {
+            // Bare key — permissive: scan bytes until we hit one in the
+            // exclusion list (per SPEC.md §3.3). All excluded bytes are ASCII,
+            // so UTF-8 multi-byte sequences pass through untouched.
+            let start = self.pos;
+            let stop_at_colon = self.opts.key_value_separator.accepts(b':');
+            while self.pos < self.input.len() {
+                let b = self.input[self.pos];
+                if is_key_delimiter(b) || (b == b':' && stop_at_colon) {
+                    break;
+                }
+                self.pos += 1;
+            }
+
+            if start == self.pos {
+                return Err(syntax_err!("Empty key"));
+            }
+
+            let s = std::str::from_utf8(&self.input[start..self.pos])
+                .map_err(|_| syntax_err!("Invalid UTF-8 in bare key"))?;
+            if self.opts.require_quoted_keys {
+                let (line, col) = self.line_col(start);
+                return Err(JhonError::UnquotedKey {
+                    line,
+                    col,
+                    key: s.to_string(),
+                });
+            }
+            Ok(Cow::Borrowed(s))
+        }
+    }
+
+    /// Consume the `=` (or `:`, per [`ParseOptions::key_value_separator`])
+    /// between a key and its value. Returns `false` if it is not there.
+    fn eat_key_value_separator(&mut self) -> bool {
+        match self.current() {
+            Some(b) if self.opts.key_value_separator.accepts(b) => {
+                self.advance();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Enforce SPEC.md §5.3 between two items: a comma or a newline must
+    /// separate them, or only a comma under `require_commas`.
+    fn check_item_separator(&self, saw_newline: bool, saw_comma: bool) -> Result<()> {
+        if saw_comma || (saw_newline && !self.opts.require_commas) {
+            return Ok(());
+        }
+        Err(syntax_err!(
+            "{}",
+            if saw_newline {
+                "items must be separated by a comma"
+            } else {
+                "items on the same line must be separated by a comma"
+            }
+        ))
+    }
+
+    /// After a key, whether it stands alone as a flag: no `=` follows, and
+    /// the next item is properly separated (a newline, `,`, `}`, or EOF).
+    fn at_bare_flag(&self) -> bool {
+        let mut probe = *self;
+        let saw_newline = probe.skip_ws_and_comments();
+        match probe.current() {
+            Some(b) if self.opts.key_value_separator.accepts(b) => false,
+            None | Some(b',') | Some(b'}') => true,
+            Some(_) => saw_newline,
+        }
+    }
+
+    /// After `key=`, reject a value position that holds a separator, a
+    /// closing delimiter, or nothing at all with [`JhonError::MissingValue`].
+    fn expect_value_for(&self, key: &str) -> Result<()> {
+        if matches!(self.current(), None | Some(b',' | b'}' | b']')) {
+            let (line, col) = self.line_col(self.pos);
+            return Err(JhonError::MissingValue {
+                line,
+                col,
+                key: key.to_string(),
+            });
+        }
+        Ok(())
+    }
+
+    fn parse_value(&mut self) -> Result<Option<Value>> {
+        self.skip_ws_and_comments();
+
+        let c = self
+            .current()
+            .ok_or_else(|| syntax_err!("Expected value"))?;
+
+        let result = match c {
+            b'"' | b'\'' => Some(Value::String(self.parse_string(c)?)),
+            _ if self.opts.bareword_values && self.at_bareword() => Some(self.parse_bareword()?),
+            b'r' | b'R' => Some(Value::String(self.parse_raw_string()?)),
+            b'[' => Some(self.parse_array()?.0),
+            b'(' if self.opts.paren_tuples => {
+                Some(self.parse_sequence(b')', "Unterminated tuple")?.0)
+            }
+            b'{' => Some(self.parse_nested_object()?.0),
+            b'@' if self.opts.tagged_objects => Some(self.parse_tagged_object()?),
+            b'<' if self.opts.heredoc_strings => Some(Value::String(self.parse_heredoc()?)),
+            b'0'..=b'9' | b'-' => Some(self.parse_number()?),
+            b'+' if self.opts.plus_sign_numbers => Some(self.parse_number()?),
+            b't' | b'f' => Some(self.parse_boolean()?),
+            b'n' => Some(self.parse_null()?),
+            _ => return Err(syntax_err!("Unexpected character in value: {}", c as char)),
+        };
+
+        if self.opts.coerce_bool_strings
+            && let Some(Value::String(s)) = &result
+            && let Ok(b) = s.parse::<bool>()
+        {
+            return Ok(Some(Value::Bool(b)));
+        }
+        Ok(result)
+    }
+
+    /// Scan a `<<MARKER` heredoc (see [`ParseOptions`]), leaving the cursor
+    /// just after the terminating marker.
+    fn parse_heredoc(&mut self) -> Result<String> {
+        if !self.input[self.pos..].starts_with(b"<<") {
+            return Err(syntax_err!("Unexpected character in value: <"));
+        }
+        self.pos += 2;
+        let marker_start = self.pos;
+        while matches!(self.current(), Some(b) if b.is_ascii_alphanumeric() || b == b'_') {
+            self.pos += 1;
+        }
+        let marker = &self.input[marker_start..self.pos];
+        if marker.is_empty() {
+            return Err(syntax_err!("Expected heredoc marker after <<"));
+        }
+        match self.input[self.pos..] {
+            [b'\n', ..] => self.pos += 1,
+            [b'\r', b'\n', ..] => self.pos += 2,
+            _ => return Err(syntax_err!("Heredoc marker must end its line")),
+        }
+
+        let body_start = self.pos;
+        let mut line_start = self.pos;
+        loop {
+            let line_end = self.input[line_start..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .map_or(self.input.len(), |i| line_start + i);
+            let line = &self.input[line_start..line_end];
+            if line.strip_suffix(b"\r").unwrap_or(line) == marker {
+                // The newline before the terminator belongs to the syntax.
+                let mut body_end = line_start.saturating_sub(1).max(body_start);
+                if body_end > body_start && self.input[body_end - 1] == b'\r' {
+                    body_end -= 1;
+                }
+                self.pos = line_start + marker.len();
+                return std::str::from_utf8(&self.input[body_start..body_end])
+                    .map(str::to_string)
+                    .map_err(|_| syntax_err!("Invalid UTF-8 in heredoc"));
+            }
+            self.check_string_len(line_end - body_start, body_start)?;
+            if line_end == self.input.len() {
+                return Err(syntax_err!(
+                    "Unterminated heredoc: expected {}",
+                    String::from_utf8_lossy(marker)
+                ));
+            }
+            line_start = line_end + 1;
+        }
+    }
+
+    /// Parse `@tag` and the object after it, if any, into an object whose
+    /// first key is `__tag__` (see `ParseOptions::tagged_objects`).
+    fn parse_tagged_object(&mut self) -> Result<Value> {
+        self.advance(); // skip '@'
+        let start = self.pos;
+        while matches!(self.current(), Some(b) if b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+        {
+            self.advance();
+        }
+        if self.pos == start {
+            return Err(syntax_err!("Expected a tag name after '@'"));
+        }
+        // Tag bytes are ASCII, checked above.
+        let tag = String::from_utf8_lossy(&self.input[start..self.pos]).into_owned();
+
+        let mut map = Map::new();
+        map.insert(TAG_KEY.to_string(), Value::String(tag));
+        // Only an object on the tag's own line belongs to it; a `{` on a
+        // later line is the next item.
+        let mut after_tag = *self;
+        let crossed_newline = after_tag.skip_ws_and_comments();
+        if !crossed_newline && after_tag.current() == Some(b'{') {
+            *self = after_tag;
+            let Value::Object(fields) = self.parse_nested_object()?.0 else {
+                unreachable!("parse_nested_object returns an object")
+            };
+            for (key, value) in fields {
+                if key == TAG_KEY {
+                    let (line, col) = self.line_col(start - 1);
+                    return Err(JhonError::Syntax {
+                        line,
+                        col,
+                        msg: format!("tagged object already has a `{}` key", TAG_KEY),
+                    });
+                }
+                map.insert(key, value);
+            }
+        }
+        Ok(Value::Object(map))
+    }
+
+    /// Whether the value at the cursor is a bareword under
+    /// `ParseOptions::bareword_values` rather than a number, container, raw
+    /// string, or a heredoc or `@tag` under the options enabling them.
+    fn at_bareword(&self) -> bool {
+        let next = self.input.get(self.pos + 1).copied();
+        match self.current() {
+            Some(b'0'..=b'9' | b'-' | b'[' | b'{' | b'(') => false,
+            Some(b'+') => !self.opts.plus_sign_numbers,
+            Some(b'r' | b'R') => !matches!(next, Some(b'"' | b'#')),
+            Some(b'<') => !(self.opts.heredoc_strings && next == Some(b'<')),
+            Some(b'@') => !self.opts.tagged_objects,
+            Some(_) => true,
+            None => false,
+        }
+    }
+
+    /// Move past the bareword at the cursor, which may be empty.
+    fn skip_bareword(&mut self) {
+        while let Some(b) = self.current() {
+            let ends = match b {
+                b' ' | b'\t' | b'\r' | b'\n' | b',' | b'=' => true,
+                b'[' | b']' | b'{' | b'}' => true,
+                b')' => self.opts.paren_tuples,
+                b'/' => matches!(self.input.get(self.pos + 1), Some(b'/' | b'*')),
+                b';' => self.opts.semicolon_comments,
+                _ => false,
+            };
+            if ends {
+                break;
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Scan a bareword value (see [`ParseOptions`]). The literals `true`,
+    /// `false`, and `null` keep their meaning.
+    fn parse_bareword(&mut self) -> Result<Value> {
+        let start = self.pos;
+        self.skip_bareword();
+        let word = std::str::from_utf8(&self.input[start..self.pos])
+            .map_err(|_| syntax_err!("Invalid UTF-8 in bareword value"))?;
+        Ok(match word {
+            "" => {
+                let c = self.input[start] as char;
+                return Err(syntax_err!("Unexpected character in value: {}", c));
+            }
+            "true" => Value::Bool(true),
+            "false" => Value::Bool(false),
+            "null" => Value::Null,
+            _ => Value::String(word.to_string()),
+        })
+    }
+
+    fn parse_boolean(&mut self) -> Result<Value> {
+        if self.input.len() >= self.pos + 4 && &self.input[self.pos..self.pos + 4] == b"true" {
+            self.pos += 4;
+            return Ok(Value::Bool(true));
+        } else if self.input.len() >= self.pos + 5
+            && &self.input[self.pos..self.pos + 5] == b"false"
+        {
+            self.pos += 5;
+            return Ok(Value::Bool(false));
+        }
+        Err(syntax_err!("Invalid boolean value"))
+    }
+
+    fn parse_null(&mut self) -> Result<Value> {
+        if self.input.len() >= self.pos + 4 && &self.input[self.pos..self.pos + 4] == b"null" {
+            self.pos += 4;
+            return Ok(Value::Null);
+        }
+        Err(syntax_err!("Invalid null value"))
+    }
+
+    // Indentation blocks (`ParseOptions::indent_blocks`). Columns are not
+    // tracked while scanning, so indentation is recomputed from the bytes
+    // of the line when needed.
+
+    /// Number of leading space/tab bytes on the line containing `pos`.
+    fn line_indent(&self, pos: usize) -> usize {
+        let line_start = self.input[..pos]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        self.input[line_start..]
+            .iter()
+            .take_while(|&&b| b == b' ' || b == b'\t')
+            .count()
+    }
+
+    /// Whether only blanks and a line comment remain before the next
+    /// newline. EOF does not count — a block key still needs its block.
+    fn at_line_end(&self) -> bool {
+        let mut i = self.pos;
+        while let Some(&b) = self.input.get(i) {
+            match b {
+                b' ' | b'\t' | b'\r' => i += 1,
+                b'\n' => return true,
+                _ => return self.at_line_comment(i),
+            }
+        }
+        false
+    }
+
+    /// Parse the indented block that follows a bare key. Entries must sit
+    /// deeper than `parent_indent` and share the indentation of the first
+    /// one; the block ends at the first line indented less than that.
+    fn parse_indented_block(&mut self, parent_indent: usize) -> Result<Value> {
+        self.skip_ws_and_comments();
+        if self.current().is_none() {
+            return Err(syntax_err!("Expected an indented block after key"));
+        }
+        let block_indent = self.line_indent(self.pos);
+        if block_indent <= parent_indent {
+            return Err(syntax_err!("Expected an indented block after key"));
+        }
+
+        let mut map = Map::new();
+        loop {
+            let key_start = self.pos;
+            let key = self.parse_key()?;
+            let value = if self.at_line_end() {
+                self.parse_indented_block(self.line_indent(key_start))?
+            } else {
+                self.skip_ws_and_comments();
+                if !self.eat_key_value_separator() {
+                    return Err(syntax_err!(
+                        "Expected {} after key",
+                        self.opts.key_value_separator
+                    ));
+                }
+                self.skip_ws_and_comments();
+                self.parse_value()?
+                    .ok_or_else(|| syntax_err!("Expected value"))?
+            };
+            self.insert_unique(&mut map, key, value)?;
+
+            // Leave the separator for the enclosing container when the
+            // block ends, so it still sees the newline before its next item.
+            let before = *self;
+            let (saw_newline, saw_comma) = self.skip_inter_item_separator();
+            match self.current() {
+                None | Some(b'}') | Some(b']') => {
+                    *self = before;
+                    break;
+                }
+                Some(_) => {}
+            }
+            if saw_newline {
+                let indent = self.line_indent(self.pos);
+                if indent < block_indent {
+                    *self = before;
+                    break;
+                }
+                if indent > block_indent {
+                    return Err(syntax_err!("Unexpected indentation in block"));
+                }
+            } else if !saw_comma {
+                return Err(syntax_err!(
+                    "items on the same line must be separated by a comma"
+                ));
+            }
+        }
+        Ok(Value::Object(map))
+    }
+}
+
+#[inline]
+/// Parse a signed radix literal into a JSON number. Tries i64 → u64 → i128 →
+/// u128 → falls back to f64 (the i128/u128 intermediates lose precision past
+/// 2^53 when stored in serde_json::Number). `source` is the literal as
+/// written, used in error messages.
+fn parse_radix_literal(
+    signed: &str,
+    source: &str,
+    radix: u32,
+    overflow: OverflowPolicy,
+) -> Result<Value> {
+    if let Ok(i) = i64::from_str_radix(signed, radix) {
+        return Ok(Value::Number(Number::from(i)));
+    }
+    if let Ok(u) = u64::from_str_radix(signed, radix) {
+        return Ok(Value::Number(Number::from(u)));
+    }
+    if let Some(value) = overflowed_int(source, overflow)? {
+        return Ok(value);
+    }
+    if let Ok(i) = i128::from_str_radix(signed, radix)
+        && let Some(n) = Number::from_f64(i as f64)
+    {
+        return Ok(Value::Number(n));
+    }
+    if let Ok(u) = u128::from_str_radix(signed, radix)
+        && let Some(n) = Number::from_f64(u as f64)
+    {
+        return Ok(Value::Number(n));
+    }
+    Err(syntax_err!("could not parse number: {}", source))
+}
+
+/// The value of an integer literal that fits neither `i64` nor `u64`, or
+/// `None` under [`OverflowPolicy::Float`], where the caller falls back to a
+/// float. `source` is the literal as written.
+fn overflowed_int(source: &str, policy: OverflowPolicy) -> Result<Option<Value>> {
+    match policy {
+        OverflowPolicy::Float => Ok(None),
+        OverflowPolicy::Error => Err(syntax_err!("integer literal out of range: {}", source)),
+        OverflowPolicy::Saturate if source.starts_with('-') => Ok(Some(Value::from(i64::MIN))),
+        OverflowPolicy::Saturate => Ok(Some(Value::from(u64::MAX))),
+    }
+}
+
+fn parse_jhon_object(parser: Parser) -> Result<Value> {
+    let mut map = Map::new();
+    parse_jhon_pairs(parser, |parser, key, value| {
+        parser.insert_unique(&mut map, key, value)
+    })?;
+    Ok(Value::Object(map))
+}
+
+/// Drive the top-level `key=value` loop of an object-mode document, handing
+/// each pair to `on_pair` in source order. Duplicate handling is left to the
+/// caller.
+fn parse_jhon_pairs(
+    mut parser: Parser,
+    mut on_pair: impl FnMut(&Parser, String, Value) -> Result<()>,
+) -> Result<()> {
+    parser.skip_ws_and_comments();
+
+    while parser.pos < parser.input.len() {
+        let (key, value) = parser.parse_pair()?;
+        if let Some(value) = value {
+            on_pair(&parser, key, value)?;
+        }
+
+        // Skip separator between pairs.
+        let (saw_newline, saw_comma) = parser.skip_inter_item_separator();
+
+        if parser.pos >= parser.input.len() {
+            break; // trailing separator at EOF is OK
+        }
+        parser.check_item_separator(saw_newline, saw_comma)?;
+    }
+
+    Ok(())
+}
+
+fn parse_jhon_array(mut parser: Parser) -> Result<Value> {
+    let mut elements = Vec::new();
+
+    parser.skip_ws_and_comments();
+
+    while parser.pos < parser.input.len() {
+        // Reject `key=value` pairs mixed into array mode. After a value is
+        // parsed, an immediately following `=` means the user wrote a pair
+        // after a bare value — that's a syntax error per SPEC.md §2.
+        if parser.current() == Some(b'=') {
+            return Err(syntax_err!(
+                "Cannot mix key=value pairs and bare values at top level"
+            ));
+        }
+
+        if let Some(value) = parser.parse_value()? {
+            elements.push(value);
+        }
+
+        // Reject mix at the value-position level too: parse_value rejects bare
+        // identifiers, so `1\na=2` errors with "Unexpected character" at `a`.
+        // The dedicated `=` check above catches `[1]=2`-style mix attempts.
+
+        let (saw_newline, saw_comma) = parser.skip_inter_item_separator();
+
+        if parser.pos >= parser.input.len() {
+            break;
+        }
+        parser.check_item_separator(saw_newline, saw_comma)?;
+    }
+
+    Ok(Value::Array(elements))
+}
+
+// =============================================================================
+// Key Scanning
+// =============================================================================
+
+/// Return the top-level keys of an object-mode document in source order,
+/// skipping over values without building them.
+///
+/// Values are only scanned far enough to find where they end (matching
+/// brackets, quotes, and comments), so malformed values are not reported —
+/// use [`parse`] when the document must be validated. Array-mode and empty
+/// documents have no keys and return an empty list.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::top_level_keys;
+///
+/// let keys = top_level_keys(r#"name="x", server={port=80, hosts=["a"]}, debug=true"#).unwrap();
+/// assert_eq!(keys, ["name", "server", "debug"]);
+/// ```
+pub fn top_level_keys(text: &str) -> Result<Vec<String>> {
+    // The input is not trimmed so that error positions match the source.
+    if !is_object_mode(text, &ParseOptions::DEFAULT) {
+        return Ok(Vec::new());
+    }
+
+    let mut parser = Parser::new(text.as_bytes());
+    let mut keys = Vec::new();
+    parser.skip_ws_and_comments();
+
+    while parser.pos < parser.input.len() {
+        let key = parser.parse_key()?;
+        parser.skip_ws_and_comments();
+        if parser.current() != Some(b'=') {
+            return Err(syntax_err!("Expected '=' after key"));
+        }
+        parser.advance();
+        parser.skip_ws_and_comments();
+        parser.expect_value_for(&key)?;
+        parser.skip_value()?;
+        keys.push(key);
+
+        let (saw_newline, saw_comma) = parser.skip_inter_item_separator();
+        if parser.pos >= parser.input.len() {
+            break;
+        }
+        if !saw_newline && !saw_comma {
+            return Err(syntax_err!(
+                "items on the same line must be separated by a comma"
+            ));
+        }
+    }
+
+    Ok(keys)
+}
+
+impl<'a> Parser<'a> {
+    /// Advance past one value without decoding it.
+    fn skip_value(&mut self) -> Result<()> {
+        self.skip_ws_and_comments();
+        match self.current() {
+            None => Err(syntax_err!("Expected value")),
+            Some(b'[') | Some(b'{') => self.skip_container(),
+            Some(_) => self.skip_scalar(),
+        }
+    }
+
+    /// Skip a scalar: a quoted or raw string, or a bare token (number or
+    /// keyword) running up to the next delimiter.
+    fn skip_scalar(&mut self) -> Result<()> {
+        match self.current() {
+            Some(q @ (b'"' | b'\'')) => self.skip_quoted(q),
+            Some(b'r' | b'R')
+                if matches!(self.input.get(self.pos + 1), Some(b'"') | Some(b'#')) =>
+            {
+                self.skip_raw()
+            }
+            _ => {
+                let start = self.pos;
+                while let Some(b) = self.current() {
+                    if is_key_delimiter(b) {
+                        break;
+                    }
+                    self.pos += 1;
+                }
+                if self.pos == start {
+                    return Err(syntax_err!("Expected value"));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Skip a bracketed value by depth counting. Strings and comments are
+    /// stepped over whole so delimiters inside them are not counted.
+    fn skip_container(&mut self) -> Result<()> {
+        let mut depth = 0usize;
+        loop {
+            self.skip_ws_and_comments();
+            match self.current() {
+                None => return Err(syntax_err!("Unterminated container")),
+                Some(b'[') | Some(b'{') => {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                Some(b']') | Some(b'}') => {
+                    depth -= 1;
+                    self.pos += 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                Some(b',') | Some(b'=') => self.pos += 1,
+                Some(_) => self.skip_scalar()?,
+            }
+        }
+    }
+
+    fn skip_quoted(&mut self, quote: u8) -> Result<()> {
+        self.pos += 1;
+        while let Some(b) = self.current() {
+            self.pos += 1;
+            if b == b'\\' {
+                self.pos += 1;
+            } else if b == quote {
+                return Ok(());
+            }
+        }
+        Err(syntax_err!("Unterminated string"))
+    }
+
+    fn skip_raw(&mut self) -> Result<()> {
+        self.pos += 1; // 'r'
+        let mut hashes = 0;
+        while self.current() == Some(b'#') {
+            hashes += 1;
+            self.pos += 1;
+        }
+        if self.current() != Some(b'"') {
+            return Err(syntax_err!(
+                "Expected opening quote after r and # symbols in raw string"
+            ));
+        }
+        self.pos += 1;
+        while self.pos < self.input.len() {
+            if self.input[self.pos] == b'"'
+                && (1..=hashes).all(|j| self.input.get(self.pos + j) == Some(&b'#'))
+            {
+                self.pos += hashes + 1;
+                return Ok(());
+            }
+            self.pos += 1;
+        }
+        Err(syntax_err!("Unterminated raw string"))
+    }
+}
+
+// =============================================================================
+// Tokens
+//
+// A lexical view of the source for highlighters: every byte of the input
+// belongs to exactly one token, including whitespace and comments, and each
+// token borrows its slice instead of allocating.
+// =============================================================================
+
+/// The lexical class of a [`SpannedToken`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// A run of spaces, tabs, and newlines.
+    Whitespace,
+    /// `// ...` up to (not including) the newline.
+    LineComment,
+    /// `/* ... */`
+    BlockComment,
+    /// A bare or quoted word followed by `=`, or any bare word that is not a
+    /// number or keyword.
+    Key,
+    /// A single- or double-quoted string in value position.
+    String,
+    /// `r"..."` / `r#"..."#`
+    RawString,
+    Number,
+    /// `true` / `false`
+    Bool,
+    Null,
+    Equals,
+    Comma,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+}
+
+/// A token and the slice of the input it covers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpannedToken<'a> {
+    pub kind: TokenKind,
+    pub text: &'a str,
+    /// Byte range of `text` in the input.
+    pub span: std::ops::Range<usize>,
+}
+
+/// Split `input` into tokens without building values.
+///
+/// Only lexical errors are reported (an unterminated string or comment, or a
+/// character that cannot start a token); the iterator stops after the first.
+/// Structural mistakes such as a missing `=` are left to [`parse`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{tokens, TokenKind};
+///
+/// let kinds: Vec<TokenKind> = tokens("port = 80").map(|t| t.unwrap().kind).collect();
+/// assert_eq!(
+///     kinds,
+///     [TokenKind::Key, TokenKind::Whitespace, TokenKind::Equals, TokenKind::Whitespace, TokenKind::Number]
+/// );
+/// ```
+pub fn tokens(input: &str) -> impl Iterator<Item = Result<SpannedToken<'_>>> {
+    tokens_with_options(input, &ParseOptions::DEFAULT)
+}
+
+/// [`tokens`] honoring the lexical [`ParseOptions`] (comment nesting,
+/// heredocs, barewords).
+fn tokens_with_options<'a>(
+    input: &'a str,
+    opts: &'a ParseOptions,
+) -> impl Iterator<Item = Result<SpannedToken<'a>>> {
+    let mut parser = Parser::with_options(input.as_bytes(), opts);
+    let mut failed = false;
+    std::iter::from_fn(move || {
+        if failed || parser.pos >= input.len() {
+            return None;
+        }
+        let start = parser.pos;
+        let token = parser.next_token_kind().map(|kind| SpannedToken {
+            kind,
+            text: &input[start..parser.pos],
+            span: start..parser.pos,
+        });
+        if let Err(JhonError::Syntax { msg, .. }) = token {
+            failed = true;
+            let (line, col) = parser.line_col(start);
+            return Some(Err(JhonError::Syntax { line, col, msg }));
+        }
+        Some(token)
+    })
+}
+
+/// Enforce the `reject_*` options of [`ParseOptions`] with a token scan, so
+/// the restrictions hold everywhere without special cases in the parser.
+fn check_lexical_restrictions(text: &str, opts: &ParseOptions) -> Result<()> {
+    if !(opts.reject_nul
+        || opts.reject_comments
+        || opts.reject_single_quotes
+        || opts.reject_raw_strings
+        || opts.reject_trailing_commas)
+    {
+        return Ok(());
+    }
+
+    let mut pending_comma: Option<usize> = None;
+    for token in tokens_with_options(text, opts) {
+        let token = token?;
+        if opts.reject_nul
+            && let Some(offset) = token.text.find('\0')
+            && !matches!(token.kind, TokenKind::String | TokenKind::RawString)
+            && !token.text.starts_with(['"', '\''])
+        {
+            let (line, col) = Parser::new(text.as_bytes()).line_col(token.span.start + offset);
+            return Err(JhonError::Syntax {
+                line,
+                col,
+                msg: "unexpected NUL byte".to_string(),
+            });
+        }
+        let msg = match token.kind {
+            TokenKind::LineComment | TokenKind::BlockComment if opts.reject_comments => {
+                Some("comments are not allowed")
+            }
+            TokenKind::String | TokenKind::Key
+                if opts.reject_single_quotes && token.text.starts_with('\'') =>
+            {
+                Some("single-quoted strings are not allowed")
+            }
+            TokenKind::RawString if opts.reject_raw_strings => Some("raw strings are not allowed"),
+            _ => None,
+        };
+        let at = match (msg, token.kind, pending_comma) {
+            (Some(_), ..) => Some(token.span.start),
+            (None, TokenKind::CloseBrace | TokenKind::CloseBracket, Some(comma)) => Some(comma),
+            _ => None,
+        };
+        if let Some(pos) = at {
+            let (line, col) = Parser::new(text.as_bytes()).line_col(pos);
+            return Err(JhonError::Syntax {
+                line,
+                col,
+                msg: msg.unwrap_or("trailing commas are not allowed").to_string(),
+            });
+        }
+        match token.kind {
+            TokenKind::Comma if opts.reject_trailing_commas => {
+                pending_comma = Some(token.span.start)
+            }
+            TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment => {}
+            _ => pending_comma = None,
+        }
+    }
+    if let Some(comma) = pending_comma {
+        let (line, col) = Parser::new(text.as_bytes()).line_col(comma);
+        return Err(JhonError::Syntax {
+            line,
+            col,
+            msg: "trailing commas are not allowed".to_string(),
+        });
+    }
+    Ok(())
+}
+
+impl<'a> Parser<'a> {
+    /// Advance past one token and return its kind.
+    fn next_token_kind(&mut self) -> Result<TokenKind> {
+        let input = self.input;
+        let b = input[self.pos];
+        let kind = match b {
+            b' ' | b'\t' | b'\r' | b'\n' => {
+                while matches!(self.current(), Some(b' ' | b'\t' | b'\r' | b'\n')) {
+                    self.pos += 1;
+                }
+                TokenKind::Whitespace
+            }
+            _ if self.at_line_comment(self.pos) => {
+                while !matches!(self.current(), None | Some(b'\n')) {
+                    self.pos += 1;
+                }
+                TokenKind::LineComment
+            }
+            b'/' if input.get(self.pos + 1) == Some(&b'*') => {
+                self.pos = self
+                    .block_comment_end(self.pos)
+                    .ok_or_else(|| syntax_err!("Unterminated block comment"))?;
+                TokenKind::BlockComment
+            }
+            b'=' | b',' | b'{' | b'}' | b'[' | b']' => {
+                self.pos += 1;
+                match b {
+                    b'=' => TokenKind::Equals,
+                    b',' => TokenKind::Comma,
+                    b'{' => TokenKind::OpenBrace,
+                    b'}' => TokenKind::CloseBrace,
+                    b'[' => TokenKind::OpenBracket,
+                    _ => TokenKind::CloseBracket,
+                }
+            }
+            b'"' | b'\'' => {
+                self.skip_quoted(b)?;
+                if self.followed_by_equals() {
+                    TokenKind::Key
+                } else {
+                    TokenKind::String
+                }
+            }
+            b'r' | b'R' if matches!(input.get(self.pos + 1), Some(b'"' | b'#')) => {
+                self.skip_raw()?;
+                TokenKind::RawString
+            }
+            b'<' if self.opts.heredoc_strings && input.get(self.pos + 1) == Some(&b'<') => {
+                self.parse_heredoc()?;
+                TokenKind::String
+            }
+            _ => {
+                let start = self.pos;
+                if self.opts.bareword_values && self.at_bareword() {
+                    self.skip_bareword();
+                } else {
+                    while self.current().is_some_and(|b| !is_key_delimiter(b)) {
+                        self.pos += 1;
+                    }
+                }
+                if self.pos == start {
+                    return Err(syntax_err!("Unexpected character {:?}", b as char));
+                }
+                if self.followed_by_equals() {
+                    return Ok(TokenKind::Key);
+                }
+                match &input[start..self.pos] {
+                    b"true" | b"false" => TokenKind::Bool,
+                    b"null" => TokenKind::Null,
+                    [b'0'..=b'9' | b'-', ..] => TokenKind::Number,
+                    _ => TokenKind::Key,
+                }
+            }
+        };
+        Ok(kind)
+    }
+
+    /// Whether the next non-blank byte on this line is `=`.
+    fn followed_by_equals(&self) -> bool {
+        self.input[self.pos..]
+            .iter()
+            .find(|&&b| b != b' ' && b != b'\t')
+            == Some(&b'=')
+    }
+}
+
+// =============================================================================
+// Optimized Serializer
+// =============================================================================
+
+#[inline(always)]
+fn serialize_compact(value: &Value, opts: &SerializeOptions, result: &mut String) {
+    match value {
+        Value::Object(map) if map.is_empty() => {}
+        Value::Object(map) => serialize_object_compact(map, opts, result),
+        Value::Array(arr) if arr.is_empty() => result.push_str("[]"),
+        Value::Array(arr) => serialize_array_compact(arr, opts, result),
+        Value::String(s) => serialize_string_value(s, opts, result),
+        Value::Number(n) => serialize_number(n, opts, result),
+        Value::Bool(b) => result.push_str(if *b { "true" } else { "false" }),
+        Value::Null => result.push_str("null"),
+    }
+}
+
+/// A string in value position: bare under `bareword_strings` when that
+/// reads back unchanged, quoted otherwise.
+#[inline(always)]
+fn serialize_string_value(s: &str, opts: &SerializeOptions, result: &mut String) {
+    if opts.bareword_strings && is_safe_bareword(s) {
+        result.push_str(s);
+    } else {
+        serialize_string_escaping(s, opts.escape_js_unsafe, result);
+    }
+}
+
+#[inline(always)]
+fn serialize_object_compact(
+    map: &Map<String, Value>,
+    opts: &SerializeOptions,
+    result: &mut String,
+) {
+    let mut first = true;
+    for (key, value) in map {
+        if !first {
+            result.push(',');
+        }
+        first = false;
+
+        serialize_key(key, result);
+        result.push('=');
+
+        match value {
+            Value::Object(inner) if inner.is_empty() => result.push_str("{}"),
+            Value::Object(inner) => {
+                result.push('{');
+                serialize_object_compact(inner, opts, result);
+                result.push('}');
+            }
+            _ => serialize_compact(value, opts, result),
+        }
+    }
+}
+
+#[inline(always)]
+fn serialize_array_compact(arr: &[Value], opts: &SerializeOptions, result: &mut String) {
+    result.push('[');
+    serialize_array_contents_compact(arr, opts, result);
+    result.push(']');
+}
+
+#[inline(always)]
+fn serialize_array_contents_compact(arr: &[Value], opts: &SerializeOptions, result: &mut String) {
+    let mut first = true;
+    for value in arr {
+        if !first {
+            result.push(',');
+        }
+        first = false;
+
+        match value {
+            Value::Object(map) if map.is_empty() => result.push_str("{}"),
+            Value::Object(map) => {
+                result.push('{');
+                serialize_object_compact(map, opts, result);
+                result.push('}');
+            }
+            _ => serialize_compact(value, opts, result),
+        }
+    }
+}
+
+#[inline(always)]
+fn serialize_key(key: &str, result: &mut String) {
+    if needs_quoting(key) {
+        serialize_string(key, result);
+    } else {
+        result.push_str(key);
+    }
+}
+
+#[inline(always)]
+fn serialize_string(s: &str, result: &mut String) {
+    serialize_string_escaping(s, true, result);
+}
+
+// Optimized string serialization using static escape table: runs of bytes
+// that need no escaping are copied as whole slices.
+#[inline(always)]
+fn serialize_string_escaping(s: &str, escape_js_unsafe: bool, result: &mut String) {
+    result.push('"');
+
+    let bytes = s.as_bytes();
+    let mut start = 0;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        if ESCAPE[byte as usize] != 0 {
+            result.push_str(&s[start..i]);
+            serialize_escape_byte(byte, result);
+            i += 1;
+            start = i;
+        } else if escape_js_unsafe && byte == 0xE2 && is_js_line_terminator(&bytes[i..]) {
+            // U+2028 / U+2029 are valid raw in JHON and JSON but terminate
+            // lines in JavaScript source, so emit them escaped unless asked
+            // not to.
+            result.push_str(&s[start..i]);
+            result.push_str(if bytes[i + 2] == 0xA8 {
+                "\\u2028"
+            } else {
+                "\\u2029"
+            });
+            i += 3;
+            start = i;
+        } else {
+            i += 1;
+        }
+    }
+
+    result.push_str(&s[start..]);
+    result.push('"');
+}
+
+// U+2028 LINE SEPARATOR and U+2029 PARAGRAPH SEPARATOR encode as E2 80 A8/A9.
+#[inline(always)]
+fn is_js_line_terminator(bytes: &[u8]) -> bool {
+    matches!(bytes, [0xE2, 0x80, 0xA8 | 0xA9, ..])
+}
+
+// Serialize a single escaped byte
+#[inline(always)]
+fn serialize_escape_byte(byte: u8, result: &mut String) {
+    match ESCAPE[byte as usize] {
+        BB => result.push_str("\\b"),
+        TT => result.push_str("\\t"),
+        NN => result.push_str("\\n"),
+        FF => result.push_str("\\f"),
+        RR => result.push_str("\\r"),
+        QU => result.push_str("\\\""),
+        BS => result.push_str("\\\\"),
+        UU => {
+            // Unicode escape for control characters (0x00-0x1F)
+            // Manual formatting: \u00XX
+            const HEX: &[u8; 16] = b"0123456789abcdef";
+            result.push_str("\\u00");
+            result.push(HEX[(byte >> 4) as usize] as char);
+            result.push(HEX[(byte & 0x0F) as usize] as char);
+        }
+        _ => {
+            // Safety: ESCAPE table only has the above values
+            // This should never be reached
+            result.push(byte as char);
+        }
+    }
+}
+
+// Number serialization — uses std formatting (itoa/ryu dropped for simplicity).
+#[inline(always)]
+fn serialize_number(n: &Number, opts: &SerializeOptions, result: &mut String) {
+    let start = result.len();
+    if let Some(i) = n.as_i64().or_else(|| whole_decimal(n, opts)) {
+        let _ = write!(result, "{}", i);
+    } else if let Some(u) = n.as_u64() {
+        let _ = write!(result, "{}", u);
+    } else if let Some(f) = n.as_f64() {
+        // Exact decimal text from the `decimal` feature: emit it verbatim.
+        // Whole values were written as integers above.
+        #[cfg(feature = "decimal")]
+        if opts.exponential_threshold.is_none() && !n.as_str().contains(['e', 'E']) {
+            result.push_str(n.as_str());
+            return;
+        }
+        if let Some(t) = opts.exponential_threshold
+            && f != 0.0
+            && (f.abs() >= t || f.abs() < t.recip())
+        {
+            let _ = write!(result, "{:e}", f);
+            return;
+        }
+        // Whole floats print as integers, but only inside the i64 range:
+        // beyond it `as i64` saturates, so use exponent form instead.
+        if f.fract() == 0.0 && f >= i64::MIN as f64 && f < i64::MAX as f64 {
+            let _ = write!(result, "{}", f as i64);
+        } else if f.fract() == 0.0 {
+            let _ = write!(result, "{:e}", f);
+            return;
+        } else {
+            let _ = write!(result, "{}", f);
+            return;
+        }
+    } else {
+        result.push('0');
+    }
+    if opts.digit_grouping {
+        group_digits(result, start);
+    }
+}
+
+/// The value of a `decimal`-feature number whose text is a whole `i64` with
+/// a zero fraction (`5.0`), so it is written as an integer exactly like a
+/// whole `f64`. `None` under `exponential_threshold`, whose `f64` rules apply.
+#[cfg(feature = "decimal")]
+fn whole_decimal(n: &Number, opts: &SerializeOptions) -> Option<i64> {
+    if opts.exponential_threshold.is_some() {
+        return None;
+    }
+    let (int, frac) = n.as_str().split_once('.')?;
+    if !frac.bytes().all(|b| b == b'0') {
+        return None;
+    }
+    int.parse().ok()
+}
+
+#[cfg(not(feature = "decimal"))]
+fn whole_decimal(_: &Number, _: &SerializeOptions) -> Option<i64> {
+    None
+}
+
+/// Keep a fractional literal (`-12.50`, underscores already stripped) as an
+/// exact decimal [`Number`]; see the `decimal` feature on [`parse`].
+#[cfg(feature = "decimal")]
+fn decimal_number(literal: &str) -> Result<Number> {
+    let (sign, digits) = match literal.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", literal),
+    };
+    // JSON number text has no leading zeros; `007.5` is `7.5`.
+    let trimmed = digits.trim_start_matches('0');
+    let digits = if trimmed.starts_with('.') {
+        &digits[digits.len() - trimmed.len() - 1..]
+    } else {
+        trimmed
+    };
+    serde_json::from_str(&format!("{}{}", sign, digits))
+        .map_err(|_| syntax_err!("could not parse number: {}", literal))
+}
+
+/// Insert `_` every three digits into the integer written at `result[start..]`.
+fn group_digits(result: &mut String, start: usize) {
+    let digits_start = start + usize::from(result[start..].starts_with('-'));
+    let mut i = result.len();
+    while i > digits_start + 3 {
+        i -= 3;
+        result.insert(i, '_');
+    }
+}
+
+// =============================================================================
+// Inline-aware pretty printer (`max_inline_width > 0` mode).
+//
+// The removed depth-based printer always multi-lined non-empty containers.
+// This path short-circuits to a single-line `{ k = v, ... }` /
+// `[ a, b, ... ]` form when the result fits within `max_inline_width` chars,
+// and falls back to a 3-line "wrapper_compact" form when only the joined
+// children fit, and finally to one-child-per-line otherwise. With
+// `max_inline_width == 0` nothing fits inline, which is how `serialize_pretty`
+// gets fully expanded output.
+// =============================================================================
+
+/// Top-level dispatch for inline-aware mode. Mirrors `serialize_top_compact`:
+/// empty containers and `null` collapse to empty string (SPEC §2); top-level
+/// arrays emit bare (no surrounding `[]`).
+fn serialize_pretty_inline_top(
+    value: &Value,
+    opts: &SerializeOptions,
+    pretty: &PrettyOptions,
+    walk: &mut Walk,
+    result: &mut String,
+) {
+    // `compact_nested` keeps top-level containers on their entry's line.
+    let mut render_entry = |v: &Value, result: &mut String| match v {
+        Value::Object(_) | Value::Array(_) if opts.compact_nested => push_inline(v, opts, result),
+        _ => render_pretty_inline(v, opts, pretty, 0, walk, result),
+    };
+    match value {
+        Value::Array(arr) if arr.is_empty() => {}
+        Value::Array(arr) => {
+            let mut first = true;
+            for v in arr {
+                if !first {
+                    result.push('\n');
+                }
+                first = false;
+                render_entry(v, result);
+            }
+        }
+        Value::Object(map) if map.is_empty() => {}
+        Value::Object(map) => {
+            // Top-level object: keys at column 0, no surrounding braces.
+            let mut first = true;
+            let mut group = None;
+            for (k, v) in map.iter() {
+                let prefix = k.split(['_', '.']).next();
+                if !first {
+                    result.push('\n');
+                    if opts.group_blank_lines && group != prefix {
+                        result.push('\n');
+                    }
+                }
+                first = false;
+                group = prefix;
+                serialize_key(k, result);
+                result.push_str(" = ");
+                render_entry(v, result);
+            }
+        }
+        Value::Null => {}
+        _ => render_entry(value, result),
+    }
+}
+
+/// Render a single value at `depth`. Caller is responsible for any leading
+/// indent (e.g. after `key = ` or inside an array's child loop).
+fn render_pretty_inline(
+    value: &Value,
+    opts: &SerializeOptions,
+    pretty: &PrettyOptions,
+    depth: usize,
+    walk: &mut Walk,
+    result: &mut String,
+) {
+    let (indent, max_inline_width) = (pretty.indent.as_str(), pretty.max_inline_width);
+    match value {
+        Value::String(s) => {
+            match pretty.wrap_strings_at {
+                Some(_) if opts.bareword_strings && is_safe_bareword(s) => result.push_str(s),
+                Some(width) => serialize_wrapped_string(s, width, indent, depth + 1, opts, result),
+                None => serialize_string_value(s, opts, result),
+            }
+            return;
+        }
+        Value::Number(n) => {
+            serialize_number(n, opts, result);
+            return;
+        }
+        Value::Bool(b) => {
+            result.push_str(if *b { "true" } else { "false" });
+            return;
+        }
+        Value::Null => {
+            result.push_str("null");
+            return;
+        }
+        Value::Object(map) if map.is_empty() => {
+            result.push_str("{}");
+            return;
+        }
+        Value::Array(arr) if arr.is_empty() => {
+            result.push_str("[]");
+            return;
+        }
+        _ => {}
+    }
+
+    if walk.max_depth.is_some_and(|max| depth >= max) {
+        result.push_str(if value.is_object() { "{…}" } else { "[…]" });
+        return;
+    }
+
+    // `array_expand_threshold` decides an array's layout by its length alone.
+    let expand_array = match value {
+        Value::Array(arr) if opts.array_expand_threshold > 0 => {
+            Some(arr.len() > opts.array_expand_threshold)
+        }
+        _ => None,
+    };
+    if expand_array == Some(false) {
+        push_inline(value, opts, result);
+        return;
+    }
+
+    // With no width to fit, skip measuring and expand right away.
+    if max_inline_width == 0 && expand_array.is_none() {
+        walk_container(
+            value,
+            Some(indent),
+            depth,
+            walk,
+            result,
+            &mut |v, walk, result| render_pretty_inline(v, opts, pretty, depth + 1, walk, result),
+        );
+        return;
+    }
+
+    // Try fully-inline form first.
+    let mut inline_buf = String::new();
+    push_inline(value, opts, &mut inline_buf);
+    if expand_array.is_none() && inline_buf.len() <= max_inline_width {
+        result.push_str(&inline_buf);
+        return;
+    }
+
+    // Try wrapper_compact: brackets on their own lines, joined children on one line.
+    let mut joined_buf = String::new();
+    push_joined_children(value, opts, &mut joined_buf);
+    if expand_array.is_none() && !joined_buf.is_empty() && joined_buf.len() <= max_inline_width {
+        let (open, close) = if matches!(value, Value::Object(_)) {
+            ('{', '}')
+        } else {
+            ('[', ']')
+        };
+        result.push(open);
+        result.push('\n');
+        push_indent(result, indent, depth + 1);
+        result.push_str(&joined_buf);
+        result.push('\n');
+        push_indent(result, indent, depth);
+        result.push(close);
+        return;
+    }
+
+    // wrapper_multi: open bracket inline, one child per line, close at parent indent.
+    walk_container(
+        value,
+        Some(indent),
+        depth,
+        walk,
+        result,
+        &mut |v, walk, result| render_pretty_inline(v, opts, pretty, depth + 1, walk, result),
+    );
+}
+
+/// Hook run by [`walk_container`] on the output after each element.
+type AfterValue<'a> = &'a mut dyn FnMut(&mut String) -> Result<()>;
+
+/// Controls for a walk through [`walk_container`], for the callers that
+/// elide or stream the output.
+#[derive(Default)]
+struct Walk<'a> {
+    /// Non-empty containers at this depth or deeper are elided as `{…}` /
+    /// `[…]` by [`render_pretty_inline`].
+    max_depth: Option<usize>,
+    /// Run on the output after every container element. The first error is
+    /// kept in `error` and ends the walk.
+    after_value: Option<AfterValue<'a>>,
+    error: Option<JhonError>,
+}
+
+impl Walk<'_> {
+    fn after_value(&mut self, result: &mut String) {
+        if self.error.is_none()
+            && let Some(hook) = &mut self.after_value
+            && let Err(e) = hook(result)
+        {
+            self.error = Some(e);
+        }
+    }
+}
+
+/// Write the container `value` with every element written by
+/// `child`. With `indent`, elements go one per line at `depth + 1` and the
+/// closing bracket on its own line, as in pretty output; without, they are
+/// comma-separated as in compact output.
+fn walk_container(
+    value: &Value,
+    indent: Option<&str>,
+    depth: usize,
+    walk: &mut Walk,
+    result: &mut String,
+    child: &mut dyn FnMut(&Value, &mut Walk, &mut String),
+) {
+    let mut element =
+        |i: usize, key: Option<&str>, v: &Value, walk: &mut Walk, result: &mut String| {
+            match indent {
+                Some(indent) => {
+                    result.push('\n');
+                    push_indent(result, indent, depth + 1);
+                }
+                None if i > 0 => result.push(','),
+                None => {}
+            }
+            if let Some(k) = key {
+                serialize_key(k, result);
+                result.push_str(if indent.is_some() { " = " } else { "=" });
+            }
+            child(v, walk, result);
+            walk.after_value(result);
+        };
+    let close = match value {
+        Value::Object(map) => {
+            result.push('{');
+            for (i, (k, v)) in map.iter().enumerate() {
+                if walk.error.is_some() {
+                    return;
+                }
+                element(i, Some(k), v, walk, result);
+            }
+            '}'
+        }
+        Value::Array(arr) => {
+            result.push('[');
+            for (i, v) in arr.iter().enumerate() {
+                if walk.error.is_some() {
+                    return;
+                }
+                element(i, None, v, walk, result);
+            }
+            ']'
+        }
+        _ => unreachable!(),
+    };
+    if let Some(indent) = indent {
+        result.push('\n');
+        push_indent(result, indent, depth);
+    }
+    result.push(close);
+}
+
+/// Serialize `s` as a double-quoted string, continuing it on new lines
+/// (indented `depth` levels) whenever a line's escaped content would exceed
+/// `width` characters. Escape sequences are never split. Breaks fall after
+/// the last space on the line if there is one; a continuation line that would
+/// start with a space writes it as `\u0020`, since the parser skips leading
+/// whitespace after a continuation.
+fn serialize_wrapped_string(
+    s: &str,
+    width: usize,
+    indent: &str,
+    depth: usize,
+    opts: &SerializeOptions,
+    result: &mut String,
+) {
+    let mut escaped = String::new();
+    serialize_string_escaping(s, opts.escape_js_unsafe, &mut escaped);
+    let body = &escaped[1..escaped.len() - 1];
+    if width == 0 || body.chars().count() <= width {
+        result.push_str(&escaped);
+        return;
+    }
+
+    // Split the escaped body into units: one char, or one whole escape.
+    let mut units: Vec<&str> = Vec::new();
+    let mut rest = body;
+    while let Some(c) = rest.chars().next() {
+        let len = match c {
+            '\\' if rest[1..].starts_with('u') => 6,
+            '\\' => 2,
+            _ => c.len_utf8(),
+        };
+        units.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+
+    result.push('"');
+    let mut line_start = 0;
+    while line_start < units.len() {
+        if line_start > 0 {
+            result.push_str("\\\n");
+            push_indent(result, indent, depth);
+        }
+
+        // Greedily take units up to `width` characters, then back up to the
+        // last space if the line is not the final one.
+        let mut end = line_start;
+        let mut chars = 0;
+        while end < units.len() {
+            let n = units[end].chars().count();
+            if chars + n > width && end > line_start {
+                break;
+            }
+            chars += n;
+            end += 1;
+        }
+        if end < units.len()
+            && let Some(space) = (line_start + 1..end).rev().find(|&i| units[i - 1] == " ")
+        {
+            end = space;
+        }
+
+        for (i, unit) in units[line_start..end].iter().enumerate() {
+            if i == 0 && line_start > 0 && *unit == " " {
+                result.push_str("\\u0020");
+            } else {
+                result.push_str(unit);
+            }
+        }
+        line_start = end;
+    }
+    result.push('"');
+}
+
+/// Append `indent × n` to `result`.
+fn push_indent(result: &mut String, indent: &str, n: usize) {
+    for _ in 0..n {
+        result.push_str(indent);
+    }
+}
+
+/// Single-line rendering of a value with `{ k = v, ... }` / `[ a, b, ... ]`
+/// spacing — including outer brackets/braces. Used both for the inline-mode
+/// emission and for measuring length via the buffer's `len()`.
+fn push_inline(value: &Value, opts: &SerializeOptions, out: &mut String) {
+    match value {
+        Value::Object(map) if map.is_empty() => out.push_str("{}"),
+        Value::Object(map) => {
+            out.push_str("{ ");
+            let mut first = true;
+            for (k, v) in map.iter() {
+                if !first {
+                    out.push_str(", ");
+                }
+                first = false;
+                serialize_key(k, out);
+                out.push_str(" = ");
+                push_inline(v, opts, out);
+            }
+            out.push_str(" }");
+        }
+        Value::Array(arr) if arr.is_empty() => out.push_str("[]"),
+        Value::Array(arr) => {
+            out.push_str("[ ");
+            let mut first = true;
+            for v in arr.iter() {
+                if !first {
+                    out.push_str(", ");
+                }
+                first = false;
+                push_inline(v, opts, out);
+            }
+            out.push_str(" ]");
+        }
+        Value::String(s) => serialize_string_value(s, opts, out),
+        Value::Number(n) => serialize_number(n, opts, out),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Null => out.push_str("null"),
+    }
+}
+
+/// Like [``push_inline``] but without the outer brackets/braces — just the
+/// joined children. Returns empty string for scalars and empty containers.
+fn push_joined_children(value: &Value, opts: &SerializeOptions, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut first = true;
+            for (k, v) in map.iter() {
+                if !first {
+                    out.push_str(", ");
+                }
+                first = false;
+                serialize_key(k, out);
+                out.push_str(" = ");
+                push_inline(v, opts, out);
+            }
+        }
+        Value::Array(arr) => {
+            let mut first = true;
+            for v in arr.iter() {
+                if !first {
+                    out.push_str(", ");
+                }
+                first = false;
+                push_inline(v, opts, out);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn needs_quoting(s: &str) -> bool {
+    if s.is_empty() {
+        return true;
+    }
+    // Keyword- and number-shaped keys parse back as strings (SPEC.md §3.3),
+    // but quoting them keeps the output unambiguous to a reader.
+    if matches!(s, "true" | "false" | "null") || is_number_literal(s) {
+        return true;
+    }
+    // A leading `;` would start a comment under `semicolon_comments`, and
+    // Unicode whitespace at either end is trimmed off the document.
+    if s.starts_with(';') || s.starts_with(char::is_whitespace) || s.ends_with(char::is_whitespace)
+    {
+        return true;
+    }
+    // Otherwise quote iff the key contains a byte that would terminate a bare
+    // key, or U+2028/U+2029, which are only written escaped.
+    s.bytes().any(is_key_delimiter) || s.contains(['\u{2028}', '\u{2029}'])
+}
+
+/// Whether `s` can be written as a bareword value that reads back as the
+/// same string under `ParseOptions::bareword_values`. It also steers clear
+/// of what `paren_tuples`, `semicolon_comments`, `heredoc_strings`,
+/// `tagged_objects`, and `plus_sign_numbers` would read differently.
+fn is_safe_bareword(s: &str) -> bool {
+    let Some(&first) = s.as_bytes().first() else {
+        return false;
+    };
+    if matches!(s, "true" | "false" | "null")
+        || matches!(first, b'0'..=b'9' | b'-' | b'+' | b'(' | b'@')
+        || s.starts_with("<<")
+        || s.starts_with(['r', 'R']) && s[1..].starts_with('#')
+        || s.contains("//")
+        || s.contains("/*")
+        || s.starts_with(char::is_whitespace)
+        || s.ends_with(char::is_whitespace)
+    {
+        return false;
+    }
+    !s.bytes().any(|b| {
+        b < 0x20
+            || b == 0x7F
+            || matches!(
+                b,
+                b' ' | b',' | b'=' | b'"' | b'\'' | b'[' | b']' | b'{' | b'}' | b')' | b';'
+            )
+    }) && !s.contains(['\u{2028}', '\u{2029}'])
+}
+
+/// True if the whole of `s` is a JHON number literal (SPEC.md §3.5).
+fn is_number_literal(s: &str) -> bool {
+    if !matches!(s.as_bytes()[0], b'0'..=b'9' | b'-') {
+        return false;
+    }
+    let mut parser = Parser::new(s.as_bytes());
+    parser.parse_number().is_ok() && parser.pos == s.len()
+}
+
+/// Returns true for any byte that terminates a bare key (SPEC.md §3.3).
+/// All such bytes are ASCII, so UTF-8 continuation/lead bytes never match.
+fn is_key_delimiter(b: u8) -> bool {
+    matches!(
+        b,
+        b' ' | b'\t'
+            | b'\n'
+            | b'\r'
+            | b'='
+            | b','
+            | b'{'
+            | b'}'
+            | b'['
+            | b']'
+            | b'/'
+            | b'"'
+            | b'\''
+            | b'#'
+    )
+}
+
+// =============================================================================
+// Value Helpers
+// =============================================================================
+
+/// Structural equality that compares numbers by numeric value, so `30` and
+/// `30.0` are equal even though `serde_json` stores one as an integer and
+/// the other as a float. Objects compare as key sets (order-insensitive).
+///
+/// # Examples
+///
+/// ```
+/// use jhon::values_equal_loose;
+/// use serde_json::json;
+///
+/// assert!(values_equal_loose(&json!({"age": 30}), &json!({"age": 30.0})));
+/// assert!(!values_equal_loose(&json!({"age": 30}), &json!({"age": 31})));
+/// ```
+pub fn values_equal_loose(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => numbers_equal_loose(x, y),
+        (Value::Array(x), Value::Array(y)) => {
+            x.len() == y.len() && x.iter().zip(y).all(|(a, b)| values_equal_loose(a, b))
+        }
+        (Value::Object(x), Value::Object(y)) => {
+            x.len() == y.len()
+                && x.iter()
+                    .all(|(k, v)| y.get(k).is_some_and(|w| values_equal_loose(v, w)))
+        }
+        _ => a == b,
+    }
+}
+
+/// Parse two documents and report whether they hold the same data,
+/// regardless of key order, whitespace, comments, quoting, or how numbers
+/// were spelled (`0x10` and `16` are equal). Integers and floats stay
+/// distinct, so `1` and `1.0` differ; see [`semantically_equal_loose`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::semantically_equal;
+///
+/// assert!(semantically_equal("a=1, b='x'", "// same\nb = \"x\"\na = 1").unwrap());
+/// assert!(!semantically_equal("a=1", "a=2").unwrap());
+/// ```
+pub fn semantically_equal(a: &str, b: &str) -> Result<bool> {
+    Ok(parse(a)? == parse(b)?)
+}
+
+/// Like [`semantically_equal`], but numbers compare by numeric value as in
+/// [`values_equal_loose`], so `1` and `1.0` are equal.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{semantically_equal, semantically_equal_loose};
+///
+/// assert!(!semantically_equal("ratio=1", "ratio=1.0").unwrap());
+/// assert!(semantically_equal_loose("ratio=1", "ratio=1.0").unwrap());
+/// ```
+pub fn semantically_equal_loose(a: &str, b: &str) -> Result<bool> {
+    Ok(values_equal_loose(&parse(a)?, &parse(b)?))
+}
+
+fn numbers_equal_loose(x: &Number, y: &Number) -> bool {
+    // Two integers compare exactly, so large i64/u64 values that share an
+    // f64 approximation are not reported equal.
+    if let (Some(a), Some(b)) = (number_as_i128(x), number_as_i128(y)) {
+        return a == b;
+    }
+    match (x.as_f64(), y.as_f64()) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+fn number_as_i128(n: &Number) -> Option<i128> {
+    n.as_i64()
+        .map(i128::from)
+        .or_else(|| n.as_u64().map(i128::from))
+}
+
+/// Count every value in the tree, containers and scalars alike, including
+/// `value` itself.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::node_count;
+/// use serde_json::json;
+///
+/// assert_eq!(node_count(&json!({"a": [1, 2], "b": null})), 5);
+/// ```
+pub fn node_count(value: &Value) -> usize {
+    1 + match value {
+        Value::Array(arr) => arr.iter().map(node_count).sum(),
+        Value::Object(map) => map.values().map(node_count).sum(),
+        _ => 0,
+    }
+}
+
+/// Nesting depth in containers: a scalar is 0, a flat object or array
+/// (empty or not) is 1, and each further level of nesting adds one.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::max_depth;
+/// use serde_json::json;
+///
+/// assert_eq!(max_depth(&json!(7)), 0);
+/// assert_eq!(max_depth(&json!({"a": [1, {"b": {}}]})), 4);
+/// ```
+pub fn max_depth(value: &Value) -> usize {
+    match value {
+        Value::Array(arr) => 1 + arr.iter().map(max_depth).max().unwrap_or(0),
+        Value::Object(map) => 1 + map.values().map(max_depth).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+/// Every string in the tree with its dotted path, in document order, for
+/// scanning user-facing text (e.g. for localization). Array elements are
+/// addressed as `name[i]`, the form [`get_path`] accepts.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{collect_strings, parse};
+///
+/// let config = parse(r#"title="Hi", menu={items=["Open", 2, "Quit"]}"#).unwrap();
+/// assert_eq!(
+///     collect_strings(&config),
+///     [
+///         ("title".to_string(), "Hi".to_string()),
+///         ("menu.items[0]".to_string(), "Open".to_string()),
+///         ("menu.items[2]".to_string(), "Quit".to_string()),
+///     ]
+/// );
+/// ```
+pub fn collect_strings(value: &Value) -> Vec<(String, String)> {
+    let mut strings = Vec::new();
+    push_strings(value, &mut String::new(), &mut strings);
+    strings
+}
+
+fn push_strings(value: &Value, path: &mut String, strings: &mut Vec<(String, String)>) {
+    let len = path.len();
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map {
+                push_path_key(path, k);
+                push_strings(v, path, strings);
+                path.truncate(len);
+            }
+        }
+        Value::Array(arr) => {
+            for (i, v) in arr.iter().enumerate() {
+                let _ = write!(path, "[{}]", i);
+                push_strings(v, path, strings);
+                path.truncate(len);
+            }
+        }
+        Value::String(s) => strings.push((path.clone(), s.clone())),
+        _ => {}
+    }
+}
+
+/// The environment variables a document references as `${NAME}` in its
+/// string values, in order of first use and without duplicates, so the
+/// environment can be checked before startup. Nothing is looked up or
+/// substituted. `NAME` is ASCII letters, digits and `_`, not starting with
+/// a digit; any other `${...}` text is ignored, as are keys.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::referenced_env_vars;
+///
+/// let text = r#"db = { url = "pg://${DB_HOST}:${DB_PORT}/app", backup = "${DB_HOST}" }"#;
+/// assert_eq!(referenced_env_vars(text).unwrap(), ["DB_HOST", "DB_PORT"]);
+/// ```
+pub fn referenced_env_vars(text: &str) -> Result<Vec<String>> {
+    let mut vars: Vec<String> = Vec::new();
+    for (_, s) in collect_strings(&parse(text)?) {
+        let mut rest = s.as_str();
+        while let Some(start) = rest.find("${") {
+            rest = &rest[start + 2..];
+            let Some(end) = rest.find('}') else { break };
+            let name = &rest[..end];
+            let valid = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+            if valid && !vars.iter().any(|v| v == name) {
+                vars.push(name.to_string());
+            }
+            rest = &rest[end + 1..];
+        }
+    }
+    Ok(vars)
+}
+
+/// Deep-merge `overlay` into `base`. Objects merge key by key, recursively;
+/// keys new to `base` are appended in `overlay`'s order. Any other overlay
+/// value (including arrays and `null`) replaces the base value outright.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::merge;
+/// use serde_json::json;
+///
+/// let mut base = json!({"db": {"host": "a", "port": 1}, "tags": ["x"]});
+/// merge(&mut base, json!({"db": {"port": 2}, "tags": ["y"]}));
+/// assert_eq!(base, json!({"db": {"host": "a", "port": 2}, "tags": ["y"]}));
+/// ```
+pub fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (k, v) in overlay_map {
+                match base_map.get_mut(&k) {
+                    Some(existing) => merge(existing, v),
+                    None => {
+                        base_map.insert(k, v);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// How [`merge_with`] combines two arrays.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum ArrayMerge {
+    /// The overlay array replaces the base array, as in [`merge`].
+    #[default]
+    Replace,
+    /// Upsert by identity: an overlay element that is an object with this
+    /// key is deep-merged into the first base element holding an equal
+    /// value under the key. Unmatched overlay elements are appended, and so
+    /// are overlay elements without the key (or that are not objects), since
+    /// they have no identity to match. Base elements are kept in place.
+    ByKey(String),
+}
+
+/// [`merge`] with a choice of [`ArrayMerge`] strategy, applied to arrays at
+/// every depth, including inside merged elements.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{merge_with, ArrayMerge};
+/// use serde_json::json;
+///
+/// let mut base = json!({"users": [{"id": 1, "role": "dev"}, {"id": 2, "role": "ops"}]});
+/// let overlay = json!({"users": [{"id": 2, "role": "admin"}, {"id": 3, "role": "qa"}]});
+/// merge_with(&mut base, overlay, &ArrayMerge::ByKey("id".to_string()));
+/// assert_eq!(
+///     base,
+///     json!({"users": [
+///         {"id": 1, "role": "dev"},
+///         {"id": 2, "role": "admin"},
+///         {"id": 3, "role": "qa"}
+///     ]})
+/// );
+/// ```
+pub fn merge_with(base: &mut Value, overlay: Value, arrays: &ArrayMerge) {
+    match (base, overlay, arrays) {
+        (Value::Object(base_map), Value::Object(overlay_map), _) => {
+            for (k, v) in overlay_map {
+                match base_map.get_mut(&k) {
+                    Some(existing) => merge_with(existing, v, arrays),
+                    None => {
+                        base_map.insert(k, v);
+                    }
+                }
+            }
+        }
+        (Value::Array(base_arr), Value::Array(overlay_arr), ArrayMerge::ByKey(key)) => {
+            for item in overlay_arr {
+                let matched = item.get(key).and_then(|id| {
+                    base_arr
+                        .iter()
+                        .position(|b| b.get(key) == Some(id) && b.is_object())
+                });
+                match matched {
+                    Some(i) => merge_with(&mut base_arr[i], item, arrays),
+                    None => base_arr.push(item),
+                }
+            }
+        }
+        (base, overlay, _) => *base = overlay,
+    }
+}
+
+/// Merge `layers` in order (see [`merge`]), recording which layer set each
+/// leaf of the result. Returns the merged value and a map from each leaf's
+/// dotted path (see [`get_path`]) to the name of the layer it came from.
+///
+/// Leaves are scalars, arrays (as a whole, never per element), and empty
+/// objects. When a layer replaces a
+/// subtree, the provenance of everything under it is replaced too. Keys are
+/// joined with `.` unescaped, so keys that themselves contain `.` give
+/// ambiguous paths.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{merge_tracked, parse};
+///
+/// let (config, origin) = merge_tracked(vec![
+///     ("defaults".to_string(), parse("db={host=\"a\", port=5432}").unwrap()),
+///     ("local.jhon".to_string(), parse("db={port=5433}").unwrap()),
+/// ]);
+/// assert_eq!(config["db"]["port"], 5433);
+/// assert_eq!(origin["db.port"], "local.jhon");
+/// assert_eq!(origin["db.host"], "defaults");
+/// ```
+pub fn merge_tracked(layers: Vec<(String, Value)>) -> (Value, BTreeMap<String, String>) {
+    let mut merged = Value::Null;
+    let mut provenance = BTreeMap::new();
+    for (source, layer) in layers {
+        merge_recording(
+            &mut merged,
+            layer,
+            &mut String::new(),
+            &source,
+            &mut provenance,
+        );
+    }
+    (merged, provenance)
+}
+
+fn merge_recording(
+    base: &mut Value,
+    overlay: Value,
+    path: &mut String,
+    source: &str,
+    provenance: &mut BTreeMap<String, String>,
+) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (k, v) in overlay_map {
+                let len = path.len();
+                push_path_key(path, &k);
+                match base_map.get_mut(&k) {
+                    Some(existing) => merge_recording(existing, v, path, source, provenance),
+                    None => {
+                        record_leaves(&v, path, source, provenance);
+                        base_map.insert(k, v);
+                    }
+                }
+                path.truncate(len);
+            }
+        }
+        (base, overlay) => {
+            let prefix = format!("{}.", path);
+            provenance.retain(|p, _| !path.is_empty() && p != path && !p.starts_with(&prefix));
+            record_leaves(&overlay, path, source, provenance);
+            *base = overlay;
+        }
+    }
+}
+
+/// A path where [`merge_reporting`] found both sides holding different
+/// scalar values.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// Dotted path (see [`get_path`]) of the conflicting key.
+    pub path: String,
+    /// The value `base` had.
+    pub base: Value,
+    /// The value `overlay` had, which is the one kept.
+    pub overlay: Value,
+}
+
+/// [`merge`], but also report every path where `base` and `overlay` hold
+/// different scalar values. The overlay still wins; the conflicts are only
+/// surfaced. Arrays and objects replacing one another, or replacing a
+/// scalar, are not conflicts. Conflicts are listed in overlay order.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{merge_reporting, parse};
+///
+/// let (config, conflicts) = merge_reporting(
+///     parse("port=8080, host=\"a\"").unwrap(),
+///     parse("port=9090, host=\"a\"").unwrap(),
+/// );
+/// assert_eq!(config["port"], 9090);
+/// assert_eq!(conflicts.len(), 1);
+/// assert_eq!(conflicts[0].path, "port");
+/// assert_eq!(conflicts[0].base, 8080);
+/// ```
+pub fn merge_reporting(mut base: Value, overlay: Value) -> (Value, Vec<Conflict>) {
+    let mut conflicts = Vec::new();
+    merge_conflicts(&mut base, overlay, &mut String::new(), &mut conflicts);
+    (base, conflicts)
+}
+
+fn merge_conflicts(
+    base: &mut Value,
+    overlay: Value,
+    path: &mut String,
+    conflicts: &mut Vec<Conflict>,
+) {
+    match (base, overlay) {
+        (Value::Object(base_map), Value::Object(overlay_map)) => {
+            for (k, v) in overlay_map {
+                match base_map.get_mut(&k) {
+                    Some(existing) => {
+                        let len = path.len();
+                        push_path_key(path, &k);
+                        merge_conflicts(existing, v, path, conflicts);
+                        path.truncate(len);
+                    }
+                    None => {
+                        base_map.insert(k, v);
+                    }
+                }
+            }
+        }
+        (base, overlay) => {
+            let is_scalar = |v: &Value| !v.is_array() && !v.is_object();
+            if is_scalar(base) && is_scalar(&overlay) && *base != overlay {
+                conflicts.push(Conflict {
+                    path: path.clone(),
+                    base: base.clone(),
+                    overlay: overlay.clone(),
+                });
+            }
+            *base = overlay;
+        }
+    }
+}
+
+fn record_leaves(
+    value: &Value,
+    path: &mut String,
+    source: &str,
+    provenance: &mut BTreeMap<String, String>,
+) {
+    match value {
+        Value::Object(map) if !map.is_empty() => {
+            for (k, v) in map {
+                let len = path.len();
+                push_path_key(path, k);
+                record_leaves(v, path, source, provenance);
+                path.truncate(len);
+            }
+        }
+        _ => {
+            provenance.insert(path.clone(), source.to_string());
+        }
+    }
+}
+
+fn push_path_key(path: &mut String, key: &str) {
+    if !path.is_empty() {
+        path.push('.');
+    }
+    path.push_str(key);
+}
+
+/// Look up a value by dotted path. Segments are object keys; a segment
+/// that is a decimal number, or a `[n]` suffix, indexes into an array.
+/// The empty path returns `value` itself.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{get_path, parse};
+///
+/// let config = parse(r#"db={hosts=["a", "b"], port=5432}"#).unwrap();
+/// assert_eq!(get_path(&config, "db.port").unwrap(), 5432);
+/// assert_eq!(get_path(&config, "db.hosts[1]").unwrap(), "b");
+/// assert_eq!(get_path(&config, "db.hosts.0").unwrap(), "a");
+/// assert!(get_path(&config, "db.user").is_none());
+/// ```
+pub fn get_path<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    path_segments(path).try_fold(value, |current, segment| match (current, segment) {
+        (Value::Object(map), PathSegment::Key(k)) => map.get(k),
+        (Value::Array(arr), PathSegment::Key(k)) => arr.get(k.parse::<usize>().ok()?),
+        (Value::Array(arr), PathSegment::Index(i)) => arr.get(i),
+        _ => None,
+    })
+}
+
+/// Mutable counterpart of [`get_path`].
+fn get_path_mut<'v>(value: &'v mut Value, path: &str) -> Option<&'v mut Value> {
+    path_segments(path).try_fold(value, |current, segment| match (current, segment) {
+        (Value::Object(map), PathSegment::Key(k)) => map.get_mut(k),
+        (Value::Array(arr), PathSegment::Key(k)) => arr.get_mut(k.parse::<usize>().ok()?),
+        (Value::Array(arr), PathSegment::Index(i)) => arr.get_mut(i),
+        _ => None,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PathSegment<'p> {
+    Key(&'p str),
+    Index(usize),
+}
+
+/// Split a dotted path into segments, peeling `[n]` suffixes off each part.
+fn path_segments(path: &str) -> impl Iterator<Item = PathSegment<'_>> {
+    path.split('.')
+        .filter(|part| !part.is_empty())
+        .flat_map(|part| {
+            let (key, indices) = match part.find('[') {
+                Some(i) if part.ends_with(']') => (&part[..i], &part[i + 1..part.len() - 1]),
+                _ => (part, ""),
+            };
+            let parsed: Option<Vec<usize>> = if indices.is_empty() {
+                Some(Vec::new())
+            } else {
+                indices.split("][").map(|n| n.parse().ok()).collect()
+            };
+            let mut segments = Vec::new();
+            match parsed {
+                Some(indices) => {
+                    if !key.is_empty() {
+                        segments.push(PathSegment::Key(key));
+                    }
+                    segments.extend(indices.into_iter().map(PathSegment::Index));
+                }
+                // Not a well-formed index suffix — treat the part as a plain key.
+                None => segments.push(PathSegment::Key(part)),
+            }
+            segments
+        })
+}
+
+/// Look up `path` (see [`get_path`]) and deserialize the value into `T`.
+///
+/// A missing path is [`JhonError::InvalidValue`]; a value of the wrong shape
+/// is [`JhonError::WrongType`]. Both carry the path.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{get_typed, parse, JhonError};
+///
+/// let config = parse(r#"server={port=8080}, name="app""#).unwrap();
+/// let port: u16 = get_typed(&config, "server.port").unwrap();
+/// assert_eq!(port, 8080);
+/// assert!(matches!(get_typed::<u16>(&config, "name"), Err(JhonError::WrongType { .. })));
+/// ```
+pub fn get_typed<T: de::DeserializeOwned>(value: &Value, path: &str) -> Result<T> {
+    let found = get_path(value, path).ok_or_else(|| JhonError::InvalidValue {
+        key: path.to_string(),
+        msg: "missing".to_string(),
+    })?;
+    T::deserialize(found).map_err(|e| JhonError::WrongType {
+        key: path.to_string(),
+        msg: e.to_string(),
+    })
+}
+
+/// Sort the array at `array_path` (see [`get_path`]) by the value of `key`
+/// in each element, so configs can be compared without depending on the
+/// order entries were written in.
+///
+/// Numbers sort before strings; numbers compare numerically and strings
+/// lexicographically. Elements that are not objects, lack `key`, or hold a
+/// non-string, non-number value there sort last. The sort is stable, so
+/// ties keep their original relative order.
+///
+/// Returns [`JhonError::InvalidValue`] if the path is missing and
+/// [`JhonError::WrongType`] if it does not point at an array.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{sort_objects_by, parse};
+/// use serde_json::json;
+///
+/// let mut config = parse(r#"users=[{name="bo"}, {id=3}, {name="al"}]"#).unwrap();
+/// sort_objects_by(&mut config, "users", "name").unwrap();
+/// assert_eq!(config["users"], json!([{"name": "al"}, {"name": "bo"}, {"id": 3}]));
+/// ```
+pub fn sort_objects_by(value: &mut Value, array_path: &str, key: &str) -> Result<()> {
+    let found = get_path_mut(value, array_path).ok_or_else(|| JhonError::InvalidValue {
+        key: array_path.to_string(),
+        msg: "missing".to_string(),
+    })?;
+    let Value::Array(arr) = found else {
+        return Err(JhonError::WrongType {
+            key: array_path.to_string(),
+            msg: "expected an array".to_string(),
+        });
+    };
+
+    enum SortKey<'a> {
+        Number(f64),
+        String(&'a str),
+        Missing,
+    }
+    impl SortKey<'_> {
+        /// A total order: numbers, then strings, then everything else.
+        fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+            match (self, other) {
+                (SortKey::Number(a), SortKey::Number(b)) => a.total_cmp(b),
+                (SortKey::String(a), SortKey::String(b)) => a.cmp(b),
+                _ => self.rank().cmp(&other.rank()),
+            }
+        }
+
+        fn rank(&self) -> u8 {
+            match self {
+                SortKey::Number(_) => 0,
+                SortKey::String(_) => 1,
+                SortKey::Missing => 2,
+            }
+        }
+    }
+    fn sort_key<'a>(element: &'a Value, key: &str) -> SortKey<'a> {
+        match element.get(key) {
+            Some(Value::Number(n)) => SortKey::Number(n.as_f64().unwrap_or(f64::NAN)),
+            Some(Value::String(s)) => SortKey::String(s),
+            _ => SortKey::Missing,
+        }
+    }
+    arr.sort_by(|a, b| sort_key(a, key).cmp(&sort_key(b, key)));
+    Ok(())
+}
+
+/// Pretty-serialize only the sub-value at `path` (see [`get_path`]).
+/// Returns `None` when the path does not exist.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse, serialize_path_pretty};
+///
+/// let config = parse(r#"server={tls={enabled=true}}, name="x""#).unwrap();
+/// assert_eq!(
+///     serialize_path_pretty(&config, "server.tls", "  ").unwrap(),
+///     "enabled = true"
+/// );
+/// ```
+pub fn serialize_path_pretty(value: &Value, path: &str, indent: &str) -> Option<String> {
+    get_path(value, path).map(|v| serialize_pretty(v, indent))
+}
+
+/// Flatten `value` into environment variables: one per scalar leaf, named by
+/// joining `prefix` and the keys on the way down with `_`, and uppercased.
+/// Array elements use their index as the key (`APP_TAGS_0`).
+///
+/// Any character in a key other than an ASCII letter or digit becomes `_`,
+/// so `log-level` gives `LOG_LEVEL`; keys that collide after this mapping
+/// keep the last value in document order. Strings are exported without
+/// quotes, numbers as the compact serializer writes them, and `null` as the
+/// empty string. Empty objects and arrays have no leaves and export nothing.
+/// An empty `prefix` adds nothing to the names.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse, to_env};
+///
+/// let config = parse(r#"server={port=5432, hosts=["a", "b"]}"#).unwrap();
+/// let env = to_env(&config, "app");
+/// assert_eq!(env["APP_SERVER_PORT"], "5432");
+/// assert_eq!(env["APP_SERVER_HOSTS_1"], "b");
+/// ```
+pub fn to_env(value: &Value, prefix: &str) -> BTreeMap<String, String> {
+    let mut env = BTreeMap::new();
+    let mut name = String::new();
+    push_env_segment(&mut name, prefix);
+    collect_env(value, &mut name, &mut env);
+    env
+}
+
+fn collect_env(value: &Value, name: &mut String, env: &mut BTreeMap<String, String>) {
+    let mut descend = |segment: &str, child: &Value| {
+        let len = name.len();
+        push_env_segment(name, segment);
+        collect_env(child, name, env);
+        name.truncate(len);
+    };
+    match value {
+        Value::Object(map) => map.iter().for_each(|(k, v)| descend(k, v)),
+        Value::Array(arr) => arr
+            .iter()
+            .enumerate()
+            .for_each(|(i, v)| descend(&i.to_string(), v)),
+        Value::String(s) => {
+            env.insert(name.clone(), s.clone());
+        }
+        Value::Null => {
+            env.insert(name.clone(), String::new());
+        }
+        _ => {
+            let mut text = String::new();
+            serialize_compact(value, &SerializeOptions::DEFAULT, &mut text);
+            env.insert(name.clone(), text);
+        }
+    }
+}
+
+/// Append `_SEGMENT` (no `_` at the start of the name) with `segment`
+/// uppercased and anything but ASCII alphanumerics replaced by `_`.
+fn push_env_segment(name: &mut String, segment: &str) {
+    if segment.is_empty() {
+        return;
+    }
+    if !name.is_empty() {
+        name.push('_');
+    }
+    name.extend(segment.chars().map(|c| {
+        if c.is_ascii_alphanumeric() {
+            c.to_ascii_uppercase()
+        } else {
+            '_'
+        }
+    }));
+}
+
+/// Write the top-level scalar entries of an object as `.env` lines,
+/// `KEY=value`, one per line with a final newline, in document order.
+///
+/// Keys are uppercased with [`to_env`]'s character mapping. Numbers and
+/// booleans are written as the compact serializer writes them and `null`
+/// as an empty value. Strings made only of ASCII letters, digits and
+/// `_-.,:/@+` are written bare; any other string is double-quoted, with
+/// `\`, `"`, `$`, newlines and carriage returns escaped.
+///
+/// This is a shallow export: a nested object or array is an error
+/// ([`JhonError::WrongType`] naming its key) rather than being skipped, so
+/// nothing is silently lost. Use [`to_env`] to flatten nested values. A
+/// document that is not an object is also a `WrongType` error, except an
+/// empty one, which gives an empty string.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse, serialize_env_style};
+///
+/// let config = parse(r#"db-host="localhost", port=5432, motd="hi there""#).unwrap();
+/// assert_eq!(
+///     serialize_env_style(&config).unwrap(),
+///     "DB_HOST=localhost\nPORT=5432\nMOTD=\"hi there\"\n"
+/// );
+/// ```
+pub fn serialize_env_style(value: &Value) -> Result<String> {
+    let map = match value {
+        Value::Object(map) => map,
+        Value::Null => return Ok(String::new()),
+        _ => {
+            return Err(JhonError::WrongType {
+                key: String::new(),
+                msg: "expected an object of key=value entries".to_string(),
+            });
+        }
+    };
+    let mut out = String::new();
+    for (k, v) in map {
+        let mut name = String::new();
+        push_env_segment(&mut name, k);
+        out.push_str(&name);
+        out.push('=');
+        match v {
+            Value::Object(_) | Value::Array(_) => {
+                return Err(JhonError::WrongType {
+                    key: k.clone(),
+                    msg: "nested values have no .env form; flatten them with to_env".to_string(),
+                });
+            }
+            Value::Null => {}
+            Value::String(s) => push_env_value(s, &mut out),
+            _ => serialize_compact(v, &SerializeOptions::DEFAULT, &mut out),
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// Write `value` as a `.env` file: every scalar leaf becomes a `KEY=value`
+/// line, named and flattened exactly as by [`to_env`] (nested keys joined
+/// with `_`, array elements by index, uppercased) and sorted by name. Values
+/// are quoted as by [`serialize_env_style`], so ones with spaces or shell
+/// metacharacters are double-quoted.
+///
+/// Reading the output back with [`from_dotenv`] gives the flat object of
+/// strings that [`to_env`] describes; nesting and value types are not
+/// restored.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse, to_dotenv};
+///
+/// let config = parse(r#"db={host="h", port=5432}, motd="hi there""#).unwrap();
+/// assert_eq!(to_dotenv(&config), "DB_HOST=h\nDB_PORT=5432\nMOTD=\"hi there\"\n");
+/// ```
+pub fn to_dotenv(value: &Value) -> String {
+    let mut out = String::new();
+    for (name, v) in to_env(value, "") {
+        out.push_str(&name);
+        out.push('=');
+        if !v.is_empty() {
+            push_env_value(&v, &mut out);
+        }
+        out.push('\n');
+    }
+    out
+}
+
+/// Read a `.env` file into a flat object of string values, in file order.
+///
+/// Each line is `KEY=value`, optionally prefixed by `export `. Blank lines
+/// and lines starting with `#` are skipped. A value is either
+/// double-quoted, with `\\`, `\"`, `\$`, `\n` and `\r` escapes; single-quoted
+/// and taken literally; or bare, trimmed and ending at a ` #` comment. A
+/// key set twice keeps its last value. A line without `=`, an empty or
+/// spaced key, or an unterminated quote is a [`JhonError::Syntax`] at that
+/// line.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::from_dotenv;
+/// use serde_json::json;
+///
+/// let env = from_dotenv("# db\nexport DB_HOST=localhost\nMOTD=\"hi\\nthere\" # greeting\n").unwrap();
+/// assert_eq!(env, json!({"DB_HOST": "localhost", "MOTD": "hi\nthere"}));
+/// ```
+pub fn from_dotenv(text: &str) -> Result<Value> {
+    let mut map = Map::new();
+    for (i, line) in text.lines().enumerate() {
+        let line_err = |msg: &str| JhonError::Syntax {
+            line: i + 1,
+            col: 1,
+            msg: msg.to_string(),
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let (key, raw) = line
+            .split_once('=')
+            .ok_or_else(|| line_err("expected KEY=value"))?;
+        let key = key.trim();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            return Err(line_err("invalid variable name"));
+        }
+        let raw = raw.trim_start();
+        let (value, rest) = if let Some(quoted) = raw.strip_prefix('"') {
+            let mut value = String::new();
+            let mut chars = quoted.char_indices();
+            let end = loop {
+                match chars.next() {
+                    None => return Err(line_err("unterminated double-quoted value")),
+                    Some((i, '"')) => break i + 1,
+                    Some((_, '\\')) => match chars.next() {
+                        Some((_, 'n')) => value.push('\n'),
+                        Some((_, 'r')) => value.push('\r'),
+                        Some((_, c @ ('\\' | '"' | '$'))) => value.push(c),
+                        Some((_, c)) => {
+                            value.push('\\');
+                            value.push(c);
+                        }
+                        None => return Err(line_err("unterminated double-quoted value")),
+                    },
+                    Some((_, c)) => value.push(c),
+                }
+            };
+            (value, &quoted[end..])
+        } else if let Some(quoted) = raw.strip_prefix('\'') {
+            let end = quoted
+                .find('\'')
+                .ok_or_else(|| line_err("unterminated single-quoted value"))?;
+            (quoted[..end].to_string(), &quoted[end + 1..])
+        } else {
+            let end = raw
+                .char_indices()
+                .find(|&(i, c)| c == '#' && raw[..i].ends_with(char::is_whitespace))
+                .map_or(raw.len(), |(i, _)| i);
+            (raw[..end].trim_end().to_string(), "")
+        };
+        let rest = rest.trim_start();
+        if !rest.is_empty() && !rest.starts_with('#') {
+            return Err(line_err("unexpected text after quoted value"));
+        }
+        map.insert(key.to_string(), Value::String(value));
+    }
+    Ok(Value::Object(map))
+}
+
+/// Append a `.env` string value, bare when that is unambiguous.
+fn push_env_value(s: &str, out: &mut String) {
+    let bare = !s.is_empty()
+        && s.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-.,:/@+".contains(c));
+    if bare {
+        out.push_str(s);
+        return;
+    }
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '\\' | '"' | '$' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+// =============================================================================
+// Document Builder
+//
+// Fluent sugar over `Map` construction for generating config from Rust code.
+// The builder only produces a `Value`; rendering is left to `serialize`.
+// =============================================================================
+
+/// Builds an object [`Value`] key by key. Keys keep insertion order, and
+/// setting a key again replaces its value in place.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{serialize, JhonBuilder};
+///
+/// let config = JhonBuilder::new()
+///     .key("name", "John")
+///     .key("age", 30)
+///     .object("server", |b| b.key("port", 8080))
+///     .build();
+/// assert_eq!(serialize(&config), r#"name="John",age=30,server={port=8080}"#);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct JhonBuilder {
+    map: Map<String, Value>,
+}
+
+impl JhonBuilder {
+    /// An empty object.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `key` to any value convertible to [`Value`]: strings, numbers,
+    /// booleans, `Option`s (`None` is `null`), vectors, or a `Value` itself.
+    pub fn key(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.map.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// Set `key` to a nested object built by `build`.
+    pub fn object(self, key: &str, build: impl FnOnce(JhonBuilder) -> JhonBuilder) -> Self {
+        let nested = build(JhonBuilder::new()).build();
+        self.key(key, nested)
+    }
+
+    /// Set `key` to an array of `items`.
+    pub fn array<T: Into<Value>>(self, key: &str, items: impl IntoIterator<Item = T>) -> Self {
+        let items: Vec<Value> = items.into_iter().map(Into::into).collect();
+        self.key(key, items)
+    }
+
+    /// The finished object.
+    pub fn build(self) -> Value {
+        Value::Object(self.map)
+    }
+}
+
+// =============================================================================
+// Limits
+//
+// One policy for untrusted documents, enforced on the way in and on the way
+// out. Depth counts container levels as in `max_depth`, so the implicit
+// top-level object of a `key=value` document is level 1. `max_keys` counts
+// object keys across the whole document.
+// =============================================================================
+
+/// Resource limits for [`parse_with_limits`] and [`serialize_with_limits`].
+///
+/// The default allows 128 levels of nesting, 100 000 keys and 16 MiB of
+/// text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    pub max_depth: usize,
+    pub max_keys: usize,
+    /// Maximum size in bytes of the input text, or of the serialized output.
+    pub max_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 128,
+            max_keys: 100_000,
+            max_len: 16 << 20,
+        }
+    }
+}
+
+/// Parse like [`parse`], rejecting documents that break `limits` with
+/// [`JhonError::LimitExceeded`].
+///
+/// Length and nesting are checked before parsing, so an over-deep document
+/// is rejected without recursing into it.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse_with_limits, JhonError, Limits};
+///
+/// let limits = Limits { max_depth: 2, ..Default::default() };
+/// assert!(parse_with_limits("a={b=1}", &limits).is_ok());
+/// assert!(matches!(
+///     parse_with_limits("a={b={c=1}}", &limits),
+///     Err(JhonError::LimitExceeded { name: "max_depth", max: 2 })
+/// ));
+/// ```
+pub fn parse_with_limits(text: &str, limits: &Limits) -> Result<Value> {
+    if text.len() > limits.max_len {
+        return Err(limit_exceeded("max_len", limits.max_len));
+    }
+    let mut depth = 0usize;
+    for token in tokens(text) {
+        let Ok(token) = token else {
+            // Lexical errors are reported by the parser proper.
+            break;
+        };
+        match token.kind {
+            TokenKind::OpenBrace | TokenKind::OpenBracket => depth += 1,
+            TokenKind::CloseBrace | TokenKind::CloseBracket => {
+                depth = depth.saturating_sub(1);
+                continue;
+            }
+            kind if is_trivia(kind) => continue,
+            _ => {}
+        }
+        // Any content sits inside the implicit top-level container.
+        if depth + 1 > limits.max_depth {
+            return Err(limit_exceeded("max_depth", limits.max_depth));
+        }
+    }
+    let value = parse(text)?;
+    if key_count_exceeds(&value, limits.max_keys) {
+        return Err(limit_exceeded("max_keys", limits.max_keys));
+    }
+    Ok(value)
+}
+
+/// Serialize compactly like [`serialize`], rejecting values that break
+/// `limits`. Depth and keys are checked before writing anything and fail
+/// with [`JhonError::LimitExceeded`]; `max_len` bounds the output as in
+/// [`serialize_bounded`] and fails with [`JhonError::OutputTooLarge`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{serialize_with_limits, JhonError, Limits};
+/// use serde_json::json;
+///
+/// let limits = Limits { max_depth: 2, ..Default::default() };
+/// assert_eq!(serialize_with_limits(&json!({"a": [1]}), &limits).unwrap(), "a=[1]");
+/// assert!(serialize_with_limits(&json!({"a": [[1]]}), &limits).is_err());
+/// ```
+pub fn serialize_with_limits(value: &Value, limits: &Limits) -> Result<String> {
+    if depth_exceeds(value, limits.max_depth) {
+        return Err(limit_exceeded("max_depth", limits.max_depth));
+    }
+    if key_count_exceeds(value, limits.max_keys) {
+        return Err(limit_exceeded("max_keys", limits.max_keys));
+    }
+    serialize_bounded(value, limits.max_len)
+}
+
+fn limit_exceeded(name: &'static str, max: usize) -> JhonError {
+    JhonError::LimitExceeded { name, max }
+}
+
+fn is_trivia(kind: TokenKind) -> bool {
+    matches!(
+        kind,
+        TokenKind::Whitespace | TokenKind::LineComment | TokenKind::BlockComment
+    )
+}
+
+/// Whether [`max_depth`] of `value` is above `limit`, without recursing
+/// more than `limit + 1` levels.
+fn depth_exceeds(value: &Value, limit: usize) -> bool {
+    let children: Box<dyn Iterator<Item = &Value>> = match value {
+        Value::Array(arr) => Box::new(arr.iter()),
+        Value::Object(map) => Box::new(map.values()),
+        _ => return false,
+    };
+    limit == 0
+        || children
+            .into_iter()
+            .any(|child| depth_exceeds(child, limit - 1))
+}
+
+/// Whether the document holds more than `limit` object keys in total.
+fn key_count_exceeds(value: &Value, limit: usize) -> bool {
+    let mut remaining = limit;
+    let mut stack = vec![value];
+    while let Some(v) = stack.pop() {
+        match v {
+            Value::Array(arr) => stack.extend(arr),
+            Value::Object(map) => {
+                remaining = match remaining.checked_sub(map.len()) {
+                    Some(r) => r,
+                    None => return true,
+                };
+                stack.extend(map.values());
+            }
+            _ => {}
+        }
+    }
+    false
+}
+
+// =============================================================================
+// Schema Validation
+//
+// A schema lists the keys an object may hold and which of them are required.
+// Validation walks the value and reports every violation, not just the first.
+// =============================================================================
+
+/// The expected shape of an object, checked by [`validate`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Schema {
+    /// The known keys.
+    pub fields: Vec<SchemaField>,
+    /// Accept keys that are not listed in `fields`. Default: `false`, so a
+    /// mistyped key is reported as unknown rather than silently ignored.
+    pub allow_unknown: bool,
+}
+
+/// One known key of a [`Schema`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SchemaField {
+    pub key: String,
+    /// Report the key if it is absent.
+    pub required: bool,
+    /// Schema for the key's value, which must then be an object. `None`
+    /// accepts any value.
+    pub schema: Option<Schema>,
+}
+
+impl SchemaField {
+    /// A key that must be present, with any value.
+    pub fn required(key: &str) -> Self {
+        SchemaField {
+            key: key.to_string(),
+            required: true,
+            schema: None,
+        }
+    }
+
+    /// A key that may be absent, with any value.
+    pub fn optional(key: &str) -> Self {
+        SchemaField {
+            required: false,
+            ..SchemaField::required(key)
+        }
+    }
+}
+
+/// A problem found by [`validate`]. `path` is the dotted path (see
+/// [`get_path`]) of the object where it was found; empty for the root.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SchemaViolation {
+    /// A required key is absent.
+    MissingKey { path: String, key: String },
+    /// A key is not in the schema and `allow_unknown` is off. `suggestion`
+    /// is the closest known key, if one is a plausible typo.
+    UnknownKey {
+        path: String,
+        key: String,
+        suggestion: Option<String>,
+    },
+    /// A value with a nested schema is not an object.
+    NotAnObject { path: String },
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let at = |path: &str| {
+            if path.is_empty() {
+                "<root>".to_string()
+            } else {
+                path.to_string()
+            }
+        };
+        match self {
+            SchemaViolation::MissingKey { path, key } => {
+                write!(f, "missing required key '{}' at {}", key, at(path))
+            }
+            SchemaViolation::UnknownKey {
+                path,
+                key,
+                suggestion,
+            } => {
+                write!(f, "unknown key '{}' at {}", key, at(path))?;
+                if let Some(known) = suggestion {
+                    write!(f, " (did you mean '{}'?)", known)?;
+                }
+                Ok(())
+            }
+            SchemaViolation::NotAnObject { path } => {
+                write!(f, "expected an object at {}", at(path))
+            }
+        }
+    }
+}
+
+/// Check `value` against `schema` and return every violation, in document
+/// order; an empty list means the value conforms.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse, validate, Schema, SchemaField};
+///
+/// let schema = Schema {
+///     fields: vec![SchemaField::required("port"), SchemaField::optional("debug")],
+///     ..Default::default()
+/// };
+/// let config = parse("port=80, debbug=true").unwrap();
+/// let problems = validate(&config, &schema);
+/// assert_eq!(
+///     problems[0].to_string(),
+///     "unknown key 'debbug' at <root> (did you mean 'debug'?)"
+/// );
+/// ```
+pub fn validate(value: &Value, schema: &Schema) -> Vec<SchemaViolation> {
+    let mut violations = Vec::new();
+    validate_object(value, schema, "", &mut violations);
+    violations
+}
+
+fn validate_object(value: &Value, schema: &Schema, path: &str, out: &mut Vec<SchemaViolation>) {
+    let Value::Object(map) = value else {
+        out.push(SchemaViolation::NotAnObject {
+            path: path.to_string(),
+        });
+        return;
+    };
+    let child_path = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", path, key)
+        }
+    };
+
+    for (key, v) in map {
+        match schema.fields.iter().find(|f| f.key == *key) {
+            Some(SchemaField {
+                schema: Some(nested),
+                ..
+            }) => validate_object(v, nested, &child_path(key), out),
+            Some(_) => {}
+            None if schema.allow_unknown => {}
+            None => out.push(SchemaViolation::UnknownKey {
+                path: path.to_string(),
+                key: key.clone(),
+                suggestion: closest_key(key, schema),
+            }),
+        }
+    }
+    for field in &schema.fields {
+        if field.required && !map.contains_key(&field.key) {
+            out.push(SchemaViolation::MissingKey {
+                path: path.to_string(),
+                key: field.key.clone(),
+            });
+        }
+    }
+}
+
+/// The known key nearest to `key` by edit distance, if it is close enough
+/// to be a typo: within two edits (or a third of the key's length, if more),
+/// and fewer edits than the key has characters.
+fn closest_key(key: &str, schema: &Schema) -> Option<String> {
+    let len = key.chars().count();
+    let limit = (len / 3).max(2).min(len.saturating_sub(1));
+    schema
+        .fields
+        .iter()
+        .map(|f| (levenshtein(key, &f.key), &f.key))
+        .filter(|&(distance, _)| distance <= limit)
+        .min_by_key(|&(distance, _)| distance)
+        .map(|(_, known)| known.clone())
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+// =============================================================================
+// Unit Values
+//
+// Durations and byte sizes are ordinary strings in JHON. The `units` module
+// interprets them after parsing, so the grammar stays unit-free; the `get_*`
+// accessors apply it to a value looked up by path.
+//
+// A quantity is a non-negative decimal number (`30`, `1.5`) immediately
+// followed by a unit, with no space:
+// - durations: `ns`, `us`, `ms`, `s`, `m`, `h`, `d`
+// - byte sizes: `B`, decimal `KB` `MB` `GB` `TB` `PB` (powers of 1000), and
+//   binary `KiB` `MiB` `GiB` `TiB` `PiB` (powers of 1024)
+// Units are case-sensitive. A plain JSON number is taken as seconds or bytes.
+// =============================================================================
+
+/// Standalone parsers for duration and byte-size strings such as `"30s"` or
+/// `"10MB"`, for values already pulled out of a config.
+///
+/// Errors are [`JhonError::InvalidValue`] with `key` set to the input text.
+pub mod units {
+    use super::{JhonError, Result};
+
+    /// Parse a duration string into seconds.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jhon::units::parse_duration;
+    ///
+    /// assert_eq!(parse_duration("30s").unwrap(), 30.0);
+    /// assert_eq!(parse_duration("1.5h").unwrap(), 5400.0);
+    /// assert!(parse_duration("30").is_err());
+    /// ```
+    pub fn parse_duration(s: &str) -> Result<f64> {
+        duration_secs(s).map_err(|msg| invalid(s, msg))
+    }
+
+    /// Parse a byte-size string into a number of bytes. Fractional amounts
+    /// are accepted when they come out to whole bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use jhon::units::parse_bytesize;
+    ///
+    /// assert_eq!(parse_bytesize("10MB").unwrap(), 10_000_000);
+    /// assert_eq!(parse_bytesize("1.5KiB").unwrap(), 1536);
+    /// assert!(parse_bytesize("10 MB").is_err());
+    /// ```
+    pub fn parse_bytesize(s: &str) -> Result<u64> {
+        bytesize(s).map_err(|msg| invalid(s, msg))
+    }
+
+    fn invalid(s: &str, msg: String) -> JhonError {
+        JhonError::InvalidValue {
+            key: s.to_string(),
+            msg,
+        }
+    }
+
+    /// [`parse_duration`] with a bare error message, for callers that report
+    /// the error against something other than the input text.
+    pub(crate) fn duration_secs(s: &str) -> std::result::Result<f64, String> {
+        let (amount, unit) =
+            split_quantity(s).ok_or_else(|| format!("malformed duration {:?}", s))?;
+        let scale = match unit {
+            "ns" => 1e-9,
+            "us" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            _ => return Err(format!("unknown duration unit {:?}", unit)),
+        };
+        Ok(amount.parse::<f64>().unwrap_or(f64::NAN) * scale)
+    }
+
+    /// [`parse_bytesize`] with a bare error message.
+    pub(crate) fn bytesize(s: &str) -> std::result::Result<u64, String> {
+        let (amount, unit) =
+            split_quantity(s).ok_or_else(|| format!("malformed byte size {:?}", s))?;
+        let multiplier: u128 = match unit {
+            "B" => 1,
+            "KB" => 1_000,
+            "MB" => 1_000_000,
+            "GB" => 1_000_000_000,
+            "TB" => 1_000_000_000_000,
+            "PB" => 1_000_000_000_000_000,
+            "KiB" => 1 << 10,
+            "MiB" => 1 << 20,
+            "GiB" => 1 << 30,
+            "TiB" => 1 << 40,
+            "PiB" => 1 << 50,
+            _ => return Err(format!("unknown byte size unit {:?}", unit)),
+        };
+        // Exact decimal arithmetic: `int.frac × multiplier` must be whole.
+        let (int, frac) = amount.split_once('.').unwrap_or((amount, ""));
+        let too_large = || format!("byte size out of range: {:?}", s);
+        let scale = 10u128
+            .checked_pow(frac.len() as u32)
+            .ok_or_else(too_large)?;
+        let digits: u128 = format!("{}{}", int, frac)
+            .parse()
+            .map_err(|_| too_large())?;
+        let scaled = digits.checked_mul(multiplier).ok_or_else(too_large)?;
+        if scaled % scale != 0 {
+            return Err(format!("{:?} is not a whole number of bytes", s));
+        }
+        u64::try_from(scaled / scale).map_err(|_| too_large())
+    }
+
+    /// Split `"1.5h"` into `("1.5", "h")`. The amount must be digits with at
+    /// most one `.` that has digits on both sides; the unit is everything
+    /// after it.
+    fn split_quantity(s: &str) -> Option<(&str, &str)> {
+        let end = s
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(s.len());
+        let (amount, unit) = s.split_at(end);
+        let mut parts = amount.split('.');
+        let well_formed = parts.next().is_some_and(|p| !p.is_empty())
+            && parts.next().is_none_or(|p| !p.is_empty())
+            && parts.next().is_none();
+        well_formed.then_some((amount, unit))
+    }
+}
+
+/// Read the value at `path` (see [`get_path`]) as a [`Duration`]; strings
+/// are read with [`units::parse_duration`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{get_duration, parse};
+/// use std::time::Duration;
+///
+/// let config = parse(r#"timeout="1.5h", retry=30"#).unwrap();
+/// assert_eq!(get_duration(&config, "timeout").unwrap(), Duration::from_secs(5400));
+/// assert_eq!(get_duration(&config, "retry").unwrap(), Duration::from_secs(30));
+/// ```
+pub fn get_duration(value: &Value, path: &str) -> Result<Duration> {
+    let invalid = |msg: String| JhonError::InvalidValue {
+        key: path.to_string(),
+        msg,
+    };
+    let secs = match get_path(value, path) {
+        None => return Err(invalid("missing".to_string())),
+        Some(Value::Number(n)) => n.as_f64().unwrap_or(f64::NAN),
+        Some(Value::String(s)) => units::duration_secs(s).map_err(invalid)?,
+        Some(other) => return Err(invalid(format!("expected a duration, found {}", other))),
+    };
+    Duration::try_from_secs_f64(secs)
+        .map_err(|_| invalid(format!("duration out of range: {}", secs)))
+}
+
+/// Read the value at `path` (see [`get_path`]) as a number of bytes;
+/// strings are read with [`units::parse_bytesize`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{get_bytesize, parse};
+///
+/// let config = parse(r#"cache="2MiB", upload="1.5KB", page=4096"#).unwrap();
+/// assert_eq!(get_bytesize(&config, "cache").unwrap(), 2 * 1024 * 1024);
+/// assert_eq!(get_bytesize(&config, "upload").unwrap(), 1500);
+/// assert_eq!(get_bytesize(&config, "page").unwrap(), 4096);
+/// ```
+pub fn get_bytesize(value: &Value, path: &str) -> Result<u64> {
+    let invalid = |msg: String| JhonError::InvalidValue {
+        key: path.to_string(),
+        msg,
+    };
+    match get_path(value, path) {
+        None => Err(invalid("missing".to_string())),
+        Some(Value::Number(n)) => n
+            .as_u64()
+            .ok_or_else(|| invalid(format!("expected a whole number of bytes, found {}", n))),
+        Some(Value::String(s)) => units::bytesize(s).map_err(invalid),
+        Some(other) => Err(invalid(format!("expected a byte size, found {}", other))),
+    }
+}
+
+// =============================================================================
+// Spanned Values (source-faithful parse)
+//
+// `parse` normalizes away how a value was written. The spanned path keeps the
+// details a formatter needs to re-emit the author's choices — the delimiter
+// of each string (including raw strings), same-line trailing comments, `///`
+// doc comments on keys, and comments above array elements — alongside the
+// byte range of every value.
+// =============================================================================
+
+/// The delimiter a string literal was written with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StringKind {
+    /// `"..."`
+    #[default]
+    Double,
+    /// `'...'`
+    Single,
+    /// `r"..."`, `r#"..."#`, ...; the count of `#` on each side.
+    Raw(usize),
+}
+
+/// A parsed value that remembers how it was written in the source.
+///
+/// Produced by [`parse_spanned`]; convert with [`SpannedValue::to_value`] or
+/// re-emit with [`serialize_spanned`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedValue {
+    /// Byte range of the value in the source text. For the document root
+    /// this covers the whole input.
+    pub span: std::ops::Range<usize>,
+    pub node: Node,
+    /// Text of a comment that follows the value on the same line, without
+    /// its `//` / `/* */` markers, e.g. `port = 80 // default`.
+    pub trailing_comment: Option<String>,
+    /// Text of the `///` doc comment lines directly above the key this value
+    /// belongs to, markers stripped and lines joined with `\n`. Ordinary `//`
+    /// comments are never captured here.
+    pub doc: Option<String>,
+    /// For an array element, the comments between the previous element (or
+    /// the opening bracket) and this one, verbatim with their `//` or
+    /// `/* */` markers. The previous element's trailing comment is not
+    /// included. Always empty for other values.
+    pub leading_comments: Vec<String>,
+}
+
+/// The shape of a [`SpannedValue`]. Object entries are kept in source order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Null,
+    Bool(bool),
+    /// `plus_sign` records an explicit leading `+` (see
+    /// [`ParseOptions`]'s `plus_sign_numbers`).
+    Number {
+        value: Number,
+        plus_sign: bool,
+    },
+    String {
+        value: String,
+        kind: StringKind,
+    },
+    Array(Vec<SpannedValue>),
+    Object(Vec<(String, SpannedValue)>),
+}
+
+impl SpannedValue {
+    /// Drop the source details and build the plain JSON value `parse` would
+    /// have returned.
+    pub fn to_value(&self) -> Value {
+        match &self.node {
+            Node::Null => Value::Null,
+            Node::Bool(b) => Value::Bool(*b),
+            Node::Number { value, .. } => Value::Number(value.clone()),
+            Node::String { value, .. } => Value::String(value.clone()),
+            Node::Array(items) => Value::Array(items.iter().map(SpannedValue::to_value).collect()),
+            Node::Object(entries) => Value::Object(
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Parse a JHON document, keeping per-value source details.
+///
+/// Follows the same document rules as [`parse`] (object mode, array mode,
+/// empty → `Null`), but strings remember their quote delimiter so
+/// [`serialize_spanned`] can re-emit them unchanged.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse_spanned, serialize_spanned};
+///
+/// let doc = parse_spanned(r#"x='It\'s',y="ok""#).unwrap();
+/// assert_eq!(serialize_spanned(&doc), r#"x='It\'s',y="ok""#);
+/// ```
+pub fn parse_spanned(text: &str) -> Result<SpannedValue> {
+    parse_spanned_with_options(text, &ParseOptions::DEFAULT)
+}
+
+/// [`parse_spanned`] with some of the extensions in [`ParseOptions`]: the
+/// key options `require_quoted_keys` and `lowercase_keys`, and the value
+/// options `plus_sign_numbers` and `heredoc_strings`. The others are ignored.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse_spanned_with_options, serialize_spanned, ParseOptions};
+///
+/// let opts = ParseOptions { plus_sign_numbers: true, ..Default::default() };
+/// let doc = parse_spanned_with_options("temp=+5,delta=-1", &opts).unwrap();
+/// assert_eq!(serialize_spanned(&doc), "temp=+5,delta=-1");
+/// ```
+pub fn parse_spanned_with_options(text: &str, opts: &ParseOptions) -> Result<SpannedValue> {
+    let opts = ParseOptions {
+        require_quoted_keys: opts.require_quoted_keys,
+        lowercase_keys: opts.lowercase_keys,
+        plus_sign_numbers: opts.plus_sign_numbers,
+        heredoc_strings: opts.heredoc_strings,
+        ..ParseOptions::DEFAULT
+    };
+    // Probe on a copy: the entry parser scans leading comments for docs.
+    let mut parser = Parser::with_options(text.as_bytes(), &opts);
+    let mut probe = parser;
+    probe.skip_ws_and_comments();
+    if probe.current().is_none() {
+        return Ok(SpannedValue {
+            span: 0..text.len(),
+            node: Node::Null,
+            trailing_comment: None,
+            doc: None,
+            leading_comments: Vec::new(),
+        });
+    }
+
+    let node = if is_object_mode(text, &ParseOptions::DEFAULT) {
+        Node::Object(parser.parse_spanned_entries(None)?)
+    } else {
+        Node::Array(parser.parse_spanned_elements(None)?)
+    };
+    Ok(SpannedValue {
+        span: 0..text.len(),
+        node,
+        trailing_comment: None,
+        doc: None,
+        leading_comments: Vec::new(),
+    })
+}
+
+/// Parse a document and collect same-line trailing comments, keyed by the
+/// dotted path of the value they follow (array elements as `name[i]`).
+///
+/// # Examples
+///
+/// ```
+/// use jhon::parse_with_trailing_comments;
+///
+/// let (value, comments) =
+///     parse_with_trailing_comments("limits={\n  max = 1048576 // 1MB in bytes\n}").unwrap();
+/// assert_eq!(value["limits"]["max"], 1048576);
+/// assert_eq!(comments["limits.max"], "1MB in bytes");
+/// ```
+pub fn parse_with_trailing_comments(text: &str) -> Result<(Value, HashMap<String, String>)> {
+    let doc = parse_spanned(text)?;
+    let mut comments = HashMap::new();
+    collect_comments(
+        &doc,
+        String::new(),
+        &|v| v.trailing_comment.clone(),
+        &mut comments,
+    );
+    Ok((doc.to_value(), comments))
+}
+
+/// Parse a document and collect `///` doc comments, keyed by the dotted path
+/// of the key they document. Consecutive `///` lines directly above a key
+/// are joined with `\n`; a blank line or ordinary comment ends the block.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::parse_with_doc_comments;
+///
+/// let (value, docs) = parse_with_doc_comments("/// The server port\nport=8080").unwrap();
+/// assert_eq!(value["port"], 8080);
+/// assert_eq!(docs["port"], "The server port");
+/// ```
+pub fn parse_with_doc_comments(text: &str) -> Result<(Value, HashMap<String, String>)> {
+    let doc = parse_spanned(text)?;
+    let mut docs = HashMap::new();
+    collect_comments(&doc, String::new(), &|v| v.doc.clone(), &mut docs);
+    Ok((doc.to_value(), docs))
+}
+
+/// Parse a document and collect the comments written above array elements,
+/// keyed by the element's path (`features[0]`). Markers are stripped and
+/// several comments above one element are joined with `\n`. A comment after
+/// an element on its own line is that element's trailing comment (see
+/// [`parse_with_trailing_comments`]), not the next element's.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::parse_with_element_comments;
+///
+/// let (value, comments) =
+///     parse_with_element_comments("features = [\n  // core\n  \"auth\"\n  \"logging\"\n]").unwrap();
+/// assert_eq!(value["features"][0], "auth");
+/// assert_eq!(comments["features[0]"], "core");
+/// assert!(!comments.contains_key("features[1]"));
+/// ```
+pub fn parse_with_element_comments(text: &str) -> Result<(Value, HashMap<String, String>)> {
+    let doc = parse_spanned(text)?;
+    let mut comments = HashMap::new();
+    collect_comments(
+        &doc,
+        String::new(),
+        &|v| {
+            (!v.leading_comments.is_empty()).then(|| {
+                let texts: Vec<&str> = v.leading_comments.iter().map(|c| comment_text(c)).collect();
+                texts.join("\n")
+            })
+        },
+        &mut comments,
+    );
+    Ok((doc.to_value(), comments))
+}
+
+/// The text of a `// ...` or `/* ... */` comment without its markers.
+fn comment_text(comment: &str) -> &str {
+    let body = match comment.strip_prefix("//") {
+        Some(line) => line,
+        None => comment
+            .strip_prefix("/*")
+            .and_then(|c| c.strip_suffix("*/"))
+            .unwrap_or(comment),
+    };
+    body.trim()
+}
+
+fn collect_comments(
+    value: &SpannedValue,
+    path: String,
+    comment: &dyn Fn(&SpannedValue) -> Option<String>,
+    out: &mut HashMap<String, String>,
+) {
+    match &value.node {
+        Node::Object(entries) => {
+            for (key, child) in entries {
+                collect_comments(child, join_path_key(&path, key), comment, out);
+            }
+        }
+        Node::Array(items) => {
+            for (i, child) in items.iter().enumerate() {
+                collect_comments(child, format!("{}[{}]", path, i), comment, out);
+            }
+        }
+        _ => {}
+    }
+    if let Some(text) = comment(value) {
+        out.insert(path, text);
+    }
+}
+
+/// Append `key` to a dotted path.
+fn join_path_key(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn parse_spanned_value(&mut self) -> Result<SpannedValue> {
+        self.skip_ws_and_comments();
+        let start = self.pos;
+        let c = self
+            .current()
+            .ok_or_else(|| syntax_err!("Expected value"))?;
+
+        let node = match c {
+            b'"' => Node::String {
+                value: self.parse_string(c)?,
+                kind: StringKind::Double,
+            },
+            b'\'' => Node::String {
+                value: self.parse_string(c)?,
+                kind: StringKind::Single,
+            },
+            b'r' | b'R' => {
+                let hashes = self.input[self.pos + 1..]
+                    .iter()
+                    .take_while(|&&b| b == b'#')
+                    .count();
+                Node::String {
+                    value: self.parse_raw_string()?,
+                    kind: StringKind::Raw(hashes),
+                }
+            }
+            b'[' => {
+                self.advance();
+                Node::Array(self.parse_spanned_elements(Some(b']'))?)
+            }
+            b'{' => {
+                self.advance();
+                Node::Object(self.parse_spanned_entries(Some(b'}'))?)
+            }
+            _ => match self.parse_value()? {
+                Some(Value::String(s)) => Node::String {
+                    value: s,
+                    kind: StringKind::Double,
+                },
+                Some(Value::Number(n)) => Node::Number {
+                    value: n,
+                    plus_sign: c == b'+',
+                },
+                Some(Value::Bool(b)) => Node::Bool(b),
+                _ => Node::Null,
+            },
+        };
+
+        Ok(SpannedValue {
+            span: start..self.pos,
+            node,
+            trailing_comment: self.peek_trailing_comment(),
+            doc: None,
+            leading_comments: Vec::new(),
+        })
+    }
+
+    /// Parse `key=value` entries up to `close` (or EOF for the top level).
+    /// The opening brace, if any, has already been consumed.
+    fn parse_spanned_entries(&mut self, close: Option<u8>) -> Result<Vec<(String, SpannedValue)>> {
+        let mut entries: Vec<(String, SpannedValue)> = Vec::new();
+        let mut gap_start = self.pos;
+        self.skip_ws_and_comments();
+
+        while self.current() != close {
+            if self.current().is_none() {
+                return Err(syntax_err!("Unterminated nested object"));
+            }
+
+            let doc = self.doc_comment_in(gap_start..self.pos);
+            let key = self.parse_key()?;
+            self.skip_ws_and_comments();
+            if self.current() != Some(b'=') {
+                return Err(syntax_err!("Expected '=' after key"));
+            }
+            self.advance();
+            self.skip_ws_and_comments();
+            self.expect_value_for(&key)?;
+
+            let mut value = self.parse_spanned_value()?;
+            value.doc = doc;
+            if entries.iter().any(|(k, _)| *k == key) {
+                return Err(JhonError::DuplicateKey {
+                    line: self.line,
+                    col: self.col,
+                    key,
+                });
+            }
+            entries.push((key, value));
+
+            gap_start = self.pos;
+            if !self.finish_spanned_item(close)? {
+                break;
+            }
+        }
+
+        if close.is_some() {
+            self.advance();
+        }
+        Ok(entries)
+    }
+
+    /// Parse array elements up to `close` (or EOF for an array-mode document).
+    /// The opening bracket, if any, has already been consumed.
+    fn parse_spanned_elements(&mut self, close: Option<u8>) -> Result<Vec<SpannedValue>> {
+        let mut elements: Vec<SpannedValue> = Vec::new();
+        let mut gap_start = self.pos;
+        self.skip_ws_and_comments();
+
+        while self.current() != close {
+            if self.current().is_none() {
+                return Err(syntax_err!("Unterminated array"));
+            }
+            if close.is_none() && self.current() == Some(b'=') {
+                return Err(syntax_err!(
+                    "Cannot mix key=value pairs and bare values at top level"
+                ));
+            }
+
+            let skip_trailing = elements
+                .last()
+                .is_some_and(|e| e.trailing_comment.is_some());
+            let leading_comments = self.comments_in(gap_start..self.pos, skip_trailing);
+            let mut value = self.parse_spanned_value()?;
+            value.leading_comments = leading_comments;
+            elements.push(value);
+
+            gap_start = self.pos;
+            if !self.finish_spanned_item(close)? {
+                break;
+            }
+        }
+
+        if close.is_some() {
+            self.advance();
+        }
+        Ok(elements)
+    }
+
+    /// Source text of each comment in the whitespace/comment gap `gap`. With
+    /// `skip_trailing`, a comment before the gap's first newline is left out:
+    /// it is the previous item's trailing comment.
+    fn comments_in(&self, gap: std::ops::Range<usize>, skip_trailing: bool) -> Vec<String> {
+        let mut probe = *self;
+        probe.pos = gap.start;
+        let mut on_first_line = true;
+        let mut comments = Vec::new();
+        while probe.pos < gap.end {
+            let start = probe.pos;
+            let Ok(kind) = probe.next_token_kind() else {
+                break;
+            };
+            let text = &self.input[start..probe.pos];
+            match kind {
+                TokenKind::LineComment | TokenKind::BlockComment
+                    if !(skip_trailing && on_first_line) =>
+                {
+                    comments.push(String::from_utf8_lossy(text).trim_end().to_string());
+                }
+                TokenKind::Whitespace if text.contains(&b'\n') => on_first_line = false,
+                _ => {}
+            }
+        }
+        comments
+    }
+
+    /// Look (without consuming) for a comment after the current value on
+    /// the same line, optionally past the item's comma. A block comment only
+    /// counts when nothing but blanks follow it before the newline;
+    /// otherwise it leads the next item instead.
+    fn peek_trailing_comment(&self) -> Option<String> {
+        let input = self.input;
+        let mut i = self.pos;
+        let skip_blanks = |mut i: usize| {
+            while matches!(input.get(i), Some(b' ') | Some(b'\t')) {
+                i += 1;
+            }
+            i
+        };
+        i = skip_blanks(i);
+        if input.get(i) == Some(&b',') {
+            i = skip_blanks(i + 1);
+        }
+        if input.get(i) != Some(&b'/') {
+            return None;
+        }
+        let body = match input.get(i + 1) {
+            Some(b'/') => {
+                let end = input[i..]
+                    .iter()
+                    .position(|&b| b == b'\n')
+                    .map_or(input.len(), |n| i + n);
+                &input[i + 2..end]
+            }
+            Some(b'*') => {
+                let end = self.block_comment_end(i)? - 2;
+                let rest = skip_blanks(end + 2);
+                if !matches!(input.get(rest), None | Some(b'\r') | Some(b'\n')) {
+                    return None;
+                }
+                &input[i + 2..end]
+            }
+            _ => return None,
+        };
+        std::str::from_utf8(body)
+            .ok()
+            .map(|text| text.trim().to_string())
+    }
+
+    /// Extract the `///` doc block ending on the line before `gap.end` from
+    /// the whitespace/comment gap between two items. The gap's first line
+    /// belongs to the previous item (or the opening brace) unless the gap
+    /// starts at the beginning of a line.
+    fn doc_comment_in(&self, gap: std::ops::Range<usize>) -> Option<String> {
+        let text = std::str::from_utf8(&self.input[gap.clone()]).ok()?;
+        let mut lines: Vec<&str> = text.split('\n').collect();
+        lines.pop(); // indentation before the key
+        if gap.start > 0 && self.input[gap.start - 1] != b'\n' && !lines.is_empty() {
+            lines.remove(0);
+        }
+
+        let mut doc: Vec<&str> = Vec::new();
+        for line in lines.iter().rev() {
+            let line = line.trim();
+            match line.strip_prefix("///") {
+                Some(body) if !body.starts_with('/') => {
+                    doc.push(body.strip_prefix(' ').unwrap_or(body).trim_end());
+                }
+                _ => break,
+            }
+        }
+        if doc.is_empty() {
+            return None;
+        }
+        doc.reverse();
+        Some(doc.join("\n"))
+    }
+
+    /// Consume the separator after an item. Returns `false` when the
+    /// container ends here (closing delimiter or EOF at the top level).
+    fn finish_spanned_item(&mut self, close: Option<u8>) -> Result<bool> {
+        let (saw_newline, saw_comma) = self.skip_inter_item_separator();
+        if self.current() == close {
+            return Ok(false);
+        }
+        if self.current().is_none() {
+            return Err(syntax_err!("Unterminated container"));
+        }
+        if !saw_newline && !saw_comma {
+            return Err(syntax_err!(
+                "items on the same line must be separated by a comma"
+            ));
+        }
+        Ok(true)
+    }
+}
+
+/// Serialize a [`SpannedValue`] into compact JHON, re-emitting each string
+/// with the quote delimiter it was parsed with. Document-level rules match
+/// [`serialize`]: an empty root emits nothing and a root array emits bare.
+pub fn serialize_spanned(value: &SpannedValue) -> String {
+    let mut result = String::new();
+    match &value.node {
+        Node::Null => {}
+        Node::Array(items) => serialize_spanned_items(items, &mut result),
+        Node::Object(entries) => serialize_spanned_entries(entries, &mut result),
+        _ => serialize_spanned_compact(value, &mut result),
+    }
+    result
+}
+
+fn serialize_spanned_compact(value: &SpannedValue, result: &mut String) {
+    match &value.node {
+        Node::Null => result.push_str("null"),
+        Node::Bool(b) => result.push_str(if *b { "true" } else { "false" }),
+        Node::Number { value, plus_sign } => {
+            if *plus_sign {
+                result.push('+');
+            }
+            serialize_number(value, &SerializeOptions::DEFAULT, result)
+        }
+        Node::String { value, kind } => match kind {
+            StringKind::Double => serialize_string(value, result),
+            StringKind::Single => serialize_single_quoted_string(value, result),
+            StringKind::Raw(hashes) => serialize_raw_string(value, *hashes, result),
+        },
+        Node::Array(items) => {
+            result.push('[');
+            serialize_spanned_items(items, result);
+            result.push(']');
+        }
+        Node::Object(entries) => {
+            result.push('{');
+            serialize_spanned_entries(entries, result);
+            result.push('}');
+        }
+    }
+}
+
+fn serialize_spanned_items(items: &[SpannedValue], result: &mut String) {
+    for (i, item) in items.iter().enumerate() {
+        if i > 0 {
+            result.push(',');
+        }
+        serialize_spanned_compact(item, result);
+    }
+}
+
+fn serialize_spanned_entries(entries: &[(String, SpannedValue)], result: &mut String) {
+    for (i, (key, value)) in entries.iter().enumerate() {
+        if i > 0 {
+            result.push(',');
+        }
+        serialize_key(key, result);
+        result.push('=');
+        serialize_spanned_compact(value, result);
+    }
+}
+
+/// Pretty-print a [`SpannedValue`] the way [`serialize_pretty`] lays out
+/// values — one entry or element per line, every non-empty container
+/// expanded — while keeping the source details: string delimiters, `///`
+/// docs above keys, comments above array elements, and trailing comments,
+/// which are re-emitted as `// text`.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse_spanned, serialize_spanned_pretty};
+///
+/// let doc = parse_spanned("/// Port\nport=8080 // default\ntags=[\n// first\n'a']").unwrap();
+/// assert_eq!(
+///     serialize_spanned_pretty(&doc, "  "),
+///     "/// Port\nport = 8080 // default\ntags = [\n  // first\n  'a'\n]"
+/// );
+/// ```
+pub fn serialize_spanned_pretty(value: &SpannedValue, indent: &str) -> String {
+    let mut result = String::new();
+    match &value.node {
+        Node::Null => {}
+        Node::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    result.push('\n');
+                }
+                write_spanned_element(item, indent, 0, &mut result);
+            }
+        }
+        Node::Object(entries) => {
+            for (i, (key, value)) in entries.iter().enumerate() {
+                if i > 0 {
+                    result.push('\n');
+                }
+                write_spanned_entry(key, value, indent, 0, &mut result);
+            }
+        }
+        _ => write_spanned_pretty(value, indent, 0, &mut result),
+    }
+    result
+}
+
+/// Whether `text` is already in canonical format, i.e. reformatting it with
+/// [`serialize_spanned_pretty`] and `indent` would leave it unchanged — the
+/// check behind a `--check` mode. A single trailing newline is allowed.
+///
+/// The formatter keeps key order, string delimiters, `///` docs, trailing
+/// comments and comments above array elements. Any other comment, such as a
+/// `//` line above a key, would be dropped, so a file containing one is
+/// reported as not formatted.
+///
+/// # Examples
+///
+/// ```
+/// use jhon::is_formatted;
+///
+/// assert!(is_formatted("name = 'app'\nport = 8080 // default\n", "  ").unwrap());
+/// assert!(!is_formatted("name='app', port=8080", "  ").unwrap());
+/// ```
+pub fn is_formatted(text: &str, indent: &str) -> Result<bool> {
+    let formatted = serialize_spanned_pretty(&parse_spanned(text)?, indent);
+    Ok(text.strip_suffix('\n').unwrap_or(text) == formatted)
+}
+
+/// An object entry at `depth`, preceded by its doc comment. The cursor is at
+/// the start of a line.
+fn write_spanned_entry(
+    key: &str,
+    value: &SpannedValue,
+    indent: &str,
+    depth: usize,
+    result: &mut String,
+) {
+    if let Some(doc) = &value.doc {
+        for line in doc.split('\n') {
+            push_indent(result, indent, depth);
+            result.push_str("///");
+            if !line.is_empty() {
+                result.push(' ');
+                result.push_str(line);
+            }
+            result.push('\n');
+        }
+    }
+    push_indent(result, indent, depth);
+    serialize_key(key, result);
+    result.push_str(" = ");
+    write_spanned_pretty(value, indent, depth, result);
+}
+
+/// An array element at `depth`, preceded by its leading comments. The
+/// cursor is at the start of a line.
+fn write_spanned_element(value: &SpannedValue, indent: &str, depth: usize, result: &mut String) {
+    for comment in &value.leading_comments {
+        push_indent(result, indent, depth);
+        result.push_str(comment);
+        result.push('\n');
+    }
+    push_indent(result, indent, depth);
+    write_spanned_pretty(value, indent, depth, result);
+}
+
+/// A value whose first line is already indented, followed by its trailing
+/// comment if any.
+fn write_spanned_pretty(value: &SpannedValue, indent: &str, depth: usize, result: &mut String) {
+    match &value.node {
+        Node::Object(entries) if !entries.is_empty() => {
+            result.push('{');
+            for (key, v) in entries {
+                result.push('\n');
+                write_spanned_entry(key, v, indent, depth + 1, result);
+            }
+            result.push('\n');
+            push_indent(result, indent, depth);
+            result.push('}');
+        }
+        Node::Array(items) if !items.is_empty() => {
+            result.push('[');
+            for item in items {
+                result.push('\n');
+                write_spanned_element(item, indent, depth + 1, result);
+            }
+            result.push('\n');
+            push_indent(result, indent, depth);
+            result.push(']');
+        }
+        _ => serialize_spanned_compact(value, result),
+    }
+    if let Some(comment) = &value.trailing_comment {
+        if comment.contains('\n') {
+            let _ = write!(result, " /* {} */", comment);
+        } else {
+            let _ = write!(result, " // {}", comment);
+        }
+    }
+}
+
+/// Write `s` verbatim as a raw string with at least `hashes` `#` on each
+/// side, adding more if `s` contains a `"` followed by that many `#`. Raw
+/// strings are always written with a lowercase `r`.
+fn serialize_raw_string(s: &str, hashes: usize, result: &mut String) {
+    let mut hashes = hashes;
+    while s.contains(&format!("\"{}", "#".repeat(hashes))) {
+        hashes += 1;
+    }
+    let fence = "#".repeat(hashes);
+    let _ = write!(result, "r{fence}\"{s}\"{fence}");
+}
+
+/// Like [``serialize_string``] but delimited by `'`: single quotes are
+/// escaped and double quotes pass through raw.
+fn serialize_single_quoted_string(s: &str, result: &mut String) {
+    result.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => result.push_str("\\'"),
+            '"' => result.push('"'),
+            '\u{2028}' => result.push_str("\\u2028"),
+            '\u{2029}' => result.push_str("\\u2029"),
+            c if c.is_ascii() && ESCAPE[c as usize] != 0 => serialize_escape_byte(c as u8, result),
+            c => result.push(c),
+        }
+    }
+    result.push('\'');
+}
+
+// =============================================================================
+// Borrowed Values (zero-copy parse)
+//
+// `parse` allocates every key and string. `parse_borrowed` instead borrows
+// them from the source text whenever the source spelling is the value
+// itself — bare keys, raw strings, and quoted strings without escapes — and
+// only allocates for strings whose escapes had to be decoded.
+// =============================================================================
+
+/// A parsed value whose keys and strings may borrow from the source text.
+/// Produced by [`parse_borrowed`]. Object entries are kept in source order.
+#[derive(Debug, Clone, PartialEq)]
+pub enum BorrowedValue<'a> {
+    Null,
+    Bool(bool),
+    Number(Number),
+    String(Cow<'a, str>),
+    Array(Vec<BorrowedValue<'a>>),
+    Object(Vec<(Cow<'a, str>, BorrowedValue<'a>)>),
+}
+
+impl BorrowedValue<'_> {
+    /// Copy any borrowed keys and strings into an owned [`Value`] that no
+    /// longer depends on the source text.
+    pub fn into_owned(self) -> Value {
+        match self {
+            BorrowedValue::Null => Value::Null,
+            BorrowedValue::Bool(b) => Value::Bool(b),
+            BorrowedValue::Number(n) => Value::Number(n),
+            BorrowedValue::String(s) => Value::String(s.into_owned()),
+            BorrowedValue::Array(items) => {
+                Value::Array(items.into_iter().map(BorrowedValue::into_owned).collect())
             }
-            if b == b'\\' {
+            BorrowedValue::Object(entries) => Value::Object(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (k.into_owned(), v.into_owned()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// Parse a JHON document without copying keys and strings out of `text`
+/// where possible. Follows the same document rules as [`parse`].
+///
+/// # Examples
+///
+/// ```
+/// use jhon::{parse_borrowed, BorrowedValue};
+/// use std::borrow::Cow;
+///
+/// let text = String::from(r#"name="app", note="a\tb""#);
+/// let doc = parse_borrowed(&text).unwrap();
+/// let BorrowedValue::Object(entries) = &doc else { unreachable!() };
+/// assert!(matches!(entries[0].1, BorrowedValue::String(Cow::Borrowed("app"))));
+/// assert!(matches!(entries[1].1, BorrowedValue::String(Cow::Owned(_))));
+///
+/// let owned = doc.into_owned();
+/// drop(text);
+/// assert_eq!(owned["name"], "app");
+/// ```
+pub fn parse_borrowed(text: &str) -> Result<BorrowedValue<'_>> {
+    let mut parser = Parser::new(text.as_bytes());
+    let mut probe = parser;
+    probe.skip_ws_and_comments();
+    if probe.current().is_none() {
+        return Ok(BorrowedValue::Null);
+    }
+    if is_object_mode(text, &ParseOptions::DEFAULT) {
+        Ok(BorrowedValue::Object(parser.parse_borrowed_entries(None)?))
+    } else {
+        Ok(BorrowedValue::Array(parser.parse_borrowed_elements(None)?))
+    }
+}
+
+impl<'a> Parser<'a> {
+    fn parse_borrowed_value(&mut self) -> Result<BorrowedValue<'a>> {
+        self.skip_ws_and_comments();
+        let c = self
+            .current()
+            .ok_or_else(|| syntax_err!("Expected value"))?;
+        Ok(match c {
+            b'"' | b'\'' => BorrowedValue::String(self.parse_string_cow(c)?),
+            b'r' | b'R' => BorrowedValue::String(self.parse_raw_string_cow()?),
+            b'[' => {
+                self.advance();
+                BorrowedValue::Array(self.parse_borrowed_elements(Some(b']'))?)
+            }
+            b'{' => {
+                self.advance();
+                BorrowedValue::Object(self.parse_borrowed_entries(Some(b'}'))?)
+            }
+            _ => match self.parse_value()? {
+                Some(Value::Number(n)) => BorrowedValue::Number(n),
+                Some(Value::Bool(b)) => BorrowedValue::Bool(b),
+                Some(Value::Null) => BorrowedValue::Null,
+                other @ (Some(Value::String(_) | Value::Array(_) | Value::Object(_)) | None) => {
+                    // Quoted and raw strings and containers are matched
+                    // above, and the default options enable no other string
+                    // or container syntax.
+                    unreachable!("parse_value returned {:?} for a scalar", other)
+                }
+            },
+        })
+    }
+
+    /// Borrowed counterpart of `parse_spanned_entries`.
+    fn parse_borrowed_entries(
+        &mut self,
+        close: Option<u8>,
+    ) -> Result<Vec<(Cow<'a, str>, BorrowedValue<'a>)>> {
+        let mut entries: Vec<(Cow<'a, str>, BorrowedValue<'a>)> = Vec::new();
+        let mut seen: HashSet<Cow<'a, str>> = HashSet::new();
+        self.skip_ws_and_comments();
+
+        while self.current() != close {
+            if self.current().is_none() {
+                return Err(syntax_err!("Unterminated nested object"));
+            }
+
+            let key = self.parse_key_as_written()?;
+            self.skip_ws_and_comments();
+            if self.current() != Some(b'=') {
+                return Err(syntax_err!("Expected '=' after key"));
+            }
+            self.advance();
+            self.skip_ws_and_comments();
+            self.expect_value_for(&key)?;
+
+            let value = self.parse_borrowed_value()?;
+            // Borrowed keys are cloned for free; only escaped keys allocate.
+            if !seen.insert(key.clone()) {
+                return Err(JhonError::DuplicateKey {
+                    line: self.line,
+                    col: self.col,
+                    key: key.into_owned(),
+                });
+            }
+            entries.push((key, value));
+
+            if !self.finish_spanned_item(close)? {
                 break;
             }
-            self.pos += 1;
         }
 
-        if self.pos >= self.input.len() {
-            return Err(syntax_err!("Unterminated string"));
-        }
+        if close.is_some() {
+            self.advance();
+        }
+        Ok(entries)
+    }
+
+    /// Borrowed counterpart of `parse_spanned_elements`.
+    fn parse_borrowed_elements(&mut self, close: Option<u8>) -> Result<Vec<BorrowedValue<'a>>> {
+        let mut elements = Vec::new();
+        self.skip_ws_and_comments();
+
+        while self.current() != close {
+            if self.current().is_none() {
+                return Err(syntax_err!("Unterminated array"));
+            }
+            if close.is_none() && self.current() == Some(b'=') {
+                return Err(syntax_err!(
+                    "Cannot mix key=value pairs and bare values at top level"
+                ));
+            }
+
+            elements.push(self.parse_borrowed_value()?);
+
+            if !self.finish_spanned_item(close)? {
+                break;
+            }
+        }
+
+        if close.is_some() {
+            self.advance();
+        }
+        Ok(elements)
+    }
+}
+
+// =============================================================================
+// Tests
+// =============================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Representative config shared by tests that exercise whole-document
+    /// helpers (paths, comments, traversal).
+    const COMPLEX_EXAMPLE: &str = r#"// Application configuration
+app_name = "ocean-note"
+version = "2.0.0"
+debug = false
+
+database = {
+  host = "localhost"
+  port = 5432
+  credentials = [
+    { user = "root", password = "hunter2" }
+    { user = "admin", password = "s3cret" }
+  ]
+}
+
+limits = {
+  max_file_size = 1048576 // 1MB in bytes
+  max_files_per_user = 100
+}
+
+features = ["auth", "logging", "caching"]
+"#;
+
+    #[test]
+    fn referenced_env_vars_scans_string_values() {
+        let text = r#"
+            db = { host = "${DB_HOST}", port = "${DB_PORT}" }
+            url = "pg://${DB_HOST}:${DB_PORT}"
+            "${NOT_A_VALUE}" = 1
+            odd = ["$PLAIN", "${}", "${1X}", "${A-B}", "${UNCLOSED"]
+        "#;
+        assert_eq!(referenced_env_vars(text).unwrap(), ["DB_HOST", "DB_PORT"]);
+        assert!(referenced_env_vars("a = ").is_err());
+    }
+
+    #[test]
+    fn collect_strings_from_complex_example() {
+        let config = parse(COMPLEX_EXAMPLE).unwrap();
+        let strings = collect_strings(&config);
+        assert_eq!(strings.len(), 10);
+        assert_eq!(
+            strings[0],
+            ("app_name".to_string(), "ocean-note".to_string())
+        );
+        assert!(strings.contains(&(
+            "database.credentials[1].user".to_string(),
+            "admin".to_string()
+        )));
+        assert_eq!(
+            strings.last().unwrap(),
+            &("features[2]".to_string(), "caching".to_string())
+        );
+        for (path, s) in &strings {
+            assert_eq!(get_path(&config, path).unwrap(), s);
+        }
+        assert_eq!(
+            collect_strings(&json!(["a", ["b"]])),
+            [
+                ("[0]".to_string(), "a".to_string()),
+                ("[1][0]".to_string(), "b".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_instrumented_counts_complex_example() {
+        let (value, metrics) = parse_instrumented(COMPLEX_EXAMPLE).unwrap();
+        assert_eq!(value, parse(COMPLEX_EXAMPLE).unwrap());
+        assert_eq!(metrics.bytes, COMPLEX_EXAMPLE.len());
+        // 6 top-level keys, 3 in `database`, 2 in each credential, 2 in `limits`.
+        assert_eq!(metrics.entries, 15);
+        assert_eq!(metrics.values, 21);
+        // root > database > credentials > credential object
+        assert_eq!(metrics.max_depth, 4);
+
+        let (value, metrics) = parse_instrumented("").unwrap();
+        assert_eq!(value, json!(null));
+        assert_eq!(
+            (metrics.entries, metrics.values, metrics.max_depth),
+            (0, 1, 0)
+        );
+    }
+
+    // =========================================================================
+    // §2 — Document Form
+    // =========================================================================
+
+    #[test]
+    fn empty_input_parses_to_null() {
+        assert_eq!(parse("").unwrap(), json!(null));
+    }
+
+    #[test]
+    fn whitespace_only_input_parses_to_null() {
+        assert_eq!(parse("   \n\t\r\n  ").unwrap(), json!(null));
+    }
+
+    #[test]
+    fn comments_only_input_parses_to_null() {
+        assert_eq!(
+            parse("// just a comment\n/* block */").unwrap(),
+            json!(null)
+        );
+    }
+
+    #[test]
+    fn top_level_object_without_braces() {
+        assert_eq!(
+            parse(r#"name="x",port=80"#).unwrap(),
+            json!({"name": "x", "port": 80})
+        );
+    }
+
+    #[test]
+    fn top_level_object_with_braces_is_single_element_array() {
+        // Per SPEC.md §2: top-level `{...}` is always one element of the
+        // implicit top-level array, never a document wrapper.
+        assert_eq!(
+            parse(r#"{name="x",port=80}"#).unwrap(),
+            json!([{"name": "x", "port": 80}])
+        );
+    }
+
+    #[test]
+    fn top_level_explicit_array_is_single_element_array() {
+        // Per SPEC.md §2: top-level `[...]` is always one element of the
+        // implicit top-level array, never a document wrapper.
+        assert_eq!(parse("[1, 2, 3]").unwrap(), json!([[1, 2, 3]]));
+    }
+
+    #[test]
+    fn top_level_scalar_number() {
+        assert_eq!(parse("42").unwrap(), json!([42]));
+    }
+
+    #[test]
+    fn top_level_scalar_string() {
+        assert_eq!(parse(r#""hello""#).unwrap(), json!(["hello"]));
+    }
+
+    #[test]
+    fn top_level_scalar_boolean() {
+        assert_eq!(parse("true").unwrap(), json!([true]));
+        assert_eq!(parse("false").unwrap(), json!([false]));
+    }
+
+    #[test]
+    fn top_level_scalar_null() {
+        assert_eq!(parse("null").unwrap(), json!([null]));
+    }
+
+    #[test]
+    fn top_level_multiple_scalars_newline_separated() {
+        assert_eq!(parse("1\n2\n3").unwrap(), json!([1, 2, 3]));
+    }
+
+    #[test]
+    fn top_level_multiple_scalars_comma_separated() {
+        assert_eq!(parse(r#"1,2,"haha""#).unwrap(), json!([1, 2, "haha"]));
+    }
+
+    #[test]
+    fn top_level_mixed_scalars_and_object() {
+        // The example from the spec change request.
+        assert_eq!(
+            parse("1\n2\n\"haha\"\n{a=4}").unwrap(),
+            json!([1, 2, "haha", {"a": 4}])
+        );
+    }
+
+    #[test]
+    fn top_level_multiple_objects() {
+        assert_eq!(parse("{a=1}\n{b=2}").unwrap(), json!([{"a": 1}, {"b": 2}]));
+    }
+
+    #[test]
+    fn top_level_keyword_as_key_is_object_mode() {
+        // `true`, `false`, `null` in key position are strings, so `true=1`
+        // is object mode, not array mode.
+        assert_eq!(parse("true=1").unwrap(), json!({"true": 1}));
+    }
+
+    #[test]
+    fn top_level_numeric_key_is_object_mode() {
+        // Bare identifiers can contain digits, so `42="x"` is object mode.
+        assert_eq!(parse("42=\"x\"").unwrap(), json!({"42": "x"}));
+    }
+
+    #[test]
+    fn top_level_quoted_string_key_is_object_mode() {
+        assert_eq!(parse(r#""key"="value""#).unwrap(), json!({"key": "value"}));
+    }
+
+    #[test]
+    fn top_level_mixed_pair_then_scalar_is_error() {
+        assert!(parse("a=1\n2").is_err());
+    }
+
+    #[test]
+    fn top_level_mixed_scalar_then_pair_is_error() {
+        assert!(parse("1\na=2").is_err());
+    }
+
+    #[test]
+    fn top_level_array_then_pair_is_error() {
+        assert!(parse("[1, 2] key=value").is_err());
+    }
 
-        // Phase 2: process escapes.
-        let mut bytes: Vec<u8> = self.input[start..self.pos].to_vec();
+    #[test]
+    fn serialize_top_level_array_compact() {
+        assert_eq!(serialize(&json!([1, 2, "haha"])), r#"1,2,"haha""#);
+    }
 
-        while self.pos < self.input.len() {
-            let b = self
-                .advance()
-                .ok_or_else(|| syntax_err!("Unterminated string"))?;
-            if b == quote {
-                return String::from_utf8(bytes)
-                    .map_err(|_| syntax_err!("Invalid UTF-8 in string"));
-            }
-            if b == b'\\' {
-                let escaped = self
-                    .advance()
-                    .ok_or_else(|| syntax_err!("Incomplete escape sequence"))?;
-                match escaped {
-                    b'n' => bytes.push(b'\n'),
-                    b'r' => bytes.push(b'\r'),
-                    b't' => bytes.push(b'\t'),
-                    b'b' => bytes.push(0x08),
-                    b'f' => bytes.push(0x0C),
-                    b'\\' => bytes.push(b'\\'),
-                    b'"' => bytes.push(b'"'),
-                    b'\'' => bytes.push(b'\''),
-                    b'/' => bytes.push(b'/'),
-                    b'x' => {
-                        let v = self.parse_hex_digits(2, "\\x")?;
-                        bytes.push(v as u8);
-                    }
-                    b'u' => {
-                        let code = self.parse_hex_digits(4, "\\u")?;
-                        if (0xD800..=0xDFFF).contains(&code) {
-                            return Err(syntax_err!(
-                                "surrogate code point U+{:04X} requires a pair; \
-                                 surrogate handling is not yet implemented",
-                                code
-                            ));
-                        }
-                        let c = char::from_u32(code).ok_or_else(|| {
-                            syntax_err!("Invalid Unicode code point U+{:04X}", code)
-                        })?;
-                        let mut buf = [0u8; 4];
-                        bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
-                    }
-                    other => {
-                        return Err(syntax_err!("Unknown escape \\{}", other as char));
-                    }
-                }
-            } else {
-                bytes.push(b);
-            }
-        }
-        Err(syntax_err!("Unterminated string"))
+    #[test]
+    fn serialize_top_level_array_pretty() {
+        assert_eq!(
+            serialize_pretty(&json!([1, 2, "haha"]), "  "),
+            "1\n2\n\"haha\""
+        );
     }
 
-    /// Parse `count` hex digits and return the assembled value.
-    fn parse_hex_digits(&mut self, count: usize, label: &str) -> Result<u32> {
-        let mut value = 0u32;
-        for _ in 0..count {
-            let h = self
-                .advance()
-                .ok_or_else(|| syntax_err!("Incomplete {} escape", label))?;
-            let d = (h as char)
-                .to_digit(16)
-                .ok_or_else(|| syntax_err!("Invalid hex digit in {} escape", label))?;
-            value = (value << 4) | d;
-        }
-        Ok(value)
+    #[test]
+    fn serialize_top_level_array_with_object() {
+        assert_eq!(serialize(&json!([1, {"a": 2}])), "1,{a=2}");
     }
 
-    fn parse_raw_string(&mut self) -> Result<String> {
-        self.advance(); // skip 'r'
+    #[test]
+    fn serialize_empty_object_to_empty_string() {
+        assert_eq!(serialize(&json!({})), "");
+    }
 
-        let mut hash_count = 0;
-        while self.current() == Some(b'#') {
-            hash_count += 1;
-            self.advance();
-        }
+    #[test]
+    fn serialize_empty_array_to_empty_string() {
+        assert_eq!(serialize(&json!([])), "");
+    }
 
-        if self.current() != Some(b'"') {
-            return Err(syntax_err!(
-                "Expected opening quote after r and # symbols in raw string"
-            ));
-        }
-        self.advance(); // skip opening quote
+    #[test]
+    fn serialize_top_level_null_to_empty_string() {
+        assert_eq!(serialize(&json!(null)), "");
+    }
 
-        let start = self.pos;
+    #[test]
+    fn serialize_nested_null_preserved() {
+        assert_eq!(serialize(&json!({"a": null})), "a=null");
+    }
 
-        while self.pos < self.input.len() {
-            if self.input[self.pos] == b'"' && self.pos + hash_count < self.input.len() {
-                let is_closing =
-                    (1..=hash_count).all(|j| self.input.get(self.pos + j) == Some(&b'#'));
+    #[test]
+    fn serialize_nested_array_preserved() {
+        assert_eq!(serialize(&json!({"a": [1, 2, 3]})), "a=[1,2,3]");
+    }
 
-                if is_closing {
-                    let s = std::str::from_utf8(&self.input[start..self.pos])
-                        .map_err(|_| syntax_err!("Invalid UTF-8 in raw string"))?
-                        .to_string();
-                    self.pos += hash_count + 1;
-                    return Ok(s);
-                }
-            }
-            self.pos += 1;
-        }
+    // =========================================================================
+    // §3.2 — Comments
+    // =========================================================================
 
-        Err(syntax_err!("Unterminated raw string"))
+    #[test]
+    fn single_line_comment_trailing() {
+        assert_eq!(
+            parse(r#"key="value" // trailing comment"#).unwrap(),
+            json!({"key": "value"})
+        );
     }
 
-    // Number parser — handles decimal, hex, octal, binary, floats, and
-    // underscores per SPEC.md §3.5.
-    fn parse_number(&mut self) -> Result<Value> {
-        let negative = self.current() == Some(b'-');
-        if negative {
-            self.advance();
-        }
+    #[test]
+    fn block_comment_inline() {
+        assert_eq!(
+            parse(r#"key=/* inline */"value""#).unwrap(),
+            json!({"key": "value"})
+        );
+    }
 
-        // Radix prefix detection. Lowercase only — uppercase variants error.
-        let radix = if self.pos + 1 < self.input.len() && self.input[self.pos] == b'0' {
-            match self.input[self.pos + 1] {
-                b'x' => Some(16u32),
-                b'o' => Some(8u32),
-                b'b' => Some(2u32),
-                b'X' | b'O' | b'B' => {
-                    return Err(syntax_err!(
-                        "uppercase radix prefix 0{} not allowed; use lowercase",
-                        self.input[self.pos + 1] as char
-                    ));
-                }
-                _ => None,
-            }
-        } else {
-            None
-        };
+    #[test]
+    fn block_comment_spanning_lines() {
+        assert_eq!(
+            parse("key=/* multi\nline\ncomment */\"value\"").unwrap(),
+            json!({"key": "value"})
+        );
+    }
 
-        // Scan the literal.
-        let literal: String;
-        let mut is_float = false;
+    #[test]
+    fn block_comment_disables_a_region_of_entries() {
+        assert_eq!(parse("/* a=1\n b=2 */ c=3").unwrap(), json!({"c": 3}));
+        // The region ends at the first `*/`, even one inside quotes.
+        assert!(parse("a=1\n/*\nb=\"*/ not yet\"\n*/\nd=4").is_err());
+        assert_eq!(
+            parse("server={\n  host=\"h\"\n  /* port=80\n  tls=true */\n}\nname=\"x\"").unwrap(),
+            json!({"server": {"host": "h"}, "name": "x"})
+        );
+        // Only the commented entries are gone; `//` inside the region is inert.
+        assert_eq!(
+            parse("a=1, /* b=2, // c=3\n d=4, */ e=5").unwrap(),
+            json!({"a": 1, "e": 5})
+        );
+        assert_eq!(parse("/* a=1\n b=2 */").unwrap(), Value::Null);
+    }
 
-        if let Some(rdx) = radix {
-            self.advance(); // '0'
-            self.advance(); // 'x' / 'o' / 'b'
-            literal = self.scan_radix_digits(rdx)?;
-        } else {
-            let int_part = self.scan_dec_digits()?;
-            let mut s = int_part;
-            if self.current() == Some(b'.') {
-                is_float = true;
-                s.push('.');
-                self.advance();
-                s.push_str(&self.scan_dec_digits()?);
-            }
-            if matches!(self.current(), Some(b'e') | Some(b'E')) {
-                is_float = true;
-                s.push('e');
-                self.advance();
-                if matches!(self.current(), Some(b'+') | Some(b'-')) {
-                    s.push(self.current().unwrap() as char);
-                    self.advance();
-                }
-                s.push_str(&self.scan_dec_digits()?);
-            }
-            literal = s;
-        }
+    #[test]
+    fn comment_before_closing_brace() {
+        assert_eq!(
+            parse("obj={a=1 // last\n}").unwrap(),
+            json!({"obj": {"a": 1}})
+        );
+        assert_eq!(
+            parse("obj={a=1, // after comma\n}").unwrap(),
+            json!({"obj": {"a": 1}})
+        );
+        assert_eq!(
+            parse("obj={a=1 /* last */}, b=2").unwrap(),
+            json!({"obj": {"a": 1}, "b": 2})
+        );
+        assert_eq!(
+            parse("obj={ // only a comment\n}").unwrap(),
+            json!({"obj": {}})
+        );
+        assert_eq!(
+            parse("obj={inner={a=1 // x\n} // y\n}").unwrap(),
+            json!({"obj": {"inner": {"a": 1}}})
+        );
+    }
 
-        // Reject type suffixes (u8, i32, f64, ...) — next byte looks like u/i/f
-        // followed by alphanumeric.
-        if let Some(b) = self.current()
-            && matches!(b, b'u' | b'i' | b'f')
-            && self
-                .input
-                .get(self.pos + 1)
-                .copied()
-                .filter(|c| c.is_ascii_alphanumeric())
-                .is_some()
-        {
-            return Err(syntax_err!(
-                "number type suffix not allowed (saw '{}{}')",
-                b as char,
-                self.input[self.pos + 1] as char
-            ));
+    #[test]
+    fn comment_before_closing_bracket() {
+        assert_eq!(
+            parse("arr=[1, 2 // last\n]").unwrap(),
+            json!({"arr": [1, 2]})
+        );
+        assert_eq!(
+            parse("arr=[1, 2, // after comma\n]").unwrap(),
+            json!({"arr": [1, 2]})
+        );
+        assert_eq!(
+            parse("arr=[1 /* last */], b=2").unwrap(),
+            json!({"arr": [1], "b": 2})
+        );
+        assert_eq!(
+            parse("arr=[ // only a comment\n]").unwrap(),
+            json!({"arr": []})
+        );
+        // A top-level `[...]` is itself an array-mode element.
+        assert_eq!(parse("[[1 // x\n] // y\n]").unwrap(), json!([[[1]]]));
+        // A `]` or `}` inside the comment must not close the container.
+        assert_eq!(
+            parse("arr=[1 // ] }\n, 2]").unwrap(),
+            json!({"arr": [1, 2]})
+        );
+    }
+
+    #[test]
+    fn type_annotated_keys_are_rejected() {
+        // JHON has no `key: type = value` annotations, typed null or
+        // otherwise; the annotation is a syntax error, not a silent null.
+        for text in ["port: int = null", "cert: string = null", "port: int = 80"] {
+            assert!(
+                matches!(parse(text), Err(JhonError::Syntax { .. })),
+                "{}",
+                text
+            );
         }
+        assert_eq!(parse("port = null").unwrap(), json!({"port": null}));
+    }
+
+    #[test]
+    fn unterminated_errors_report_where_the_opener_was() {
+        let msg = |text: &str| match parse(text) {
+            Err(JhonError::Syntax { msg, .. }) => msg,
+            other => panic!("expected syntax error, got {:?}", other),
+        };
+        assert_eq!(
+            msg("a = 1\nk = [1,\n  2"),
+            "Unterminated array started at line 2, column 5"
+        );
+        assert_eq!(
+            msg("a = \"abc"),
+            "Unterminated string started at line 1, column 5"
+        );
+        assert_eq!(
+            msg("a = \"x\\ny"),
+            "Unterminated string started at line 1, column 5"
+        );
+        assert_eq!(
+            msg("\n\no = {x = 1"),
+            "Unterminated nested object started at line 3, column 5"
+        );
+        assert_eq!(
+            msg("s = r#\"abc\""),
+            "Unterminated raw string started at line 1, column 5"
+        );
+    }
 
-        // Assemble signed form for parsing.
-        let signed = if negative {
-            format!("-{}", literal)
-        } else {
-            literal
+    #[test]
+    fn unterminated_block_comment_is_error() {
+        assert!(parse("key=/* unterminated").is_err());
+    }
+
+    #[test]
+    fn nested_block_comments_track_depth() {
+        let opts = ParseOptions {
+            nested_block_comments: true,
+            ..Default::default()
         };
+        let one = "a=1, /* outer /* inner */ still commented */ b=2";
+        assert!(parse(one).is_err());
+        assert_eq!(
+            parse_with_options(one, &opts).unwrap(),
+            json!({"a": 1, "b": 2})
+        );
 
-        if let Some(rdx) = radix {
-            return parse_radix_literal(&signed, rdx);
-        }
+        let two = "/* x=1\n/* y=2 /* z=3 */ */\nw=4 */\nv=5";
+        assert_eq!(parse_with_options(two, &opts).unwrap(), json!({"v": 5}));
+        // Without nesting the comment ends at the first `*/`.
+        assert_eq!(parse("/* x /* y */ v=5").unwrap(), json!({"v": 5}));
 
-        if !is_float {
-            if let Ok(i) = signed.parse::<i64>() {
-                return Ok(Value::Number(Number::from(i)));
-            }
-            if let Ok(u) = signed.parse::<u64>() {
-                return Ok(Value::Number(Number::from(u)));
-            }
-            if let Ok(i) = signed.parse::<i128>()
-                && let Some(n) = Number::from_f64(i as f64)
-            {
-                return Ok(Value::Number(n));
-            }
-            if let Ok(u) = signed.parse::<u128>()
-                && let Some(n) = Number::from_f64(u as f64)
-            {
-                return Ok(Value::Number(n));
+        let err = parse_with_options("a=1\n/* outer /* inner */ b=2", &opts).unwrap_err();
+        assert_eq!(
+            err,
+            JhonError::Syntax {
+                line: 2,
+                col: 1,
+                msg: "Unterminated block comment".to_string(),
             }
-        }
-
-        let f = signed
-            .parse::<f64>()
-            .map_err(|_| syntax_err!("could not parse number: {}", signed))?;
-        Number::from_f64(f)
-            .map(Value::Number)
-            .ok_or_else(|| syntax_err!("invalid number value: {}", signed))
+        );
+        assert!(parse_with_options("a=1 /* */ */", &opts).is_err());
+        assert!(parse_with_options("/* /* only comments */", &opts).is_err());
     }
 
-    /// Scan a run of decimal digits with Rust-style underscore separators.
-    /// Validates that underscores occur only between two digits.
-    fn scan_dec_digits(&mut self) -> Result<String> {
-        let mut s = String::new();
-        let mut last_was_under = false;
-        let mut has_digit = false;
-        while self.pos < self.input.len() {
-            let b = self.input[self.pos];
-            if b.is_ascii_digit() {
-                s.push(b as char);
-                last_was_under = false;
-                has_digit = true;
-                self.pos += 1;
-            } else if b == b'_' {
-                if !has_digit || last_was_under {
-                    return Err(syntax_err!("invalid underscore placement in number"));
-                }
-                last_was_under = true;
-                self.pos += 1;
-            } else {
-                break;
+    #[test]
+    fn nested_block_comments_with_heredocs_and_barewords() {
+        let opts = ParseOptions {
+            nested_block_comments: true,
+            heredoc_strings: true,
+            bareword_values: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_options("msg = <<EOT\nit's here\nEOT\n", &opts).unwrap(),
+            json!({"msg": "it's here"})
+        );
+        assert_eq!(
+            parse_with_options("msg = <<EOT\nopen /* here\nEOT\nn = 1", &opts).unwrap(),
+            json!({"msg": "open /* here", "n": 1})
+        );
+        assert_eq!(
+            parse_with_options("p = /usr/bin\nq = 1", &opts).unwrap(),
+            json!({"p": "/usr/bin", "q": 1})
+        );
+        let err = parse_with_options("p = /usr/bin\n/* /* */", &opts).unwrap_err();
+        assert_eq!(
+            err,
+            JhonError::Syntax {
+                line: 2,
+                col: 1,
+                msg: "Unterminated block comment".to_string(),
             }
-        }
-        if !has_digit {
-            return Err(syntax_err!("number requires at least one digit"));
-        }
-        if last_was_under {
-            return Err(syntax_err!("number cannot end with underscore"));
-        }
-        Ok(s)
+        );
     }
 
-    /// Scan a run of digits in the given radix with underscore separators.
-    fn scan_radix_digits(&mut self, radix: u32) -> Result<String> {
-        let mut s = String::new();
-        let mut last_was_under = false;
-        let mut has_digit = false;
-        while self.pos < self.input.len() {
-            let b = self.input[self.pos];
-            if (b as char).is_digit(radix) {
-                s.push(b as char);
-                last_was_under = false;
-                has_digit = true;
-                self.pos += 1;
-            } else if b == b'_' {
-                if !has_digit || last_was_under {
-                    return Err(syntax_err!("invalid underscore placement in number"));
-                }
-                last_was_under = true;
-                self.pos += 1;
-            } else {
-                break;
-            }
-        }
-        if !has_digit {
-            return Err(syntax_err!(
-                "number requires at least one digit after radix prefix"
-            ));
-        }
-        if last_was_under {
-            return Err(syntax_err!("number cannot end with underscore"));
-        }
-        Ok(s)
+    // =========================================================================
+    // §3.3 — Bare Keys
+    // =========================================================================
+
+    #[test]
+    fn simple_identifier_key() {
+        assert_eq!(
+            parse(r#"keyname="value""#).unwrap(),
+            json!({"keyname": "value"})
+        );
     }
 
-    fn parse_array(&mut self) -> Result<(Value, usize)> {
-        self.advance(); // skip '['
+    #[test]
+    fn keyword_true_as_string_key() {
+        assert_eq!(parse(r#"true="yes""#).unwrap(), json!({"true": "yes"}));
+    }
 
-        let mut elements = Vec::new();
+    #[test]
+    fn keyword_false_as_string_key() {
+        assert_eq!(parse(r#"false="no""#).unwrap(), json!({"false": "no"}));
+    }
 
-        self.skip_ws_and_comments();
+    #[test]
+    fn keyword_null_as_string_key() {
+        assert_eq!(
+            parse(r#"null="nothing""#).unwrap(),
+            json!({"null": "nothing"})
+        );
+    }
 
-        while self.current() != Some(b']') {
-            if self.current().is_none() {
-                return Err(syntax_err!("Unterminated array"));
-            }
+    #[test]
+    fn key_with_hyphen() {
+        assert_eq!(
+            parse(r#"my-key="value""#).unwrap(),
+            json!({"my-key": "value"})
+        );
+    }
 
-            if let Some(value) = self.parse_value()? {
-                elements.push(value);
-            }
+    #[test]
+    fn key_with_underscore_and_digits() {
+        assert_eq!(
+            parse(r#"key_1="value""#).unwrap(),
+            json!({"key_1": "value"})
+        );
+    }
 
-            // Skip separator between items. Per §5.3, an item that follows
-            // another on the same physical line must be preceded by a comma.
-            let (saw_newline, saw_comma) = self.skip_inter_item_separator();
+    #[test]
+    fn key_with_dot() {
+        assert_eq!(parse("app.version=1").unwrap(), json!({"app.version": 1}));
+    }
 
-            if self.current() == Some(b']') {
-                break;
-            }
-            if self.current().is_none() {
-                return Err(syntax_err!("Unterminated array"));
-            }
-            if !saw_newline && !saw_comma {
-                return Err(syntax_err!(
-                    "items on the same line must be separated by a comma"
-                ));
-            }
-        }
+    #[test]
+    fn unicode_key() {
+        assert_eq!(
+            parse(r#"名前="テスト""#).unwrap(),
+            json!({"名前": "テスト"})
+        );
+    }
 
-        self.advance(); // skip ']'
-        Ok((Value::Array(elements), self.pos))
+    #[test]
+    fn quoted_key_with_spaces() {
+        assert_eq!(
+            parse(r#""quoted key"="value""#).unwrap(),
+            json!({"quoted key": "value"})
+        );
     }
 
-    fn parse_nested_object(&mut self) -> Result<(Value, usize)> {
-        self.advance(); // skip '{'
+    #[test]
+    fn empty_key_is_error() {
+        assert!(parse("=value").is_err());
+    }
 
-        let mut map = Map::new();
+    // =========================================================================
+    // §3.4 — Strings
+    // =========================================================================
 
-        self.skip_ws_and_comments();
+    #[test]
+    fn double_quoted_string() {
+        assert_eq!(parse(r#"key="hello""#).unwrap(), json!({"key": "hello"}));
+    }
 
-        while self.current() != Some(b'}') {
-            if self.current().is_none() {
-                return Err(syntax_err!("Unterminated nested object"));
-            }
+    #[test]
+    fn single_quoted_string() {
+        assert_eq!(parse(r#"key='hello'"#).unwrap(), json!({"key": "hello"}));
+    }
 
-            // Parse key
-            let key = self.parse_key()?;
+    #[test]
+    fn string_escape_newline_and_tab() {
+        assert_eq!(
+            parse(r#"key="line1\nline2\tindented""#).unwrap(),
+            json!({"key": "line1\nline2\tindented"})
+        );
+    }
 
-            // Skip whitespace/comments before '='
-            self.skip_ws_and_comments();
+    #[test]
+    fn string_escape_unicode() {
+        assert_eq!(parse(r#"key="é""#).unwrap(), json!({"key": "é"}));
+    }
 
-            // Expect '='
-            if self.current() != Some(b'=') {
-                return Err(syntax_err!("Expected '=' after key in nested object"));
-            }
-            self.advance();
+    #[test]
+    fn string_escape_quote_and_backslash() {
+        assert_eq!(
+            parse(r#"key="a\"b\\c""#).unwrap(),
+            json!({"key": "a\"b\\c"})
+        );
+    }
 
-            // Skip whitespace/comments before value
-            self.skip_ws_and_comments();
+    #[test]
+    fn raw_string_basic() {
+        assert_eq!(
+            parse(r###"key=r"C:\path""###).unwrap(),
+            json!({"key": r"C:\path"})
+        );
+    }
 
-            // Parse value
-            if let Some(value) = self.parse_value()? {
-                if map.contains_key(&key) {
-                    return Err(JhonError::DuplicateKey {
-                        line: self.line,
-                        col: self.col,
-                        key,
-                    });
-                }
-                map.insert(key, value);
-            }
+    #[test]
+    fn raw_string_with_hashes() {
+        let result = parse(r###"key=r#"contains "quotes""#"###).unwrap();
+        assert_eq!(result["key"], r#"contains "quotes""#);
+    }
 
-            // Skip separator between pairs.
-            let (saw_newline, saw_comma) = self.skip_inter_item_separator();
+    #[test]
+    fn raw_string_multiline() {
+        let result = parse("key=r\"line1\nline2\"").unwrap();
+        assert_eq!(result["key"], "line1\nline2");
+    }
 
-            if self.current() == Some(b'}') {
-                break;
-            }
-            if self.current().is_none() {
-                return Err(syntax_err!("Unterminated nested object"));
-            }
-            if !saw_newline && !saw_comma {
-                return Err(syntax_err!(
-                    "items on the same line must be separated by a comma"
-                ));
-            }
-        }
+    #[test]
+    fn backslash_newline_continues_string() {
+        let value = parse("s = \"part one \\\n      part two\"").unwrap();
+        assert_eq!(value, json!({"s": "part one part two"}));
+    }
 
-        self.advance(); // skip '}'
-        Ok((Value::Object(map), self.pos))
+    #[test]
+    fn backslash_crlf_continues_string() {
+        let value = parse("s = \"part one \\\r\n  part two\"\r\nt = 'x\\\r\ny'").unwrap();
+        assert_eq!(value, json!({"s": "part one part two", "t": "xy"}));
+        // A lone carriage return is not a line break.
+        assert!(parse("s = \"a\\\rb\"").is_err());
     }
 
-    fn parse_key(&mut self) -> Result<String> {
-        self.skip_ws_and_comments();
+    #[test]
+    fn unrecognized_escape_is_error() {
+        assert!(parse(r#"key="\q""#).is_err());
+    }
 
-        let quote = self.current();
+    #[test]
+    fn literal_newline_in_regular_string_is_error() {
+        assert!(parse("key=\"line1\nline2\"").is_err());
+    }
 
-        if quote == Some(b'"') || quote == Some(b'\'') {
-            self.parse_string(quote.unwrap())
-        } else {
-            // Bare key — permissive: scan bytes until we hit one in the
-            // exclusion list (per SPEC.md §3.3). All excluded bytes are ASCII,
-            // so UTF-8 multi-byte sequences pass through untouched.
-            let start = self.pos;
-            while self.pos < self.input.len() {
-                let b = self.input[self.pos];
-                if is_key_delimiter(b) {
-                    break;
-                }
-                self.pos += 1;
-            }
+    #[test]
+    fn unterminated_string_is_error() {
+        assert!(parse(r#"key="unterminated"#).is_err());
+    }
 
-            if start == self.pos {
-                return Err(syntax_err!("Empty key"));
-            }
+    // =========================================================================
+    // §3.5 — Numbers
+    // =========================================================================
 
-            let s = std::str::from_utf8(&self.input[start..self.pos])
-                .map_err(|_| syntax_err!("Invalid UTF-8 in bare key"))?;
-            Ok(s.to_string())
-        }
+    #[test]
+    fn decimal_integer() {
+        assert_eq!(parse("n=42").unwrap(), json!({"n": 42}));
     }
 
-    fn parse_value(&mut self) -> Result<Option<Value>> {
-        self.skip_ws_and_comments();
+    #[test]
+    fn negative_integer() {
+        assert_eq!(parse("n=-5").unwrap(), json!({"n": -5}));
+    }
 
-        let c = self
-            .current()
-            .ok_or_else(|| syntax_err!("Expected value"))?;
+    #[test]
+    fn number_with_underscores() {
+        assert_eq!(parse("n=1_000_000").unwrap(), json!({"n": 1000000}));
+    }
 
-        let result = match c {
-            b'"' | b'\'' => Some(Value::String(self.parse_string(c)?)),
-            b'r' | b'R' => Some(Value::String(self.parse_raw_string()?)),
-            b'[' => Some(self.parse_array()?.0),
-            b'{' => Some(self.parse_nested_object()?.0),
-            b'0'..=b'9' | b'-' => Some(self.parse_number()?),
-            b't' | b'f' => Some(self.parse_boolean()?),
-            b'n' => Some(self.parse_null()?),
-            _ => return Err(syntax_err!("Unexpected character in value: {}", c as char)),
-        };
+    #[test]
+    fn negative_number_with_underscores() {
+        assert_eq!(parse("n=-50_000").unwrap(), json!({"n": -50000}));
+    }
 
-        Ok(result)
+    #[test]
+    fn float_fractional() {
+        assert_eq!(parse("n=12.5").unwrap(), json!({"n": 12.5}));
     }
 
-    fn parse_boolean(&mut self) -> Result<Value> {
-        if self.input.len() >= self.pos + 4 && &self.input[self.pos..self.pos + 4] == b"true" {
-            self.pos += 4;
-            return Ok(Value::Bool(true));
-        } else if self.input.len() >= self.pos + 5
-            && &self.input[self.pos..self.pos + 5] == b"false"
-        {
-            self.pos += 5;
-            return Ok(Value::Bool(false));
-        }
-        Err(syntax_err!("Invalid boolean value"))
+    #[test]
+    fn negative_float() {
+        assert_eq!(parse("n=-45.67").unwrap(), json!({"n": -45.67}));
     }
 
-    fn parse_null(&mut self) -> Result<Value> {
-        if self.input.len() >= self.pos + 4 && &self.input[self.pos..self.pos + 4] == b"null" {
-            self.pos += 4;
-            return Ok(Value::Null);
-        }
-        Err(syntax_err!("Invalid null value"))
+    #[test]
+    fn float_with_exponent_only() {
+        assert_eq!(parse("n=1e10").unwrap(), json!({"n": 1e10}));
     }
-}
 
-#[inline]
-/// Parse a signed radix literal into a JSON number. Tries i64 → u64 → i128 →
-/// u128 → falls back to f64 (the i128/u128 intermediates lose precision past
-/// 2^53 when stored in serde_json::Number).
-fn parse_radix_literal(signed: &str, radix: u32) -> Result<Value> {
-    if let Ok(i) = i64::from_str_radix(signed, radix) {
-        return Ok(Value::Number(Number::from(i)));
+    #[test]
+    fn float_with_fractional_and_exponent() {
+        assert_eq!(parse("n=1.5E-3").unwrap(), json!({"n": 1.5e-3}));
     }
-    if let Ok(u) = u64::from_str_radix(signed, radix) {
-        return Ok(Value::Number(Number::from(u)));
+
+    #[test]
+    fn hex_literal_lowercase() {
+        assert_eq!(parse("n=0xff").unwrap(), json!({"n": 255}));
     }
-    if let Ok(i) = i128::from_str_radix(signed, radix)
-        && let Some(n) = Number::from_f64(i as f64)
-    {
-        return Ok(Value::Number(n));
+
+    #[test]
+    fn hex_literal_uppercase_digits() {
+        assert_eq!(parse("n=0xDE_AD").unwrap(), json!({"n": 0xDE_AD}));
     }
-    if let Ok(u) = u128::from_str_radix(signed, radix)
-        && let Some(n) = Number::from_f64(u as f64)
-    {
-        return Ok(Value::Number(n));
+
+    #[test]
+    fn octal_literal() {
+        assert_eq!(parse("n=0o777").unwrap(), json!({"n": 511}));
     }
-    Err(syntax_err!("could not parse number: {}", signed))
-}
 
-fn parse_jhon_object(input: &str) -> Result<Value> {
-    let mut parser = Parser::new(input.as_bytes());
-    let mut map = Map::new();
+    #[test]
+    fn binary_literal() {
+        assert_eq!(parse("n=0b1010").unwrap(), json!({"n": 10}));
+    }
 
-    parser.skip_ws_and_comments();
+    #[test]
+    fn negative_hex_literal() {
+        assert_eq!(parse("n=-0xff").unwrap(), json!({"n": -255}));
+    }
 
-    while parser.pos < parser.input.len() {
-        // Parse key
-        let key = parser.parse_key()?;
+    #[test]
+    fn positive_with_plus_prefix_is_error() {
+        assert!(parse("n=+5").is_err());
+    }
 
-        // Skip whitespace/comments before '='
-        parser.skip_ws_and_comments();
+    #[test]
+    fn uppercase_hex_prefix_is_error() {
+        assert!(parse("n=0Xff").is_err());
+    }
 
-        // Expect '='
-        if parser.current() != Some(b'=') {
-            return Err(syntax_err!("Expected '=' after key"));
-        }
-        parser.advance();
+    #[test]
+    fn uppercase_octal_prefix_is_error() {
+        assert!(parse("n=0O77").is_err());
+    }
 
-        // Skip whitespace/comments before value
-        parser.skip_ws_and_comments();
+    #[test]
+    fn uppercase_binary_prefix_is_error() {
+        assert!(parse("n=0B10").is_err());
+    }
 
-        // Parse value
-        if let Some(value) = parser.parse_value()? {
-            if map.contains_key(&key) {
-                return Err(JhonError::DuplicateKey {
-                    line: parser.line,
-                    col: parser.col,
-                    key,
-                });
-            }
-            map.insert(key, value);
-        }
+    #[test]
+    fn number_type_suffix_is_error() {
+        assert!(parse("n=5u8").is_err());
+    }
 
-        // Skip separator between pairs.
-        let (saw_newline, saw_comma) = parser.skip_inter_item_separator();
+    #[test]
+    fn leading_underscore_is_error() {
+        assert!(parse("n=_5").is_err());
+    }
 
-        if parser.pos >= parser.input.len() {
-            break; // trailing separator at EOF is OK
-        }
-        if !saw_newline && !saw_comma {
-            return Err(syntax_err!(
-                "items on the same line must be separated by a comma"
-            ));
-        }
+    #[test]
+    fn trailing_underscore_is_error() {
+        assert!(parse("n=5_").is_err());
     }
 
-    Ok(Value::Object(map))
-}
+    #[test]
+    fn adjacent_underscores_are_error() {
+        assert!(parse("n=5__5").is_err());
+    }
 
-fn parse_jhon_array(input: &str) -> Result<Value> {
-    let mut parser = Parser::new(input.as_bytes());
-    let mut elements = Vec::new();
+    // =========================================================================
+    // §5 — Objects
+    // =========================================================================
 
-    parser.skip_ws_and_comments();
+    #[test]
+    fn basic_key_value_pairs() {
+        assert_eq!(
+            parse(r#"a="hello", b=123.45"#).unwrap(),
+            json!({"a": "hello", "b": 123.45})
+        );
+    }
 
-    while parser.pos < parser.input.len() {
-        // Reject `key=value` pairs mixed into array mode. After a value is
-        // parsed, an immediately following `=` means the user wrote a pair
-        // after a bare value — that's a syntax error per SPEC.md §2.
-        if parser.current() == Some(b'=') {
-            return Err(syntax_err!(
-                "Cannot mix key=value pairs and bare values at top level"
-            ));
-        }
+    #[test]
+    fn nested_object() {
+        assert_eq!(
+            parse(r#"server={host="localhost", port=8080}"#).unwrap(),
+            json!({"server": {"host": "localhost", "port": 8080}})
+        );
+    }
 
-        if let Some(value) = parser.parse_value()? {
-            elements.push(value);
-        }
+    #[test]
+    fn whitespace_around_equals_is_insignificant() {
+        let a = parse(r#"name = "x""#).unwrap();
+        let b = parse(r#"name="x""#).unwrap();
+        let c = parse(r#"name ="x""#).unwrap();
+        let d = parse(r#"name= "x""#).unwrap();
+        assert_eq!(a, b);
+        assert_eq!(b, c);
+        assert_eq!(c, d);
+    }
 
-        // Reject mix at the value-position level too: parse_value rejects bare
-        // identifiers, so `1\na=2` errors with "Unexpected character" at `a`.
-        // The dedicated `=` check above catches `[1]=2`-style mix attempts.
+    #[test]
+    fn duplicate_keys_at_top_level_are_error() {
+        assert!(parse("a=1, a=2").is_err());
+    }
 
-        let (saw_newline, saw_comma) = parser.skip_inter_item_separator();
+    #[test]
+    fn duplicate_keys_in_nested_object_are_error() {
+        assert!(parse(r#"outer={a=1, a=2}"#).is_err());
+    }
 
-        if parser.pos >= parser.input.len() {
-            break;
-        }
-        if !saw_newline && !saw_comma {
-            return Err(syntax_err!(
-                "items on the same line must be separated by a comma"
-            ));
-        }
+    #[test]
+    fn key_order_is_preserved() {
+        let result = parse(r#"z=1, a=2, m=3"#).unwrap();
+        let keys: Vec<&String> = result.as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["z", "a", "m"]);
     }
 
-    Ok(Value::Array(elements))
-}
+    #[test]
+    fn reserializing_keeps_source_key_order() {
+        // Both the Value path (serde_json's `preserve_order`) and the spanned
+        // path (objects are entry lists) write keys back in source order.
+        let text = "b = 1\na = {y = 2, x = 3}";
+        assert_eq!(serialize(&parse(text).unwrap()), "b=1,a={y=2,x=3}");
+        assert_eq!(
+            serialize_pretty(&parse(text).unwrap(), "  "),
+            "b = 1\na = {\n  y = 2\n  x = 3\n}"
+        );
+        let spanned = parse_spanned(text).unwrap();
+        assert_eq!(serialize_spanned(&spanned), "b=1,a={y=2,x=3}");
+        assert_eq!(
+            serialize_spanned_pretty(&spanned, "  "),
+            serialize_pretty(&parse(text).unwrap(), "  ")
+        );
+    }
 
-// =============================================================================
-// Optimized Serializer
-// =============================================================================
+    // =========================================================================
+    // §5.3 — Separators
+    // =========================================================================
 
-#[inline(always)]
-fn serialize_compact(value: &Value, result: &mut String) {
-    match value {
-        Value::Object(map) if map.is_empty() => {}
-        Value::Object(map) => serialize_object_compact(map, result),
-        Value::Array(arr) if arr.is_empty() => result.push_str("[]"),
-        Value::Array(arr) => serialize_array_compact(arr, result),
-        Value::String(s) => serialize_string(s, result),
-        Value::Number(n) => serialize_number(n, result),
-        Value::Bool(b) => result.push_str(if *b { "true" } else { "false" }),
-        Value::Null => result.push_str("null"),
+    #[test]
+    fn same_line_comma_separated() {
+        assert_eq!(
+            parse("a=1, b=2, c=3").unwrap(),
+            json!({"a": 1, "b": 2, "c": 3})
+        );
     }
-}
 
-#[inline(always)]
-fn serialize_object_compact(map: &Map<String, Value>, result: &mut String) {
-    let mut first = true;
-    for (key, value) in map {
-        if !first {
-            result.push(',');
-        }
-        first = false;
+    #[test]
+    fn newline_separated_multiline() {
+        assert_eq!(
+            parse("a=1\nb=2\nc=3").unwrap(),
+            json!({"a": 1, "b": 2, "c": 3})
+        );
+    }
 
-        serialize_key(key, result);
-        result.push('=');
+    #[test]
+    fn mixed_comma_and_newline_separators() {
+        assert_eq!(
+            parse("a=1,\nb=2,\nc=3").unwrap(),
+            json!({"a": 1, "b": 2, "c": 3})
+        );
+    }
 
-        match value {
-            Value::Object(inner) if inner.is_empty() => result.push_str("{}"),
-            Value::Object(inner) => {
-                result.push('{');
-                serialize_object_compact(inner, result);
-                result.push('}');
-            }
-            _ => serialize_compact(value, result),
-        }
+    #[test]
+    fn trailing_comma_at_top_level() {
+        assert_eq!(parse("a=1, b=2,").unwrap(), json!({"a": 1, "b": 2}));
     }
-}
 
-#[inline(always)]
-fn serialize_array_compact(arr: &[Value], result: &mut String) {
-    result.push('[');
-    serialize_array_contents_compact(arr, result);
-    result.push(']');
-}
+    #[test]
+    fn trailing_comma_in_braced_object() {
+        // Per SPEC.md §2: top-level `{...}` is a single-element array.
+        assert_eq!(parse("{a=1, b=2,}").unwrap(), json!([{"a": 1, "b": 2}]));
+    }
 
-#[inline(always)]
-fn serialize_array_contents_compact(arr: &[Value], result: &mut String) {
-    let mut first = true;
-    for value in arr {
-        if !first {
-            result.push(',');
-        }
-        first = false;
+    #[test]
+    fn trailing_comma_in_array() {
+        assert_eq!(parse("k=[1, 2, 3,]").unwrap()["k"], json!([1, 2, 3]));
+    }
 
-        match value {
-            Value::Object(map) if map.is_empty() => result.push_str("{}"),
-            Value::Object(map) => {
-                result.push('{');
-                serialize_object_compact(map, result);
-                result.push('}');
-            }
-            _ => serialize_compact(value, result),
-        }
+    #[test]
+    fn whitespace_around_comma_is_insignificant() {
+        let a = parse("a=1,b=2").unwrap();
+        let b = parse("a=1, b=2").unwrap();
+        let c = parse("a=1 ,b=2").unwrap();
+        let d = parse("a=1 , b=2").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(b, c);
+        assert_eq!(c, d);
     }
-}
 
-#[inline(always)]
-fn serialize_key(key: &str, result: &mut String) {
-    if needs_quoting(key) {
-        serialize_string(key, result);
-    } else {
-        result.push_str(key);
+    #[test]
+    fn same_line_space_only_separator_is_error() {
+        assert!(parse("a=1 b=2").is_err());
     }
-}
 
-// Optimized string serialization using static escape table with SIMD-like scanning
-#[inline(always)]
-fn serialize_string(s: &str, result: &mut String) {
-    result.push('"');
+    #[test]
+    fn same_line_tab_only_separator_is_error() {
+        assert!(parse("a=1\tb=2").is_err());
+    }
 
-    let bytes = s.as_bytes();
-    let mut i = 0;
+    #[test]
+    fn tabs_are_whitespace_not_separators_in_arrays_and_objects() {
+        assert!(parse("k=[1\t2]").is_err());
+        assert!(parse("k={a=1\tb=2}").is_err());
+        assert!(parse("1\t2").is_err());
+        assert_eq!(
+            parse("k=[\t1,\t2\t,\t3\t]").unwrap(),
+            json!({"k": [1, 2, 3]})
+        );
+        assert_eq!(
+            parse("k={\ta\t=\t1,\tb=2\t}").unwrap(),
+            json!({"k": {"a": 1, "b": 2}})
+        );
+        assert_eq!(
+            parse("k=[\n\t1\n\t2\n]\n\tm=\t{\n\t\tx=1\n\t}").unwrap(),
+            json!({"k": [1, 2], "m": {"x": 1}})
+        );
+        assert_eq!(parse("\t1,\t2\n\t3").unwrap(), json!([1, 2, 3]));
+    }
 
-    // Fast path: process 8 bytes at a time
-    while i + 8 <= bytes.len() {
-        // Check if any byte in the next 8 needs escaping
-        let mut needs_escape = false;
-        for j in 0..8 {
-            if ESCAPE[bytes[i + j] as usize] != 0 {
-                needs_escape = true;
-                // Write up to the escape char
-                if j > 0 {
-                    let safe_part = unsafe { std::str::from_utf8_unchecked(&bytes[i..i + j]) };
-                    result.push_str(safe_part);
-                }
-                // Handle the escape
-                let byte = bytes[i + j];
-                serialize_escape_byte(byte, result);
-                i += j + 1;
-                break;
+    #[test]
+    fn array_same_line_no_commas_is_error() {
+        assert!(parse("k=[1 2 3]").is_err());
+    }
+
+    #[test]
+    fn adjacent_items_need_a_separator_even_without_spaces() {
+        // A closing quote or bracket does not separate items, and neither
+        // does a block comment; only a comma or a newline does (§5.3).
+        for text in [
+            r#"a="hello" b="world""#,
+            r#"a="x"b=2"#,
+            "a=[1]b=2",
+            "a={x=1}b=2",
+            r#"k=["a""b"]"#,
+            "k=[{a=1}{b=2}]",
+            "a=1 /* c */ b=2",
+        ] {
+            match parse(text) {
+                Err(JhonError::Syntax { msg, .. }) => assert_eq!(
+                    msg, "items on the same line must be separated by a comma",
+                    "{}",
+                    text
+                ),
+                other => panic!("{}: expected syntax error, got {:?}", text, other),
             }
         }
-        if !needs_escape {
-            // No escapes in this chunk, write all 8 bytes
-            let safe_part = unsafe { std::str::from_utf8_unchecked(&bytes[i..i + 8]) };
-            result.push_str(safe_part);
-            i += 8;
+        assert_eq!(
+            parse("a=1, b=2 // c\nc=3").unwrap(),
+            json!({"a": 1, "b": 2, "c": 3})
+        );
+    }
+
+    #[test]
+    fn nested_arrays_with_comma_or_newline_separators() {
+        let expected = json!({"k": [[1, 2], [3, 4]]});
+        for text in [
+            "k=[[1,2],[3,4]]",
+            "k=[[1, 2], [3, 4]]",
+            "k=[ [1,2] , [3,4] ]",
+            "k=[[1, 2],[3, 4],]",
+            "k=[\n  [1\n   2]\n  [3, 4]\n]",
+            "k=[[1, 2] // first\n [3, 4]]",
+        ] {
+            assert_eq!(parse(text).unwrap(), expected, "{}", text);
         }
     }
 
-    // Handle remaining bytes
-    while i < bytes.len() {
-        let escape = ESCAPE[bytes[i] as usize];
-        if escape != 0 {
-            serialize_escape_byte(bytes[i], result);
-        } else {
-            result.push(unsafe { char::from_u32_unchecked(bytes[i] as u32) });
+    #[test]
+    fn nested_arrays_on_one_line_need_commas() {
+        // §5.3 applies at every level: `][` or `] [` on one line is two
+        // items without a comma, just like `1 2`.
+        for text in [
+            "k=[[1 2] [3 4]]",
+            "k=[[1 2][3 4]]",
+            "k=[[1,2] [3,4]]",
+            "k=[[1,2][3,4]]",
+        ] {
+            assert!(parse(text).is_err(), "{}", text);
         }
-        i += 1;
     }
 
-    result.push('"');
-}
+    // =========================================================================
+    // §6 — Arrays
+    // =========================================================================
 
-// Serialize a single escaped byte
-#[inline(always)]
-fn serialize_escape_byte(byte: u8, result: &mut String) {
-    match ESCAPE[byte as usize] {
-        BB => result.push_str("\\b"),
-        TT => result.push_str("\\t"),
-        NN => result.push_str("\\n"),
-        FF => result.push_str("\\f"),
-        RR => result.push_str("\\r"),
-        QU => result.push_str("\\\""),
-        BS => result.push_str("\\\\"),
-        UU => {
-            // Unicode escape for control characters (0x00-0x1F)
-            // Manual formatting: \u00XX
-            const HEX: &[u8; 16] = b"0123456789abcdef";
-            result.push_str("\\u00");
-            result.push(HEX[(byte >> 4) as usize] as char);
-            result.push(HEX[(byte & 0x0F) as usize] as char);
-        }
-        _ => {
-            // Safety: ESCAPE table only has the above values
-            // This should never be reached
-            result.push(byte as char);
-        }
+    #[test]
+    fn empty_array() {
+        assert_eq!(parse("k=[]").unwrap()["k"], json!([]));
     }
-}
 
-// Number serialization — uses std formatting (itoa/ryu dropped for simplicity).
-#[inline(always)]
-fn serialize_number(n: &Number, result: &mut String) {
-    if let Some(i) = n.as_i64() {
-        let _ = write!(result, "{}", i);
-    } else if let Some(u) = n.as_u64() {
-        let _ = write!(result, "{}", u);
-    } else if let Some(f) = n.as_f64() {
-        if f.fract() == 0.0 {
-            let _ = write!(result, "{}", f as i64);
-        } else {
-            let _ = write!(result, "{}", f);
-        }
-    } else {
-        result.push('0');
+    #[test]
+    fn array_of_strings() {
+        assert_eq!(
+            parse(r#"k=["a", "b", "c"]"#).unwrap()["k"],
+            json!(["a", "b", "c"])
+        );
     }
-}
 
-fn serialize_pretty_with_depth(
-    value: &Value,
-    indent: &str,
-    depth: usize,
-    in_array: bool,
-    result: &mut String,
-) {
-    match value {
-        Value::Object(map) if map.is_empty() => {
-            if in_array {
-                result.push_str("{}");
-            }
-        }
-        Value::Object(map) => serialize_object_pretty(map, indent, depth, in_array, result),
-        Value::Array(arr) if arr.is_empty() => result.push_str("[]"),
-        Value::Array(arr) => serialize_array_pretty(arr, indent, depth, result),
-        Value::String(s) => serialize_string(s, result),
-        Value::Number(n) => serialize_number(n, result),
-        Value::Bool(b) => result.push_str(if *b { "true" } else { "false" }),
-        Value::Null => result.push_str("null"),
+    #[test]
+    fn array_mixed_types() {
+        assert_eq!(
+            parse(r#"k=[1, "two", true, null]"#).unwrap()["k"],
+            json!([1, "two", true, null])
+        );
+    }
+
+    #[test]
+    fn nested_arrays() {
+        assert_eq!(
+            parse("k=[[1, 2], [3, 4]]").unwrap()["k"],
+            json!([[1, 2], [3, 4]])
+        );
     }
-}
 
-fn serialize_object_pretty(
-    map: &Map<String, Value>,
-    indent: &str,
-    depth: usize,
-    in_array: bool,
-    result: &mut String,
-) {
-    // Add opening brace if nested
-    if in_array {
-        for _ in 0..depth + 1 {
-            result.push_str(indent);
-        }
-        result.push_str("{\n");
-    } else if depth > 0 {
-        result.push_str("{\n");
+    #[test]
+    fn multiline_array_newline_separated() {
+        assert_eq!(parse("k=[\n1\n2\n3\n]").unwrap()["k"], json!([1, 2, 3]));
     }
 
-    let mut first = true;
-    for (key, value) in map {
-        if !first {
-            result.push('\n');
-        }
-        first = false;
+    #[test]
+    fn unbalanced_array_is_error() {
+        assert!(parse("k=[1, 2").is_err());
+    }
 
-        let inner_indent = if in_array {
-            depth + 2
-        } else if depth == 0 {
-            0
-        } else {
-            depth
-        };
+    // =========================================================================
+    // Key scanning
+    // =========================================================================
 
-        for _ in 0..inner_indent {
-            result.push_str(indent);
-        }
+    #[test]
+    fn top_level_keys_match_full_parse() {
+        let keys = top_level_keys(COMPLEX_EXAMPLE).unwrap();
+        let parsed = parse(COMPLEX_EXAMPLE).unwrap();
+        let expected: Vec<String> = parsed.as_object().unwrap().keys().cloned().collect();
+        assert_eq!(keys, expected);
+        assert_eq!(keys[0], "app_name");
+        assert_eq!(keys.len(), 6);
+    }
 
-        serialize_key(key, result);
-        result.push_str(" = ");
+    #[test]
+    fn top_level_keys_skip_delimiters_inside_strings_and_comments() {
+        let text = "a=\"}]\\\"\", b=r#\"{\"#, c=[/* ] */ '[', {x=1}], d=-1.5e3";
+        assert_eq!(top_level_keys(text).unwrap(), ["a", "b", "c", "d"]);
+    }
 
-        serialize_pretty_with_depth(value, indent, depth + 1, false, result);
+    #[test]
+    fn top_level_keys_of_array_or_empty_document_is_empty() {
+        assert!(top_level_keys("1, 2, 3").unwrap().is_empty());
+        assert!(top_level_keys("// nothing").unwrap().is_empty());
     }
 
-    // Add closing brace if nested
-    if in_array {
-        result.push('\n');
-        for _ in 0..depth + 1 {
-            result.push_str(indent);
+    #[test]
+    fn top_level_keys_reports_structural_errors() {
+        assert!(top_level_keys("a=[1, 2").is_err());
+        assert!(top_level_keys("a=1 b=2").is_err());
+    }
+
+    #[test]
+    fn object_entries_streams_a_large_object() {
+        let mut text = String::new();
+        for i in 0..10_000 {
+            text.push_str(&format!(
+                "item{} = {{id = {}, size = {}, tags = [\"t\"]}}\n",
+                i,
+                i,
+                i % 7
+            ));
         }
-        result.push('}');
-    } else if depth > 0 {
-        result.push('\n');
-        for _ in 0..depth - 1 {
-            result.push_str(indent);
+        let mut count = 0;
+        let mut total = 0;
+        for entry in object_entries(&text) {
+            let (key, value) = entry.unwrap();
+            assert_eq!(key, format!("item{}", value["id"]));
+            total += value["size"].as_i64().unwrap();
+            count += 1;
         }
-        result.push('}');
+        assert_eq!(count, 10_000);
+        assert_eq!(total, (0..10_000).map(|i| i % 7).sum::<i64>());
+
+        assert_eq!(object_entries("[1, 2]").count(), 0);
+        assert_eq!(object_entries("  // nothing\n").count(), 0);
+
+        // An entry is yielded before the error that follows it, then iteration stops.
+        let mut entries = object_entries("a = 1 b = 2\nc = 3");
+        assert_eq!(
+            entries.next().unwrap().unwrap(),
+            ("a".to_string(), json!(1))
+        );
+        assert!(entries.next().unwrap().is_err());
+        assert!(entries.next().is_none());
     }
-}
 
-fn serialize_array_pretty(arr: &[Value], indent: &str, depth: usize, result: &mut String) {
-    result.push_str("[\n");
-    serialize_array_contents_pretty(arr, indent, depth, result);
-    result.push('\n');
-    for _ in 0..depth {
-        result.push_str(indent);
+    #[test]
+    fn parse_with_warnings_recovers_duplicates_and_unknown_escapes() {
+        let text = "\u{feff}name = \"a\\qb\"\nport = 1\nport = 2\ndb = {x = 1, x = 2}";
+        let (value, warnings) = parse_with_warnings(text).unwrap();
+        assert_eq!(value, json!({"name": "a\\qb", "port": 2, "db": {"x": 2}}));
+        let msgs: Vec<&str> = warnings.iter().map(|w| w.msg.as_str()).collect();
+        assert_eq!(
+            msgs,
+            [
+                "byte order mark skipped",
+                "unknown escape \\q kept as written",
+                "duplicate key `port`; the last value wins",
+                "duplicate key `x`; the last value wins",
+            ]
+        );
+        assert_eq!((warnings[1].line, warnings[1].col), (1, 10));
+        assert_eq!(warnings[2].line, 3);
+        assert_eq!(warnings[3].line, 4);
+
+        // The same input is an error for the strict parsers, and clean input
+        // gives no warnings.
+        assert!(parse(&text[3..]).is_err());
+        assert_eq!(parse_with_warnings("a = 1").unwrap().1, []);
+        assert!(parse_with_warnings("a = ").is_err());
     }
-    result.push(']');
-}
 
-/// Emit the contents of an array without the surrounding `[]`. Used for
-/// top-level implicit arrays per SPEC.md §2 (array mode).
-fn serialize_array_contents_pretty(arr: &[Value], indent: &str, depth: usize, result: &mut String) {
-    let mut first = true;
-    for value in arr {
-        if !first {
-            result.push('\n');
-        }
-        first = false;
+    #[test]
+    fn parse_pairs_keeps_duplicates_in_order() {
+        assert_eq!(
+            parse_pairs("a=1, a=2, b=3").unwrap(),
+            vec![
+                ("a".to_string(), json!(1)),
+                ("a".to_string(), json!(2)),
+                ("b".to_string(), json!(3)),
+            ]
+        );
+    }
 
-        if matches!(value, Value::Object(_)) {
-            serialize_pretty_with_depth(value, indent, depth, true, result);
-        } else {
-            for _ in 0..depth + 1 {
-                result.push_str(indent);
+    #[test]
+    fn parse_pairs_nested_values_and_non_object_documents() {
+        let pairs = parse_pairs("s = {x=1}\ns = [1, 2]").unwrap();
+        assert_eq!(pairs[0], ("s".to_string(), json!({"x": 1})));
+        assert_eq!(pairs[1], ("s".to_string(), json!([1, 2])));
+
+        assert!(parse_pairs("").unwrap().is_empty());
+        assert!(parse_pairs("1, 2").unwrap().is_empty());
+        assert!(parse_pairs("s = {x=1, x=2}").is_err());
+    }
+
+    #[test]
+    fn parse_one_value_parses_a_single_value() {
+        assert_eq!(parse_one_value("[1,2]").unwrap(), json!([1, 2]));
+        assert_eq!(parse_one_value("{a=1}").unwrap(), json!({"a": 1}));
+        assert_eq!(parse_one_value("\"x\"").unwrap(), json!("x"));
+        assert_eq!(parse_one_value("42").unwrap(), json!(42));
+        assert_eq!(parse_one_value("\n  null\n").unwrap(), json!(null));
+
+        match parse_one_value("1 2") {
+            Err(JhonError::Syntax { line, col, msg }) => {
+                assert_eq!((line, col), (1, 3));
+                assert_eq!(msg, "expected end of input after the root value");
             }
-            serialize_pretty_with_depth(value, indent, depth + 1, false, result);
+            other => panic!("expected syntax error, got {:?}", other),
+        }
+        for text in ["", "// only a comment", "a=1", "[1] [2]"] {
+            assert!(parse_one_value(text).is_err(), "{:?}", text);
         }
     }
-}
 
-// =============================================================================
-// Inline-aware pretty printer (`max_inline_width > 0` mode).
-//
-// Older `serialize_pretty_with_depth` always multi-lines non-empty containers.
-// This separate path short-circuits to a single-line `{ k = v, ... }` /
-// `[ a, b, ... ]` form when the result fits within `max_inline_width` chars,
-// and falls back to a 3-line "wrapper_compact" form when only the joined
-// children fit, and finally to one-child-per-line otherwise. Existing
-// `serialize_pretty` is unchanged because it routes through the legacy path
-// (`max_inline_width == 0`).
-// =============================================================================
-
-/// Top-level dispatch for inline-aware mode. Mirrors `serialize_top_pretty`:
-/// empty containers and `null` collapse to empty string (SPEC §2); top-level
-/// arrays emit bare (no surrounding `[]`).
-fn serialize_pretty_inline_top(value: &Value, indent: &str, max_inline_width: usize, result: &mut String) {
-    match value {
-        Value::Array(arr) if arr.is_empty() => {}
-        Value::Array(arr) => {
-            let mut first = true;
-            for v in arr {
-                if !first {
-                    result.push('\n');
-                }
-                first = false;
-                render_pretty_inline(v, indent, 0, max_inline_width, result);
-            }
+    #[test]
+    fn parse_object_accepts_only_object_documents() {
+        let map = parse_object(COMPLEX_EXAMPLE).unwrap();
+        assert_eq!(map["database"]["port"], 5432);
+        assert!(parse_object("// nothing here\n").unwrap().is_empty());
+
+        for text in ["[1, 2]", "\n  {a=1}", "1, 2", "\"s\""] {
+            let err = parse_object(text).unwrap_err();
+            assert!(
+                matches!(&err, JhonError::Syntax { msg, .. } if msg.contains("array-mode")),
+                "{text:?}: {err}"
+            );
         }
-        Value::Object(map) if map.is_empty() => {}
-        Value::Object(map) => {
-            // Top-level object: keys at column 0, no surrounding braces
-            // (mirrors `serialize_object_pretty` at depth=0, in_array=false).
-            let mut first = true;
-            for (k, v) in map.iter() {
-                if !first {
-                    result.push('\n');
-                }
-                first = false;
-                serialize_key(k, result);
-                result.push_str(" = ");
-                render_pretty_inline(v, indent, 0, max_inline_width, result);
+        assert!(matches!(
+            parse_object("\n  {a=1}").unwrap_err(),
+            JhonError::Syntax {
+                line: 2,
+                col: 3,
+                ..
             }
-        }
-        Value::Null => {}
-        _ => render_pretty_inline(value, indent, 0, max_inline_width, result),
+        ));
     }
-}
 
-/// Render a single value at `depth`. Caller is responsible for any leading
-/// indent (e.g. after `key = ` or inside an array's child loop).
-fn render_pretty_inline(value: &Value, indent: &str, depth: usize, max_inline_width: usize, result: &mut String) {
-    match value {
-        Value::String(s) => { serialize_string(s, result); return; }
-        Value::Number(n) => { serialize_number(n, result); return; }
-        Value::Bool(b) => { result.push_str(if *b { "true" } else { "false" }); return; }
-        Value::Null => { result.push_str("null"); return; }
-        Value::Object(map) if map.is_empty() => { result.push_str("{}"); return; }
-        Value::Array(arr) if arr.is_empty() => { result.push_str("[]"); return; }
-        _ => {}
+    // =========================================================================
+    // Tokens
+    // =========================================================================
+
+    #[test]
+    fn tokens_kinds_and_slices() {
+        let input = "// cfg\nname = 'x', tags=[r\"a\", 1.5] /* c */\ntrue=null";
+        let got: Vec<(TokenKind, &str)> = tokens(input)
+            .map(|t| t.map(|t| (t.kind, t.text)).unwrap())
+            .collect();
+        use TokenKind::*;
+        assert_eq!(
+            got,
+            vec![
+                (LineComment, "// cfg"),
+                (Whitespace, "\n"),
+                (Key, "name"),
+                (Whitespace, " "),
+                (Equals, "="),
+                (Whitespace, " "),
+                (String, "'x'"),
+                (Comma, ","),
+                (Whitespace, " "),
+                (Key, "tags"),
+                (Equals, "="),
+                (OpenBracket, "["),
+                (RawString, "r\"a\""),
+                (Comma, ","),
+                (Whitespace, " "),
+                (Number, "1.5"),
+                (CloseBracket, "]"),
+                (Whitespace, " "),
+                (BlockComment, "/* c */"),
+                (Whitespace, "\n"),
+                (Key, "true"),
+                (Equals, "="),
+                (Null, "null"),
+            ]
+        );
+        let rebuilt: std::string::String = tokens(input).map(|t| t.unwrap().text).collect();
+        assert_eq!(rebuilt, input);
     }
 
-    // Try fully-inline form first.
-    let mut inline_buf = String::new();
-    push_inline(value, &mut inline_buf);
-    if inline_buf.len() <= max_inline_width {
-        result.push_str(&inline_buf);
-        return;
+    #[test]
+    fn tokens_stop_at_lexical_error() {
+        let mut iter = tokens("a = \"open");
+        assert_eq!(iter.next().unwrap().unwrap().kind, TokenKind::Key);
+        assert_eq!(iter.nth(2).unwrap().unwrap().kind, TokenKind::Whitespace);
+        assert!(matches!(
+            iter.next(),
+            Some(Err(JhonError::Syntax {
+                line: 1,
+                col: 5,
+                ..
+            }))
+        ));
+        assert!(iter.next().is_none());
     }
 
-    // Try wrapper_compact: brackets on their own lines, joined children on one line.
-    let mut joined_buf = String::new();
-    push_joined_children(value, &mut joined_buf);
-    if !joined_buf.is_empty() && joined_buf.len() <= max_inline_width {
-        let (open, close) = if matches!(value, Value::Object(_)) { ('{', '}') } else { ('[', ']') };
-        result.push(open);
-        result.push('\n');
-        push_indent(result, indent, depth + 1);
-        result.push_str(&joined_buf);
-        result.push('\n');
-        push_indent(result, indent, depth);
-        result.push(close);
-        return;
+    // =========================================================================
+    // §7 — Serialization
+    // =========================================================================
+
+    #[test]
+    fn compact_serialize_no_spaces_around_equals() {
+        let value = json!({"name": "John", "age": 30});
+        assert_eq!(serialize(&value), r#"name="John",age=30"#);
     }
 
-    // wrapper_multi: open bracket inline, one child per line, close at parent indent.
-    match value {
-        Value::Object(map) => {
-            result.push('{');
-            for (k, v) in map.iter() {
-                result.push('\n');
-                push_indent(result, indent, depth + 1);
-                serialize_key(k, result);
-                result.push_str(" = ");
-                render_pretty_inline(v, indent, depth + 1, max_inline_width, result);
-            }
-            result.push('\n');
-            push_indent(result, indent, depth);
-            result.push('}');
-        }
-        Value::Array(arr) => {
-            result.push('[');
-            for v in arr.iter() {
-                result.push('\n');
-                push_indent(result, indent, depth + 1);
-                render_pretty_inline(v, indent, depth + 1, max_inline_width, result);
-            }
-            result.push('\n');
-            push_indent(result, indent, depth);
-            result.push(']');
-        }
-        _ => unreachable!(),
+    #[test]
+    fn compact_serialize_nested_object() {
+        let value = json!({"server": {"host": "localhost", "port": 8080}});
+        assert_eq!(serialize(&value), r#"server={host="localhost",port=8080}"#);
     }
-}
 
-/// Append `indent × n` to `result`.
-fn push_indent(result: &mut String, indent: &str, n: usize) {
-    for _ in 0..n {
-        result.push_str(indent);
+    #[test]
+    fn compact_serialize_top_level_array() {
+        // Top-level arrays serialize bare (no surrounding []).
+        let value = json!([{"a": 1}, {"b": 2}]);
+        assert_eq!(serialize(&value), r#"{a=1},{b=2}"#);
     }
-}
 
-/// Single-line rendering of a value with `{ k = v, ... }` / `[ a, b, ... ]`
-/// spacing — including outer brackets/braces. Used both for the inline-mode
-/// emission and for measuring length via the buffer's `len()`.
-fn push_inline(value: &Value, out: &mut String) {
-    match value {
-        Value::Object(map) if map.is_empty() => out.push_str("{}"),
-        Value::Object(map) => {
-            out.push_str("{ ");
-            let mut first = true;
-            for (k, v) in map.iter() {
-                if !first { out.push_str(", "); }
-                first = false;
-                serialize_key(k, out);
-                out.push_str(" = ");
-                push_inline(v, out);
-            }
-            out.push_str(" }");
-        }
-        Value::Array(arr) if arr.is_empty() => out.push_str("[]"),
-        Value::Array(arr) => {
-            out.push_str("[ ");
-            let mut first = true;
-            for v in arr.iter() {
-                if !first { out.push_str(", "); }
-                first = false;
-                push_inline(v, out);
-            }
-            out.push_str(" ]");
-        }
-        Value::String(s) => serialize_string(s, out),
-        Value::Number(n) => serialize_number(n, out),
-        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
-        Value::Null => out.push_str("null"),
+    #[test]
+    fn compact_serialize_has_no_trailing_comma() {
+        let value = json!({"a": 1, "b": 2});
+        let s = serialize(&value);
+        assert!(!s.ends_with(','));
     }
+
+    #[test]
+    fn pretty_serialize_spaces_around_equals_no_trailing_commas() {
+        let value = json!({"name": "John", "age": 30});
+        assert_eq!(serialize_pretty(&value, "  "), "name = \"John\"\nage = 30");
+    }
+
+    #[test]
+    fn pretty_serialize_nested_object() {
+        let value = json!({"server": {"host": "localhost", "port": 8080}});
+        assert_eq!(
+            serialize_pretty(&value, "  "),
+            "server = {\n  host = \"localhost\"\n  port = 8080\n}"
+        );
+    }
+
+    #[test]
+    fn pretty_serialize_deeply_nested() {
+        // Mixed nesting stress test: arrays of objects containing arrays,
+        // some with nested objects; deep object chains; arrays of arrays
+        // containing objects. Verifies byte-level output across impls.
+        let value = json!({
+            "a1": [
+                {"b1": ["c1"]},
+                {"b1": ["c1", {"d1": 4}]},
+                {"b1": ["c1"]},
+                {"b1": ["c1"]},
+                {"b1": ["c1"]},
+                {"b1": ["c1"]}
+            ],
+            "a2": {
+                "b2": {
+                    "c2": {"d2": "hahaha"},
+                    "c3": {"d3": "hohohoh"}
+                }
+            },
+            "a3": [
+                ["b4", "b5", "b6", {"c4": ["d1", "d3"]}]
+            ]
+        });
+        let expected = r#"a1 = [
+	{ b1 = [ "c1" ] }
+	{ b1 = [ "c1", { d1 = 4 } ] }
+	{ b1 = [ "c1" ] }
+	{ b1 = [ "c1" ] }
+	{ b1 = [ "c1" ] }
+	{ b1 = [ "c1" ] }
+]
+a2 = {
+	b2 = {
+		c2 = { d2 = "hahaha" }
+		c3 = { d3 = "hohohoh" }
+	}
 }
+a3 = [
+	[
+		"b4", "b5", "b6", { c4 = [ "d1", "d3" ] }
+	]
+]"#;
+        assert_eq!(
+            serialize_pretty_with_options(
+                &value,
+                &PrettyOptions {
+                    indent: "\t".to_string(),
+                    max_inline_width: 44,
+                    ..Default::default()
+                }
+            ),
+            expected
+        );
+    }
 
-/// Like [``push_inline``] but without the outer brackets/braces — just the
-/// joined children. Returns empty string for scalars and empty containers.
-fn push_joined_children(value: &Value, out: &mut String) {
-    match value {
-        Value::Object(map) => {
-            let mut first = true;
-            for (k, v) in map.iter() {
-                if !first { out.push_str(", "); }
-                first = false;
-                serialize_key(k, out);
-                out.push_str(" = ");
-                push_inline(v, out);
-            }
-        }
-        Value::Array(arr) => {
-            let mut first = true;
-            for v in arr.iter() {
-                if !first { out.push_str(", "); }
-                first = false;
-                push_inline(v, out);
-            }
-        }
-        _ => {}
+    #[test]
+    fn pretty_serialize_array_no_trailing_commas() {
+        // Top-level arrays serialize bare: one element per line, no [].
+        let value = json!([1, 2, 3, "hello"]);
+        assert_eq!(serialize_pretty(&value, "  "), "1\n2\n3\n\"hello\"");
     }
-}
 
-fn needs_quoting(s: &str) -> bool {
-    if s.is_empty() {
-        return true;
+    #[test]
+    fn pretty_serialize_empty_containers_as_braces() {
+        let value = json!({"outer": {}, "list": []});
+        assert_eq!(serialize_pretty(&value, "  "), "outer = {}\nlist = []");
+
+        let nested = json!({"a": {"b": {}, "c": [[], {}]}});
+        let out = serialize_pretty(&nested, "  ");
+        assert_eq!(out, "a = {\n  b = {}\n  c = [\n    []\n    {}\n  ]\n}");
+        assert!(!out.contains("= \n"));
+        assert_eq!(parse(&out).unwrap(), nested);
+
+        let inline = PrettyOptions {
+            max_inline_width: 80,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_pretty_with_options(&nested, &inline),
+            "a = { b = {}, c = [ [], {} ] }"
+        );
     }
-    // Quote iff the key contains a byte that would terminate a bare key.
-    s.bytes().any(is_key_delimiter)
-}
 
-/// Returns true for any byte that terminates a bare key (SPEC.md §3.3).
-/// All such bytes are ASCII, so UTF-8 continuation/lead bytes never match.
-fn is_key_delimiter(b: u8) -> bool {
-    matches!(
-        b,
-        b' ' | b'\t'
-            | b'\n'
-            | b'\r'
-            | b'='
-            | b','
-            | b'{'
-            | b'}'
-            | b'['
-            | b']'
-            | b'/'
-            | b'"'
-            | b'\''
-            | b'#'
-    )
-}
+    #[test]
+    fn pretty_serialize_wraps_long_strings() {
+        let long = "lorem ipsum dolor sit amet ".repeat(8);
+        let long = long.trim_end();
+        assert!(long.len() >= 200);
+        let value = json!({"server": {"motd": long}, "short": "ok"});
+        let opts = PrettyOptions {
+            wrap_strings_at: Some(80),
+            ..Default::default()
+        };
+        let out = serialize_pretty_with_options(&value, &opts);
 
-// =============================================================================
-// Tests
-// =============================================================================
+        assert!(out.lines().count() > 5, "{out}");
+        assert!(out.lines().all(|line| line.len() <= 80 + 12), "{out}");
+        assert!(out.contains("short = \"ok\""));
+        assert_eq!(parse(&out).unwrap(), value);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
+    #[test]
+    fn pretty_serialize_wrap_keeps_escapes_and_leading_spaces() {
+        let text = format!("{}\n{}  tail", "x".repeat(9), "y".repeat(8));
+        let value = json!({"s": text});
+        let opts = PrettyOptions {
+            wrap_strings_at: Some(10),
+            ..Default::default()
+        };
+        let out = serialize_pretty_with_options(&value, &opts);
+        assert_eq!(out, "s = \"xxxxxxxxx\\\n  \\nyyyyyyyy\\\n  \\u0020 tail\"");
+        assert_eq!(parse(&out).unwrap(), value);
+    }
 
-    // =========================================================================
-    // §2 — Document Form
-    // =========================================================================
+    #[test]
+    fn preview_elides_containers_below_max_depth() {
+        let value = json!({
+            "server": {"tls": {"cert": "a.pem"}, "hosts": ["x", ["y"]], "port": 80},
+            "empty": {}
+        });
+        assert_eq!(
+            serialize_preview(&value, 1, "  "),
+            "server = {\n  tls = {…}\n  hosts = […]\n  port = 80\n}\nempty = {}"
+        );
+        assert_eq!(
+            serialize_preview(&value, 0, "  "),
+            "server = {…}\nempty = {}"
+        );
+        assert_eq!(
+            serialize_preview(&value, 3, "  "),
+            serialize_pretty(&value, "  ")
+        );
+    }
 
     #[test]
-    fn empty_input_parses_to_null() {
-        assert_eq!(parse("").unwrap(), json!(null));
+    fn serialize_with_digit_grouping() {
+        let value = json!({"size": 1048576, "neg": -1234, "small": 999, "f": 1.5, "big": 12345.0});
+        let opts = SerializeOptions {
+            digit_grouping: true,
+            ..Default::default()
+        };
+        let out = serialize_with(&value, &opts);
+        assert_eq!(out, "size=1_048_576,neg=-1_234,small=999,f=1.5,big=12_345");
+        assert_eq!(parse(&out).unwrap()["size"], 1048576);
+        assert!(values_equal_loose(&parse(&out).unwrap(), &value));
+
+        let pretty = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            digit_grouping: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&json!({"a": {"n": 1000000}}), &pretty),
+            "a = {\n  n = 1_000_000\n}"
+        );
     }
 
     #[test]
-    fn whitespace_only_input_parses_to_null() {
-        assert_eq!(parse("   \n\t\r\n  ").unwrap(), json!(null));
+    fn serialize_with_compact_nested() {
+        let config = parse(COMPLEX_EXAMPLE).unwrap();
+        let opts = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            compact_nested: true,
+            ..Default::default()
+        };
+        let out = serialize_with(&config, &opts);
+        assert_eq!(out.lines().count(), config.as_object().unwrap().len());
+        assert!(
+            out.contains("\nlimits = { max_file_size = 1048576, max_files_per_user = 100 }\n"),
+            "{out}"
+        );
+        assert_eq!(parse(&out).unwrap(), config);
+
+        let value = json!({"name": "app", "db": {"host": "h", "ports": [1, 2]}, "empty": {}});
+        assert_eq!(
+            serialize_with(&value, &opts),
+            "name = \"app\"\ndb = { host = \"h\", ports = [ 1, 2 ] }\nempty = {}"
+        );
+        assert_eq!(
+            serialize_with(&json!([{"a": 1}, [2]]), &opts),
+            "{ a = 1 }\n[ 2 ]"
+        );
+        // Compact output already has no line breaks.
+        let compact = SerializeOptions {
+            compact_nested: true,
+            ..Default::default()
+        };
+        assert_eq!(serialize_with(&value, &compact), serialize(&value));
     }
 
     #[test]
-    fn comments_only_input_parses_to_null() {
+    fn serialize_with_bareword_strings_quotes_keyword_and_number_shapes() {
+        let value = json!({
+            "root": "/usr/local/bin",
+            "path": "C:\\Users\\me",
+            "level": "info",
+            "t": "true",
+            "n": "null",
+            "num": "123",
+            "neg": "-x",
+            "empty": "",
+            "spaced": "a b",
+            "comment": "a//b",
+            "list": ["on", "false", "x,y"],
+            "raw": "r#x"
+        });
+        let opts = SerializeOptions {
+            bareword_strings: true,
+            ..Default::default()
+        };
+        let out = serialize_with(&value, &opts);
         assert_eq!(
-            parse("// just a comment\n/* block */").unwrap(),
-            json!(null)
+            out,
+            r#"root=/usr/local/bin,path=C:\Users\me,level=info,t="true",n="null",num="123",neg="-x",empty="",spaced="a b",comment="a//b",list=[on,"false","x,y"],raw="r#x""#
         );
+        let barewords = ParseOptions {
+            bareword_values: true,
+            ..Default::default()
+        };
+        assert_eq!(parse_with_options(&out, &barewords).unwrap(), value);
+
+        // The document is trimmed of Unicode whitespace, so a bareword must
+        // not begin or end with any.
+        let edges = json!({"a": "x\u{3000}", "b": "\u{a0}y"});
+        let out = serialize_with(&edges, &opts);
+        assert_eq!(out, "a=\"x\u{3000}\",b=\"\u{a0}y\"");
+        assert_eq!(parse_with_options(&out, &barewords).unwrap(), edges);
+
+        let pretty = SerializeOptions {
+            pretty: Some(PrettyOptions {
+                wrap_strings_at: Some(4),
+                ..Default::default()
+            }),
+            ..opts
+        };
+        let out = serialize_with(&value, &pretty);
+        assert!(out.contains("level = info\n"), "{}", out);
+        assert!(out.contains("t = \"true\"\n"), "{}", out);
+        assert_eq!(parse_with_options(&out, &barewords).unwrap(), value);
     }
 
     #[test]
-    fn top_level_object_without_braces() {
+    fn serialize_with_group_blank_lines_separates_key_prefixes() {
+        let value = json!({
+            "db_host": "h",
+            "db_port": 5432,
+            "server_host": "s",
+            "server.port": 80,
+            "name": "app",
+            "nested": {"a_x": 1, "b_y": 2}
+        });
+        let opts = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            group_blank_lines: true,
+            ..Default::default()
+        };
+        let out = serialize_with(&value, &opts);
         assert_eq!(
-            parse(r#"name="x",port=80"#).unwrap(),
-            json!({"name": "x", "port": 80})
+            out,
+            "db_host = \"h\"\ndb_port = 5432\n\nserver_host = \"s\"\nserver.port = 80\n\nname = \"app\"\n\nnested = {\n  a_x = 1\n  b_y = 2\n}"
+        );
+        assert_eq!(parse(&out).unwrap(), value);
+        assert_eq!(
+            serialize_with(
+                &value,
+                &SerializeOptions {
+                    pretty: None,
+                    ..opts
+                }
+            ),
+            serialize(&value)
         );
     }
 
     #[test]
-    fn top_level_object_with_braces_is_single_element_array() {
-        // Per SPEC.md §2: top-level `{...}` is always one element of the
-        // implicit top-level array, never a document wrapper.
+    fn serialize_with_dotted_keys_round_trips() {
+        let value = json!({
+            "name": "app",
+            "server": {"host": "localhost", "port": 8080, "tls": {"on": true}},
+            "empty": {},
+            "list": [{"a": {"b": 1}}]
+        });
+        let opts = SerializeOptions {
+            dotted_keys: true,
+            ..Default::default()
+        };
+        let out = serialize_with(&value, &opts);
         assert_eq!(
-            parse(r#"{name="x",port=80}"#).unwrap(),
-            json!([{"name": "x", "port": 80}])
+            out,
+            r#"name="app",server.host="localhost",server.port=8080,server.tls.on=true,empty={},list=[{a={b=1}}]"#
+        );
+        let nested = ParseOptions {
+            dotted_keys_to_nested: true,
+            ..Default::default()
+        };
+        assert_eq!(parse_with_options(&out, &nested).unwrap(), value);
+
+        let pretty = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            ..opts
+        };
+        let out = serialize_with(&json!({"server": {"host": "h", "port": 1}}), &pretty);
+        assert_eq!(out, "server.host = \"h\"\nserver.port = 1");
+        assert_eq!(
+            parse_with_options(&out, &nested).unwrap(),
+            json!({"server": {"host": "h", "port": 1}})
         );
     }
 
     #[test]
-    fn top_level_explicit_array_is_single_element_array() {
-        // Per SPEC.md §2: top-level `[...]` is always one element of the
-        // implicit top-level array, never a document wrapper.
-        assert_eq!(parse("[1, 2, 3]").unwrap(), json!([[1, 2, 3]]));
+    fn serialize_with_array_expand_threshold() {
+        let value = json!({
+            "short": [1, 2, 3],
+            "long": [1, 2, 3, 4, 5, 6, 7, 8, 9, 10],
+            "pairs": [{"a": 1}, {"b": 2}]
+        });
+        let opts = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            array_expand_threshold: 5,
+            ..Default::default()
+        };
+        let out = serialize_with(&value, &opts);
+        let long: String = (1..=10).map(|n| format!("\n  {}", n)).collect();
+        assert_eq!(
+            out,
+            format!(
+                "short = [ 1, 2, 3 ]\nlong = [{}\n]\npairs = [ {{ a = 1 }}, {{ b = 2 }} ]",
+                long
+            )
+        );
+        assert_eq!(parse(&out).unwrap(), value);
+
+        // The threshold beats a width that would fit the long array inline.
+        let wide = SerializeOptions {
+            pretty: Some(PrettyOptions {
+                max_inline_width: 80,
+                ..Default::default()
+            }),
+            ..opts
+        };
+        assert_eq!(serialize_with(&value, &wide), out);
     }
 
     #[test]
-    fn top_level_scalar_number() {
-        assert_eq!(parse("42").unwrap(), json!([42]));
+    fn serialize_with_wrap_root_in_braces() {
+        let value = json!({"a": 1, "b": {"c": [true]}});
+        let wrapped = SerializeOptions {
+            wrap_root_in_braces: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&value, &SerializeOptions::default()),
+            "a=1,b={c=[true]}"
+        );
+        assert_eq!(serialize_with(&value, &wrapped), "{a=1,b={c=[true]}}");
+        let pretty = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            ..wrapped.clone()
+        };
+        assert_eq!(
+            serialize_with(&value, &pretty),
+            "{\n  a = 1\n  b = {\n    c = [\n      true\n    ]\n  }\n}"
+        );
+
+        // Both forms read back: the bare one as a document, the braced one
+        // as a single root value (plain `parse` sees an array element).
+        let single = ParseOptions {
+            single_root_value: true,
+            ..Default::default()
+        };
+        assert_eq!(parse("a=1,b={c=[true]}").unwrap(), value);
+        for text in [
+            serialize_with(&value, &wrapped),
+            serialize_with(&value, &pretty),
+        ] {
+            assert_eq!(parse_with_options(&text, &single).unwrap(), value);
+            assert_eq!(parse(&text).unwrap(), json!([value]));
+        }
+
+        assert_eq!(serialize_with(&json!({}), &wrapped), "{}");
+        assert_eq!(serialize_with(&json!([1, 2]), &wrapped), "1,2");
     }
 
     #[test]
-    fn top_level_scalar_string() {
-        assert_eq!(parse(r#""hello""#).unwrap(), json!(["hello"]));
+    fn serialize_with_exponential_threshold() {
+        let value =
+            parse("tiny = 0.000000001, huge = 12345678.5, mid = 0.25, n = 100000000").unwrap();
+        assert_eq!(
+            serialize(&value),
+            "tiny=0.000000001,huge=12345678.5,mid=0.25,n=100000000"
+        );
+
+        let opts = SerializeOptions {
+            exponential_threshold: Some(1e6),
+            ..Default::default()
+        };
+        let out = serialize_with(&value, &opts);
+        assert_eq!(out, "tiny=1e-9,huge=1.23456785e7,mid=0.25,n=100000000");
+        // Through `serialize`, since under the `decimal` feature `1e-9` and
+        // `0.000000001` are unequal `Number`s.
+        assert_eq!(serialize(&parse(&out).unwrap()), serialize(&value));
+
+        assert_eq!(
+            serialize_with(&json!([-0.00000025, 0.0, 1e6]), &opts),
+            "-2.5e-7,0,1e6"
+        );
     }
 
     #[test]
-    fn top_level_scalar_boolean() {
-        assert_eq!(parse("true").unwrap(), json!([true]));
-        assert_eq!(parse("false").unwrap(), json!([false]));
+    fn serialize_pretty_writer_matches_serialize_pretty() {
+        let large = json!({
+            "items": (0..2_000)
+                .map(|i| json!({"id": i, "tags": ["a", "b"], "name": format!("item {}", i)}))
+                .collect::<Vec<_>>()
+        });
+        let values = [
+            parse(COMPLEX_EXAMPLE).unwrap(),
+            json!([1, {"a": []}, [[2]], {}]),
+            json!({"a": {}, "b": [], "c": null}),
+            json!("scalar"),
+            json!(null),
+            large,
+        ];
+        for value in &values {
+            for indent in ["  ", "\t"] {
+                let mut out = Vec::new();
+                serialize_pretty_writer(value, indent, &mut out).unwrap();
+                assert_eq!(
+                    String::from_utf8(out).unwrap(),
+                    serialize_pretty(value, indent)
+                );
+            }
+        }
     }
 
     #[test]
-    fn top_level_scalar_null() {
-        assert_eq!(parse("null").unwrap(), json!([null]));
+    fn serialize_pretty_writer_reports_io_errors() {
+        struct Full;
+        impl std::io::Write for Full {
+            fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::WriteZero.into())
+            }
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+        assert!(matches!(
+            serialize_pretty_writer(&json!({"a": 1}), "  ", Full),
+            Err(JhonError::Io(_))
+        ));
     }
 
     #[test]
-    fn top_level_multiple_scalars_newline_separated() {
-        assert_eq!(parse("1\n2\n3").unwrap(), json!([1, 2, 3]));
+    fn serialize_with_defaults_match_serialize() {
+        let value = parse(COMPLEX_EXAMPLE).unwrap();
+        assert_eq!(
+            serialize_with(&value, &SerializeOptions::default()),
+            serialize(&value)
+        );
+        let pretty = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&value, &pretty),
+            serialize_pretty(&value, "  ")
+        );
     }
 
     #[test]
-    fn top_level_multiple_scalars_comma_separated() {
-        assert_eq!(parse(r#"1,2,"haha""#).unwrap(), json!([1, 2, "haha"]));
+    fn flat_string_objects_serialize_like_the_general_path() {
+        let value =
+            json!({"greeting": "hi \"you\"", "my key": "a\nb", "true": "\u{2028}", "e": ""});
+        let text = serialize(&value);
+        assert_eq!(
+            text,
+            r#"greeting="hi \"you\"","my key"="a\nb","true"="\u2028",e="""#
+        );
+        let mut general = String::new();
+        serialize_compact(&value, &SerializeOptions::DEFAULT, &mut general);
+        assert_eq!(text, general);
+        assert_eq!(parse(&text).unwrap(), value);
+
+        // Options that change how strings are written skip the fast path.
+        let bare = SerializeOptions {
+            bareword_strings: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&json!({"a": "x", "b": "y z"}), &bare),
+            r#"a=x,b="y z""#
+        );
+        let raw = SerializeOptions {
+            escape_js_unsafe: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&json!({"a": "\u{2028}"}), &raw),
+            "a=\"\u{2028}\""
+        );
     }
 
     #[test]
-    fn top_level_mixed_scalars_and_object() {
-        // The example from the spec change request.
+    fn serialize_with_combines_options() {
+        let value = json!({
+            "db_host": "/var/run/db",
+            "db_port": 5432,
+            "server": {"max_bytes": 1048576, "name": "web 1"},
+        });
+
+        let compact = SerializeOptions {
+            digit_grouping: true,
+            bareword_strings: true,
+            dotted_keys: true,
+            ..Default::default()
+        };
+        let text = serialize_with(&value, &compact);
         assert_eq!(
-            parse("1\n2\n\"haha\"\n{a=4}").unwrap(),
-            json!([1, 2, "haha", {"a": 4}])
+            text,
+            r#"db_host=/var/run/db,db_port=5_432,server.max_bytes=1_048_576,server.name="web 1""#
+        );
+        let opts = ParseOptions {
+            bareword_values: true,
+            dotted_keys_to_nested: true,
+            ..Default::default()
+        };
+        assert_eq!(parse_with_options(&text, &opts).unwrap(), value);
+
+        let pretty = SerializeOptions {
+            pretty: Some(PrettyOptions::default()),
+            digit_grouping: true,
+            compact_nested: true,
+            group_blank_lines: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&value, &pretty),
+            "db_host = \"/var/run/db\"\ndb_port = 5_432\n\nserver = { max_bytes = 1_048_576, name = \"web 1\" }"
         );
     }
 
     #[test]
-    fn top_level_multiple_objects() {
-        assert_eq!(parse("{a=1}\n{b=2}").unwrap(), json!([{"a": 1}, {"b": 2}]));
+    fn serialize_bounded_stops_at_limit() {
+        let value = json!({"items": (0..100_000).collect::<Vec<_>>()});
+        assert!(matches!(
+            serialize_bounded(&value, 1024),
+            Err(JhonError::OutputTooLarge { limit: 1024 })
+        ));
+
+        let small = parse(COMPLEX_EXAMPLE).unwrap();
+        let full = serialize(&small);
+        assert_eq!(serialize_bounded(&small, full.len()).unwrap(), full);
+        assert!(serialize_bounded(&small, full.len() - 1).is_err());
+        assert_eq!(
+            serialize_bounded(&json!([1, [], {}]), 64).unwrap(),
+            serialize(&json!([1, [], {}]))
+        );
     }
 
     #[test]
-    fn top_level_keyword_as_key_is_object_mode() {
-        // `true`, `false`, `null` in key position are strings, so `true=1`
-        // is object mode, not array mode.
-        assert_eq!(parse("true=1").unwrap(), json!({"true": 1}));
+    fn serialize_whole_floats_beyond_i64_use_exponent() {
+        let value = json!([1e300, -1.6773147187882541e134, 9.3e18, 4.0]);
+        let out = serialize(&value);
+        assert_eq!(out, "1e300,-1.6773147187882541e134,9.3e18,4");
+        assert!(values_equal_loose(&parse(&out).unwrap(), &value));
     }
 
     #[test]
-    fn top_level_numeric_key_is_object_mode() {
-        // Bare identifiers can contain digits, so `42="x"` is object mode.
-        assert_eq!(parse("42=\"x\"").unwrap(), json!({"42": "x"}));
+    fn grouped_serialize_puts_scalars_on_one_line() {
+        let value = parse(COMPLEX_EXAMPLE).unwrap();
+        let out = serialize_grouped(&value, "  ");
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next().unwrap(),
+            r#"app_name = "ocean-note", version = "2.0.0", debug = false"#
+        );
+        assert_eq!(lines.next().unwrap(), "database = {");
+        assert!(out.contains("\nlimits = {\n  max_file_size = 1048576\n"));
+        assert!(out.ends_with("features = [\n  \"auth\"\n  \"logging\"\n  \"caching\"\n]"));
+        assert_eq!(parse(&out).unwrap(), value);
+
+        assert_eq!(
+            serialize_grouped(&json!({"a": {}, "b": [1], "c": 1}), "  "),
+            "a = {}, c = 1\nb = [\n  1\n]"
+        );
     }
 
     #[test]
-    fn top_level_quoted_string_key_is_object_mode() {
-        assert_eq!(parse(r#""key"="value""#).unwrap(), json!({"key": "value"}));
+    fn round_trip_compact_preserves_value() {
+        let original = json!({"name": "John", "age": 30, "active": true});
+        let parsed = parse(&serialize(&original)).unwrap();
+        assert_eq!(original, parsed);
     }
 
     #[test]
-    fn top_level_mixed_pair_then_scalar_is_error() {
-        assert!(parse("a=1\n2").is_err());
+    fn round_trip_pretty_preserves_value() {
+        let original = json!({"name": "John", "tags": ["a", "b"]});
+        let parsed = parse(&serialize_pretty(&original, "  ")).unwrap();
+        assert_eq!(original, parsed);
     }
 
     #[test]
-    fn top_level_mixed_scalar_then_pair_is_error() {
-        assert!(parse("1\na=2").is_err());
+    fn serialize_escapes_line_and_paragraph_separators() {
+        let value = json!({"s": "a\u{2028}b\u{2029}c"});
+        let out = serialize(&value);
+        assert_eq!(out, r#"s="a\u2028b\u2029c""#);
+        assert_eq!(parse(&out).unwrap(), value);
+
+        // Keys too: one holding a separator is quoted so it can be escaped.
+        let value = json!({"k\u{2028}": ["\u{2029}"]});
+        let out = serialize(&value);
+        assert_eq!(out, r#""k\u2028"=["\u2029"]"#);
+        assert!(out.is_ascii());
+        assert_eq!(parse(&out).unwrap(), value);
+        assert!(serialize_pretty(&value, "  ").is_ascii());
+
+        // `escape_js_unsafe` is on by default; off, values keep them raw.
+        let value = json!({"k\u{2028}": "a\u{2028}b", "list": ["\u{2029}"]});
+        let raw = SerializeOptions {
+            escape_js_unsafe: false,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&value, &raw),
+            "\"k\\u2028\"=\"a\u{2028}b\",list=[\"\u{2029}\"]"
+        );
+        assert_eq!(
+            serialize_with(&value, &SerializeOptions::default()),
+            serialize(&value)
+        );
+        let pretty = SerializeOptions {
+            pretty: Some(PrettyOptions {
+                wrap_strings_at: Some(40),
+                ..Default::default()
+            }),
+            ..raw
+        };
+        let out = serialize_with(&value, &pretty);
+        assert!(out.contains("= \"a\u{2028}b\""), "{}", out);
+        assert_eq!(parse(&out).unwrap(), value);
     }
 
     #[test]
-    fn top_level_array_then_pair_is_error() {
-        assert!(parse("[1, 2] key=value").is_err());
+    fn serialize_keeps_non_ascii_in_short_strings() {
+        let value = json!({"s": "h\u{e9}llo", "t": "\u{2027}\u{202a}"});
+        let out = serialize(&value);
+        assert_eq!(out, "s=\"h\u{e9}llo\",t=\"\u{2027}\u{202a}\"");
+        assert_eq!(parse(&out).unwrap(), value);
     }
 
     #[test]
-    fn serialize_top_level_array_compact() {
-        assert_eq!(serialize(&json!([1, 2, "haha"])), r#"1,2,"haha""#);
+    fn serialize_quotes_keyword_number_and_empty_keys() {
+        let value =
+            json!({"true": 1, "null": 2, "42": 3, "": 4, "-1.5e3": 5, "1st": 6, "nullable": 7});
+        let out = serialize(&value);
+        assert_eq!(
+            out,
+            r#""true"=1,"null"=2,"42"=3,""=4,"-1.5e3"=5,1st=6,nullable=7"#
+        );
+        assert_eq!(parse(&out).unwrap(), value);
+        assert_eq!(parse(&serialize_pretty(&value, "  ")).unwrap(), value);
     }
 
     #[test]
-    fn serialize_top_level_array_pretty() {
+    fn underscore_and_digit_keys_quote_only_when_number_shaped() {
+        // `needs_quoting` and the bare-key parser agree: a key is quoted only
+        // if it would read back as a number, keyword or delimiter. Underscore
+        // and digit/letter mixes stay bare, `_1` included.
+        let value = json!({"_1": 1, "_": 2, "1a": 3, "a1": 4, "1": 5, "1_000": 6, "0x1": 7});
+        let out = serialize(&value);
+        assert_eq!(out, r#"_1=1,_=2,1a=3,a1=4,"1"=5,"1_000"=6,"0x1"=7"#);
+        assert_eq!(parse(&out).unwrap(), value);
         assert_eq!(
-            serialize_pretty(&json!([1, 2, "haha"]), "  "),
-            "1\n2\n\"haha\""
+            parse("_1=1, _=2, 1a=3, a1=4, 1=5, 1_000=6").unwrap(),
+            json!({"_1": 1, "_": 2, "1a": 3, "a1": 4, "1": 5, "1_000": 6})
         );
     }
 
     #[test]
-    fn serialize_top_level_array_with_object() {
-        assert_eq!(serialize(&json!([1, {"a": 2}])), "1,{a=2}");
+    fn hex_octal_binary_serialize_as_decimal() {
+        assert_eq!(serialize(&json!({"n": 255})), "n=255");
+        assert_eq!(serialize(&json!({"n": 511})), "n=511");
+        assert_eq!(serialize(&json!({"n": 10})), "n=10");
     }
 
+    // =========================================================================
+    // §8 — Required Parse Errors (cross-cutting)
+    // =========================================================================
+
     #[test]
-    fn serialize_empty_object_to_empty_string() {
-        assert_eq!(serialize(&json!({})), "");
+    fn missing_value_is_error() {
+        assert!(parse("key=").is_err());
+        assert!(parse("key=,").is_err());
+        assert!(parse("a=1, key=, b=2").is_err());
     }
 
     #[test]
-    fn serialize_empty_array_to_empty_string() {
-        assert_eq!(serialize(&json!([])), "");
+    fn unbalanced_braces_are_error() {
+        assert!(parse("{a=1").is_err());
+        assert!(parse("a=1}").is_err());
     }
 
     #[test]
-    fn serialize_top_level_null_to_empty_string() {
-        assert_eq!(serialize(&json!(null)), "");
+    fn missing_value_in_nested_object() {
+        assert!(matches!(
+            parse("x = [{a=}]").unwrap_err(),
+            JhonError::MissingValue { key, line: 1, col: 9 } if key == "a"
+        ));
+        assert!(matches!(
+            parse("x = [{a=, b=1}]").unwrap_err(),
+            JhonError::MissingValue { key, .. } if key == "a"
+        ));
+        assert!(matches!(
+            parse("{a=}").unwrap_err(),
+            JhonError::MissingValue { key, .. } if key == "a"
+        ));
     }
 
     #[test]
-    fn serialize_nested_null_preserved() {
-        assert_eq!(serialize(&json!({"a": null})), "a=null");
+    fn missing_value_at_end_of_input_names_the_key() {
+        let text = "port = 80\nname =";
+        let expected = JhonError::MissingValue {
+            line: 2,
+            col: 7,
+            key: "name".to_string(),
+        };
+        assert_eq!(parse(text).unwrap_err(), expected);
+        assert_eq!(parse_spanned(text).unwrap_err(), expected);
+        assert_eq!(parse_borrowed(text).unwrap_err(), expected);
+        assert_eq!(top_level_keys(text).unwrap_err(), expected);
+        assert_eq!(
+            top_level_keys("\n\n  name = // later\n").unwrap_err(),
+            JhonError::MissingValue {
+                line: 4,
+                col: 1,
+                key: "name".to_string()
+            }
+        );
+        assert_eq!(
+            expected.to_string(),
+            "missing value at 2:7: for key \"name\""
+        );
     }
 
     #[test]
-    fn serialize_nested_array_preserved() {
-        assert_eq!(serialize(&json!({"a": [1, 2, 3]})), "a=[1,2,3]");
+    fn missing_value_at_top_level() {
+        assert!(matches!(
+            parse("a=1\nb=").unwrap_err(),
+            JhonError::MissingValue { key, line: 2, col: 3 } if key == "b"
+        ));
+        assert!(matches!(
+            parse("a=, b=1").unwrap_err(),
+            JhonError::MissingValue { key, .. } if key == "a"
+        ));
+        assert!(matches!(
+            parse_spanned("s={a= // nothing\n}").unwrap_err(),
+            JhonError::MissingValue { key, line: 2, col: 1 } if key == "a"
+        ));
     }
 
     // =========================================================================
-    // §3.2 — Comments
+    // Serde Integration (orthogonal to syntax spec)
     // =========================================================================
 
     #[test]
-    fn single_line_comment_trailing() {
-        assert_eq!(
-            parse(r#"key="value" // trailing comment"#).unwrap(),
-            json!({"key": "value"})
-        );
+    fn serde_round_trip_struct() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Person {
+            name: String,
+            age: u32,
+            email: String,
+        }
+
+        let original = Person {
+            name: "Alice".to_string(),
+            age: 25,
+            email: "alice@example.com".to_string(),
+        };
+
+        let serialized = to_string(&original).unwrap();
+        let deserialized: Person = from_str(&serialized).unwrap();
+        assert_eq!(original, deserialized);
     }
 
     #[test]
-    fn block_comment_inline() {
+    fn serde_nested_struct_round_trip() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct ServerConfig {
+            host: String,
+            port: u16,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct AppConfig {
+            name: String,
+            server: ServerConfig,
+        }
+
+        let config = AppConfig {
+            name: "MyApp".to_string(),
+            server: ServerConfig {
+                host: "localhost".to_string(),
+                port: 8080,
+            },
+        };
+
+        let jhon_string = to_string(&config).unwrap();
         assert_eq!(
-            parse(r#"key=/* inline */"value""#).unwrap(),
-            json!({"key": "value"})
+            jhon_string,
+            r#"name="MyApp",server={host="localhost",port=8080}"#
         );
+        let decoded: AppConfig = from_str(&jhon_string).unwrap();
+        assert_eq!(config, decoded);
     }
 
     #[test]
-    fn block_comment_spanning_lines() {
+    fn serde_struct_with_arrays_round_trip() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Config {
+            tags: Vec<String>,
+            scores: Vec<i32>,
+        }
+
+        let config = Config {
+            tags: vec!["rust".to_string(), "web".to_string()],
+            scores: vec![100, 95, 88],
+        };
+
+        let jhon_string = to_string(&config).unwrap();
+        assert_eq!(jhon_string, r#"tags=["rust","web"],scores=[100,95,88]"#);
+        let decoded: Config = from_str(&jhon_string).unwrap();
+        assert_eq!(config, decoded);
+    }
+
+    #[test]
+    fn serde_option_some_and_none() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Config {
+            name: String,
+            description: Option<String>,
+        }
+
+        let with_some = Config {
+            name: "Test".to_string(),
+            description: Some("A test config".to_string()),
+        };
         assert_eq!(
-            parse("key=/* multi\nline\ncomment */\"value\"").unwrap(),
-            json!({"key": "value"})
+            to_string(&with_some).unwrap(),
+            r#"name="Test",description="A test config""#
         );
+
+        let decoded: Config = from_str(r#"name="Test""#).unwrap();
+        assert_eq!(decoded.name, "Test");
+        assert_eq!(decoded.description, None);
     }
 
     #[test]
-    fn unterminated_block_comment_is_error() {
-        assert!(parse("key=/* unterminated").is_err());
-    }
+    fn serde_option_null_and_absent_both_deserialize_to_none() {
+        use serde::Deserialize;
 
-    // =========================================================================
-    // §3.3 — Bare Keys
-    // =========================================================================
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct S {
+            id: u32,
+            a: Option<i32>,
+        }
+
+        let null: S = from_str("id=1,a=null").unwrap();
+        let absent: S = from_str("id=1").unwrap();
+        assert_eq!(null, S { id: 1, a: None });
+        assert_eq!(absent, S { id: 1, a: None });
+    }
 
     #[test]
-    fn simple_identifier_key() {
-        assert_eq!(
-            parse(r#"keyname="value""#).unwrap(),
-            json!({"keyname": "value"})
-        );
+    fn serde_deserialize_some_distinguishes_null_from_absent() {
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct S {
+            id: u32,
+            #[serde(default, deserialize_with = "deserialize_some")]
+            a: Option<Option<i32>>,
+        }
+
+        let null: S = from_str("id=1,a=null").unwrap();
+        let absent: S = from_str("id=1").unwrap();
+        let present: S = from_str("id=1,a=7").unwrap();
+        assert_eq!(null.a, Some(None));
+        assert_eq!(absent.a, None);
+        assert_eq!(present.a, Some(Some(7)));
     }
 
     #[test]
-    fn keyword_true_as_string_key() {
-        assert_eq!(parse(r#"true="yes""#).unwrap(), json!({"true": "yes"}));
+    fn serde_jhon_wrapper_round_trip() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Point {
+            x: i32,
+            y: i32,
+        }
+
+        let point = Point { x: 10, y: 20 };
+        let jhon_string = Jhon::to_string(&point).unwrap();
+        assert_eq!(jhon_string, "x=10,y=20");
+        let decoded: Point = Jhon::from_str(&jhon_string).unwrap();
+        assert_eq!(point, decoded);
     }
 
     #[test]
-    fn keyword_false_as_string_key() {
-        assert_eq!(parse(r#"false="no""#).unwrap(), json!({"false": "no"}));
+    fn serde_enum_representation() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        enum Status {
+            Active,
+            Inactive,
+            Pending,
+        }
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Task {
+            name: String,
+            status: Status,
+        }
+
+        let task = Task {
+            name: "Task1".to_string(),
+            status: Status::Active,
+        };
+
+        let jhon_string = to_string(&task).unwrap();
+        assert_eq!(jhon_string, r#"name="Task1",status="Active""#);
+        let decoded: Task = from_str(&jhon_string).unwrap();
+        assert_eq!(decoded, task);
     }
 
     #[test]
-    fn keyword_null_as_string_key() {
+    fn serde_internally_tagged_enum_variants() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        #[serde(tag = "type", rename_all = "lowercase")]
+        enum Plugin {
+            Http { url: String },
+            Grpc { endpoint: String, port: u16 },
+        }
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Config {
+            plugins: Vec<Plugin>,
+        }
+
+        let config: Config = from_str(
+            r#"plugins = [
+                {type="http", url="https://example.com"}
+                {type="grpc", endpoint="localhost", port=50051}
+            ]"#,
+        )
+        .unwrap();
         assert_eq!(
-            parse(r#"null="nothing""#).unwrap(),
-            json!({"null": "nothing"})
+            config.plugins,
+            vec![
+                Plugin::Http {
+                    url: "https://example.com".to_string()
+                },
+                Plugin::Grpc {
+                    endpoint: "localhost".to_string(),
+                    port: 50051
+                },
+            ]
+        );
+
+        let single: Plugin = from_str(r#"type="grpc", endpoint="h", port=1"#).unwrap();
+        assert_eq!(
+            to_string(&single).unwrap(),
+            r#"type="grpc",endpoint="h",port=1"#
         );
     }
 
     #[test]
-    fn key_with_hyphen() {
+    fn serde_internally_tagged_enum_unknown_tag_is_error() {
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize)]
+        #[serde(tag = "type")]
+        #[allow(dead_code)]
+        enum Plugin {
+            Http { url: String },
+        }
+
+        let err = from_str::<Plugin>(r#"type="ftp", url="x""#).unwrap_err();
+        assert!(matches!(err, JhonError::Serde(msg) if msg.contains("ftp")));
+    }
+
+    #[test]
+    fn serde_pretty_print_no_trailing_commas() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, Serialize, Deserialize, PartialEq)]
+        struct Config {
+            name: String,
+            age: u32,
+        }
+
+        let config = Config {
+            name: "John".to_string(),
+            age: 30,
+        };
+
         assert_eq!(
-            parse(r#"my-key="value""#).unwrap(),
-            json!({"my-key": "value"})
+            to_string_pretty(&config, "  ").unwrap(),
+            "name = \"John\"\nage = 30"
         );
     }
 
     #[test]
-    fn key_with_underscore_and_digits() {
+    fn serde_maps_serialize_as_objects() {
+        let map = BTreeMap::from([("b".to_string(), 2), ("a".to_string(), 1)]);
+        assert_eq!(Jhon::to_string(&map).unwrap(), "a=1,b=2");
         assert_eq!(
-            parse(r#"key_1="value""#).unwrap(),
-            json!({"key_1": "value"})
+            Jhon::from_str::<BTreeMap<String, i32>>("a=1,b=2").unwrap(),
+            map
+        );
+
+        // Integer keys are stringified, so they come back as quoted keys.
+        let by_id = BTreeMap::from([(2, "two"), (10, "ten")]);
+        assert_eq!(Jhon::to_string(&by_id).unwrap(), r#""2"="two","10"="ten""#);
+        assert_eq!(
+            Jhon::from_str::<BTreeMap<i32, String>>(r#""2"="two""#).unwrap()[&2],
+            "two"
         );
+
+        let mut hash = HashMap::new();
+        hash.insert("only", vec![1, 2]);
+        assert_eq!(Jhon::to_string(&hash).unwrap(), "only=[1,2]");
+        assert_eq!(Jhon::to_string(&HashMap::<String, i32>::new()).unwrap(), "");
+
+        // Keys that are not scalars have no string form.
+        let err = Jhon::to_string(&BTreeMap::from([(vec![1], 1)])).unwrap_err();
+        assert!(matches!(err, JhonError::Serde(_)), "{err}");
     }
 
-    #[test]
-    fn key_with_dot() {
-        assert_eq!(parse("app.version=1").unwrap(), json!({"app.version": 1}));
+    // =========================================================================
+    // Parse options — indentation blocks
+    // =========================================================================
+
+    fn indent_blocks() -> ParseOptions {
+        ParseOptions {
+            indent_blocks: true,
+            ..Default::default()
+        }
     }
 
     #[test]
-    fn unicode_key() {
+    fn indent_block_two_levels_matches_braced_form() {
+        let indented = "name = \"app\"\nserver\n  host = \"localhost\"\n  tls\n    enabled = true\n    port = 443\n  port = 8080\ndebug = false";
+        let braced = r#"name = "app"
+server = { host = "localhost", tls = { enabled = true, port = 443 }, port = 8080 }
+debug = false"#;
         assert_eq!(
-            parse(r#"名前="テスト""#).unwrap(),
-            json!({"名前": "テスト"})
+            parse_with_options(indented, &indent_blocks()).unwrap(),
+            parse(braced).unwrap()
         );
     }
 
     #[test]
-    fn quoted_key_with_spaces() {
+    fn indent_block_as_first_key_and_inside_braces() {
         assert_eq!(
-            parse(r#""quoted key"="value""#).unwrap(),
-            json!({"quoted key": "value"})
+            parse_with_options("db // primary\n  port = 1, user = \"u\"", &indent_blocks())
+                .unwrap(),
+            json!({"db": {"port": 1, "user": "u"}})
+        );
+        assert_eq!(
+            parse_with_options("outer = {\n  inner\n    a = 1\n}", &indent_blocks()).unwrap(),
+            json!({"outer": {"inner": {"a": 1}}})
         );
     }
 
     #[test]
-    fn empty_key_is_error() {
-        assert!(parse("=value").is_err());
+    fn indent_block_inconsistent_indentation_is_error() {
+        assert!(parse_with_options("server\n  a = 1\n    b = 2", &indent_blocks()).is_err());
+        assert!(parse_with_options("server\nport = 1", &indent_blocks()).is_err());
+    }
+
+    #[test]
+    fn indent_block_is_off_by_default() {
+        assert!(parse("server\n  port = 80").is_err());
     }
 
     // =========================================================================
-    // §3.4 — Strings
+    // Parse options — quoted keys
     // =========================================================================
 
-    #[test]
-    fn double_quoted_string() {
-        assert_eq!(parse(r#"key="hello""#).unwrap(), json!({"key": "hello"}));
+    fn quoted_keys() -> ParseOptions {
+        ParseOptions {
+            require_quoted_keys: true,
+            ..Default::default()
+        }
     }
 
     #[test]
-    fn single_quoted_string() {
-        assert_eq!(parse(r#"key='hello'"#).unwrap(), json!({"key": "hello"}));
+    fn unicode_whitespace_around_document_is_trimmed() {
+        assert_eq!(parse("\u{a0}a = 1").unwrap(), json!({"a": 1}));
+        assert_eq!(parse("a = 1\u{3000}").unwrap(), json!({"a": 1}));
+        assert_eq!(parse("\u{a0}1, 2").unwrap(), json!([1, 2]));
+        // Positions still count the trimmed characters.
+        match parse("\u{a0}\u{a0}x = [1,\n") {
+            Err(JhonError::Syntax { msg, .. }) => {
+                assert!(msg.ends_with("started at line 1, column 7"), "{}", msg)
+            }
+            other => panic!("expected syntax error, got {:?}", other),
+        }
+
+        // Keys with Unicode whitespace at either end are quoted to survive it.
+        let value = json!({"\u{2000}": null, "b\u{a0}": 1});
+        let out = serialize(&value);
+        assert_eq!(out, "\"\u{2000}\"=null,\"b\u{a0}\"=1");
+        assert_eq!(parse(&out).unwrap(), value);
     }
 
     #[test]
-    fn string_escape_newline_and_tab() {
+    fn require_quoted_keys_rejects_bare_key() {
+        let err = parse_with_options("\"a\"=1\nname=\"x\"", &quoted_keys()).unwrap_err();
         assert_eq!(
-            parse(r#"key="line1\nline2\tindented""#).unwrap(),
-            json!({"key": "line1\nline2\tindented"})
+            err,
+            JhonError::UnquotedKey {
+                line: 2,
+                col: 1,
+                key: "name".to_string()
+            }
         );
+        assert!(parse_with_options("name=\"x\"", &quoted_keys()).is_err());
+        assert!(parse_with_options(r#""o"={inner=1}"#, &quoted_keys()).is_err());
     }
 
     #[test]
-    fn string_escape_unicode() {
-        assert_eq!(parse(r#"key="é""#).unwrap(), json!({"key": "é"}));
-    }
-
-    #[test]
-    fn string_escape_quote_and_backslash() {
+    fn require_quoted_keys_accepts_quoted_keys_and_bare_values() {
         assert_eq!(
-            parse(r#"key="a\"b\\c""#).unwrap(),
-            json!({"key": "a\"b\\c"})
+            parse_with_options(r#""name"="x", 'o'={"n"=1}"#, &quoted_keys()).unwrap(),
+            json!({"name": "x", "o": {"n": 1}})
         );
-    }
-
-    #[test]
-    fn raw_string_basic() {
         assert_eq!(
-            parse(r###"key=r"C:\path""###).unwrap(),
-            json!({"key": r"C:\path"})
+            parse_with_options("true, 1", &quoted_keys()).unwrap(),
+            json!([true, 1])
         );
     }
 
     #[test]
-    fn raw_string_with_hashes() {
-        let result = parse(r###"key=r#"contains "quotes""#"###).unwrap();
-        assert_eq!(result["key"], r#"contains "quotes""#);
+    fn bare_keys_allowed_by_default() {
+        assert_eq!(parse(r#"name="x""#).unwrap(), json!({"name": "x"}));
     }
 
-    #[test]
-    fn raw_string_multiline() {
-        let result = parse("key=r\"line1\nline2\"").unwrap();
-        assert_eq!(result["key"], "line1\nline2");
+    // =========================================================================
+    // Parse options — bare keys as flags
+    // =========================================================================
+
+    fn bare_flags() -> ParseOptions {
+        ParseOptions {
+            bare_keys_are_true: true,
+            ..Default::default()
+        }
     }
 
     #[test]
-    fn unrecognized_escape_is_error() {
-        assert!(parse(r#"key="\q""#).is_err());
+    fn bare_flag_keys_are_true() {
+        assert_eq!(
+            parse_with_options(r#"verbose, name="x""#, &bare_flags()).unwrap(),
+            json!({"verbose": true, "name": "x"})
+        );
+        assert_eq!(
+            parse_with_options("a = 1\ndebug // on\nopts = {fast, level=2}", &bare_flags())
+                .unwrap(),
+            json!({"a": 1, "debug": true, "opts": {"fast": true, "level": 2}})
+        );
     }
 
     #[test]
-    fn literal_newline_in_regular_string_is_error() {
-        assert!(parse("key=\"line1\nline2\"").is_err());
+    fn bare_flag_requires_separator() {
+        assert!(matches!(
+            parse_with_options("verbose debug", &bare_flags()).unwrap_err(),
+            JhonError::Syntax { .. }
+        ));
+        assert!(parse_with_options("a=1, verbose debug", &bare_flags()).is_err());
     }
 
     #[test]
-    fn unterminated_string_is_error() {
-        assert!(parse(r#"key="unterminated"#).is_err());
+    fn bare_flag_leaves_array_mode_and_default_grammar_alone() {
+        assert_eq!(
+            parse_with_options("1, true", &bare_flags()).unwrap(),
+            json!([1, true])
+        );
+        assert!(parse(r#"verbose, name="x""#).is_err());
     }
 
     // =========================================================================
-    // §3.5 — Numbers
+    // Parse options — JSON-strict preset
     // =========================================================================
 
     #[test]
-    fn decimal_integer() {
-        assert_eq!(parse("n=42").unwrap(), json!({"n": 42}));
-    }
+    fn bareword_values_accept_paths() {
+        let opts = ParseOptions {
+            bareword_values: true,
+            ..Default::default()
+        };
+        assert!(parse(r"path=C:\Users").is_err());
+
+        let text = r"
+windows = C:\Users\me\AppData
+unc = \\server\share
+unix = /usr/local/bin // install prefix
+relative = ./conf.d/app.jhon, home = ~/.config
+";
+        assert_eq!(
+            parse_with_options(text, &opts).unwrap(),
+            json!({
+                "windows": r"C:\Users\me\AppData",
+                "unc": r"\\server\share",
+                "unix": "/usr/local/bin",
+                "relative": "./conf.d/app.jhon",
+                "home": "~/.config",
+            })
+        );
 
-    #[test]
-    fn negative_integer() {
-        assert_eq!(parse("n=-5").unwrap(), json!({"n": -5}));
-    }
+        assert_eq!(
+            parse_with_options("a = [C:\\, /tmp], b = {c = on}, d = true, e = rust", &opts)
+                .unwrap(),
+            json!({"a": ["C:\\", "/tmp"], "b": {"c": "on"}, "d": true, "e": "rust"})
+        );
+        assert_eq!(
+            parse_with_options("p = /a/*note*/, q = r\"raw\"", &opts).unwrap(),
+            json!({"p": "/a", "q": "raw"})
+        );
+        assert_eq!(
+            parse_with_options("error\nwarn", &opts).unwrap(),
+            json!(["error", "warn"])
+        );
+        // `//` always starts a comment, so URLs still need quotes.
+        assert_eq!(
+            parse_with_options("url = https://example.com", &opts).unwrap(),
+            json!({"url": "https:"})
+        );
+        assert!(parse_with_options("name = two words", &opts).is_err());
+        assert!(parse_with_options("ip = 10.0.0.1", &opts).is_err());
 
-    #[test]
-    fn number_with_underscores() {
-        assert_eq!(parse("n=1_000_000").unwrap(), json!({"n": 1000000}));
+        // Quotes inside a bareword are kept; one at the start opens a string.
+        assert_eq!(
+            parse_with_options("q = it's, r = 'it'", &opts).unwrap(),
+            json!({"q": "it's", "r": "it"})
+        );
+
+        // The `reject_*` scan reads barewords the same way.
+        let strict = ParseOptions {
+            reject_nul: true,
+            reject_single_quotes: true,
+            nested_block_comments: true,
+            ..opts
+        };
+        assert_eq!(
+            parse_with_options("p = /usr/bin\nq = it's", &strict).unwrap(),
+            json!({"p": "/usr/bin", "q": "it's"})
+        );
+        assert!(parse_with_options("p = /usr\0bin", &strict).is_err());
+        assert!(parse_with_options("q = 'it'", &strict).is_err());
     }
 
     #[test]
-    fn negative_number_with_underscores() {
-        assert_eq!(parse("n=-50_000").unwrap(), json!({"n": -50000}));
+    fn lowercase_keys_folds_case_and_reports_collisions() {
+        let opts = ParseOptions {
+            lowercase_keys: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_options(r#"Port=1, HOST="x", "Db"={User="Root"}"#, &opts).unwrap(),
+            json!({"port": 1, "host": "x", "db": {"user": "Root"}})
+        );
+        assert_eq!(
+            parse("Port=1, port=2").unwrap(),
+            json!({"Port": 1, "port": 2})
+        );
+        assert!(matches!(
+            parse_with_options("Port=1\nport=2", &opts),
+            Err(JhonError::DuplicateKey { line: 2, key, .. }) if key == "port"
+        ));
+        assert!(matches!(
+            parse_with_options("db={Name=1, NAME=2}", &opts),
+            Err(JhonError::DuplicateKey { .. })
+        ));
     }
 
     #[test]
-    fn float_fractional() {
-        assert_eq!(parse("n=12.5").unwrap(), json!({"n": 12.5}));
+    fn coerce_bool_strings_turns_exact_matches_into_booleans() {
+        let opts = ParseOptions {
+            coerce_bool_strings: true,
+            ..Default::default()
+        };
+        let text = r#"enabled="true", debug='false', raw=r"true", name="true story", upper="TRUE""#;
+        assert_eq!(parse(text).unwrap()["enabled"], "true");
+        assert_eq!(
+            parse_with_options(text, &opts).unwrap(),
+            json!({
+                "enabled": true,
+                "debug": false,
+                "raw": true,
+                "name": "true story",
+                "upper": "TRUE",
+            })
+        );
+        assert_eq!(
+            parse_with_options(r#"flags=["true", "no"], "true"="false""#, &opts).unwrap(),
+            json!({"flags": [true, "no"], "true": false})
+        );
     }
 
     #[test]
-    fn negative_float() {
-        assert_eq!(parse("n=-45.67").unwrap(), json!({"n": -45.67}));
+    fn paren_tuples_parse_as_arrays() {
+        let opts = ParseOptions {
+            paren_tuples: true,
+            ..Default::default()
+        };
+        assert!(parse("pos = (1, 2, 3)").is_err());
+
+        let value = parse_with_options("pos = (1,2,3), name = \"p\"", &opts).unwrap();
+        assert_eq!(value, json!({"pos": [1, 2, 3], "name": "p"}));
+        assert_eq!(serialize(&value), r#"pos=[1,2,3],name="p""#);
+
+        assert_eq!(
+            parse_with_options("m = ((1, 2), (3, 4))", &opts).unwrap(),
+            json!({"m": [[1, 2], [3, 4]]})
+        );
+        assert_eq!(
+            parse_with_options("a = ([1], {b = (true)}, ())", &opts).unwrap(),
+            json!({"a": [[1], {"b": [true]}, []]})
+        );
+        assert_eq!(
+            parse_with_options("(1, 2)\n(3, 4)", &opts).unwrap(),
+            json!([[1, 2], [3, 4]])
+        );
+        assert_eq!(
+            parse_with_options("pos = (\n  1\n  2 // two\n)", &opts).unwrap(),
+            json!({"pos": [1, 2]})
+        );
+
+        assert!(parse_with_options("pos = (1 2)", &opts).is_err());
+        assert!(parse_with_options("pos = (1, 2", &opts).is_err());
+        assert!(parse_with_options("pos = (1, 2]", &opts).is_err());
     }
 
     #[test]
-    fn float_with_exponent_only() {
-        assert_eq!(parse("n=1e10").unwrap(), json!({"n": 1e10}));
+    fn plus_sign_numbers_parse_and_round_trip_in_spanned() {
+        let opts = ParseOptions {
+            plus_sign_numbers: true,
+            ..Default::default()
+        };
+        assert!(parse("temp = +5").is_err());
+
+        let value =
+            parse_with_options("temp = +5, ratio = +5.0, big = +1e3, neg = -5", &opts).unwrap();
+        assert_eq!(
+            value,
+            json!({"temp": 5, "ratio": 5.0, "big": 1000.0, "neg": -5})
+        );
+        assert_eq!(serialize(&value), "temp=5,ratio=5,big=1000,neg=-5");
+        assert_eq!(
+            parse_with_options("+5, +0.5", &opts).unwrap(),
+            json!([5, 0.5])
+        );
+
+        let doc = parse_spanned_with_options("temp = +5, ratio = +5.0, n = 5", &opts).unwrap();
+        let Node::Object(entries) = &doc.node else {
+            panic!("expected object");
+        };
+        assert!(matches!(
+            entries[0].1.node,
+            Node::Number {
+                plus_sign: true,
+                ..
+            }
+        ));
+        assert!(matches!(
+            entries[2].1.node,
+            Node::Number {
+                plus_sign: false,
+                ..
+            }
+        ));
+        assert_eq!(serialize_spanned(&doc), "temp=+5,ratio=+5,n=5");
+        assert_eq!(doc.to_value(), json!({"temp": 5, "ratio": 5.0, "n": 5}));
+        assert!(parse_spanned("temp = +5").is_err());
+
+        for bad in [
+            "x = +", "x = + 5", "x = +-5", "x = ++5", "x = +.5", "x = +e5",
+        ] {
+            assert!(parse_with_options(bad, &opts).is_err(), "{bad}");
+        }
     }
 
     #[test]
-    fn float_with_fractional_and_exponent() {
-        assert_eq!(parse("n=1.5E-3").unwrap(), json!({"n": 1.5e-3}));
+    fn max_string_len_rejects_long_strings() {
+        let opts = ParseOptions {
+            max_string_len: Some(5),
+            heredoc_strings: true,
+            ..Default::default()
+        };
+        let ok = r#"a = "12345", b = '\t\t\t\t\t', c = r"12345", "keyed" = 1"#;
+        assert_eq!(parse_with_options(ok, &opts).unwrap()["b"], "\t\t\t\t\t");
+
+        for text in [
+            r#"a = "123456""#,
+            r#"a = 'abc\ndef'"#,
+            r##"a = r#"123456"#"##,
+            r#""long key" = 1"#,
+            "a = <<END\n123\n456\nEND",
+        ] {
+            let err = parse_with_options(text, &opts).unwrap_err();
+            assert!(
+                matches!(err, JhonError::StringTooLong { max: 5, .. }),
+                "{text}: {err}"
+            );
+        }
+        // The scan stops at the cap, before the missing closing quote.
+        let err = parse_with_options("x = 1\ny = \"unterminated and long", &opts).unwrap_err();
+        assert_eq!(err.to_string(), "string at 2:6 exceeds 5 bytes");
+        assert!(parse(r#"a = "123456""#).is_ok());
     }
 
     #[test]
-    fn hex_literal_lowercase() {
-        assert_eq!(parse("n=0xff").unwrap(), json!({"n": 255}));
+    #[cfg(feature = "decimal")]
+    fn decimal_feature_keeps_fractions_exact() {
+        let text = "price = 99.99, rate = 0.1, tiny = 0.100_000_000_000_000_000_001, pad = -007.50";
+        let value = parse(text).unwrap();
+        assert_eq!(value["price"].as_number().unwrap().as_str(), "99.99");
+        assert_eq!(
+            value["tiny"].as_number().unwrap().as_str(),
+            "0.100000000000000000001"
+        );
+        assert_eq!(value["pad"].as_number().unwrap().as_str(), "-7.50");
+        assert_eq!(
+            serialize(&value),
+            "price=99.99,rate=0.1,tiny=0.100000000000000000001,pad=-7.50"
+        );
+        assert_eq!(parse(&serialize(&value)).unwrap(), value);
+        assert_eq!(parse("n = 42").unwrap()["n"].as_i64(), Some(42));
+
+        // Whole values are written as integers, as in the default build, so
+        // digit grouping applies; fractions stay exact and ungrouped.
+        let value = parse("a = 5.0, b = 12345.000, c = -0.0, d = 1.5e3, e = 1234.5").unwrap();
+        assert_eq!(serialize(&value), "a=5,b=12345,c=0,d=1500,e=1234.5");
+        let grouped = SerializeOptions {
+            digit_grouping: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with(&value, &grouped),
+            "a=5,b=12_345,c=0,d=1_500,e=1234.5"
+        );
     }
 
     #[test]
-    fn hex_literal_uppercase_digits() {
-        assert_eq!(parse("n=0xDE_AD").unwrap(), json!({"n": 0xDE_AD}));
+    fn plus_sign_is_dropped_on_serialize() {
+        let opts = ParseOptions {
+            plus_sign_numbers: true,
+            ..Default::default()
+        };
+        let value =
+            parse_with_options("a = +5, b = +5.5, c = +0, d = +0x10, e = +1_000", &opts).unwrap();
+        assert_eq!(value, json!({"a": 5, "b": 5.5, "c": 0, "d": 16, "e": 1000}));
+        assert_eq!(serialize(&value), "a=5,b=5.5,c=0,d=16,e=1000");
+        assert_eq!(parse(&serialize(&value)).unwrap(), value);
+        // SPEC.md §3.5: without the option, `+` stays a syntax error.
+        assert!(parse("offset = +5").is_err());
     }
 
     #[test]
-    fn octal_literal() {
-        assert_eq!(parse("n=0o777").unwrap(), json!({"n": 511}));
+    fn int_overflow_policy_handles_30_digit_literals() {
+        let big = "123456789012345678901234567890";
+        let with = |on_int_overflow| ParseOptions {
+            on_int_overflow,
+            ..Default::default()
+        };
+        let n = |text: &str, policy| {
+            parse_with_options(&format!("n = {}", text), &with(policy)).map(|v| v["n"].clone())
+        };
+
+        assert_eq!(
+            n(big, OverflowPolicy::Float).unwrap(),
+            json!(1.2345678901234568e29)
+        );
+        assert_eq!(
+            parse(&format!("n = {}", big)).unwrap()["n"],
+            json!(1.2345678901234568e29)
+        );
+        assert_eq!(n(big, OverflowPolicy::Saturate).unwrap(), json!(u64::MAX));
+        assert_eq!(
+            n(&format!("-{}", big), OverflowPolicy::Saturate).unwrap(),
+            json!(i64::MIN)
+        );
+        assert_eq!(
+            n("0x1_0000_0000_0000_0000", OverflowPolicy::Saturate).unwrap(),
+            json!(u64::MAX)
+        );
+        match n(big, OverflowPolicy::Error) {
+            Err(JhonError::Syntax { msg, .. }) => assert!(msg.contains(big), "{}", msg),
+            other => panic!("expected syntax error, got {:?}", other),
+        }
+        match n("-0x1_0000_0000_0000_0000", OverflowPolicy::Error) {
+            Err(JhonError::Syntax { msg, .. }) => assert_eq!(
+                msg,
+                "integer literal out of range: -0x1_0000_0000_0000_0000"
+            ),
+            other => panic!("expected syntax error, got {:?}", other),
+        }
+
+        // In-range integers and floats are untouched by the policy.
+        for policy in [OverflowPolicy::Error, OverflowPolicy::Saturate] {
+            assert_eq!(n("18446744073709551615", policy).unwrap(), json!(u64::MAX));
+            assert_eq!(n("-9223372036854775808", policy).unwrap(), json!(i64::MIN));
+            assert_eq!(n("1e30", policy).unwrap(), json!(1e30));
+        }
     }
 
     #[test]
-    fn binary_literal() {
-        assert_eq!(parse("n=0b1010").unwrap(), json!({"n": 10}));
+    fn tagged_objects_deserialize_into_enums() {
+        use serde::Deserialize;
+
+        #[derive(Debug, Deserialize, PartialEq)]
+        #[serde(tag = "__tag__", rename_all = "lowercase")]
+        enum Mode {
+            Enabled { level: String },
+            Off,
+        }
+        #[derive(Debug, Deserialize, PartialEq)]
+        struct Flags {
+            mode: Mode,
+            fallback: Mode,
+            all: Vec<Mode>,
+        }
+
+        let opts = ParseOptions {
+            tagged_objects: true,
+            ..Default::default()
+        };
+        let text = "mode = @enabled { level = \"high\" }\nfallback = @off\nall = [@off, @enabled {level = \"low\"}]";
+        let value = parse_with_options(text, &opts).unwrap();
+        assert_eq!(
+            value["mode"],
+            json!({"__tag__": "enabled", "level": "high"})
+        );
+        assert_eq!(
+            value["mode"].as_object().unwrap().keys().next().unwrap(),
+            "__tag__"
+        );
+        let flags: Flags = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            flags,
+            Flags {
+                mode: Mode::Enabled {
+                    level: "high".to_string()
+                },
+                fallback: Mode::Off,
+                all: vec![
+                    Mode::Off,
+                    Mode::Enabled {
+                        level: "low".to_string()
+                    }
+                ],
+            }
+        );
+
+        assert!(parse(text).is_err());
+
+        // Only an object on the tag's own line attaches to it.
+        assert_eq!(
+            parse_with_options("a = [@off\n{x=1}]", &opts).unwrap(),
+            json!({"a": [{"__tag__": "off"}, {"x": 1}]})
+        );
+        assert_eq!(
+            parse_with_options("a = [@on /* c */ {x=1}]", &opts).unwrap(),
+            json!({"a": [{"__tag__": "on", "x": 1}]})
+        );
+
+        // `@` stays a tag when barewords are on too.
+        let both = ParseOptions {
+            bareword_values: true,
+            ..opts.clone()
+        };
+        assert_eq!(
+            parse_with_options("m = @off", &both).unwrap(),
+            json!({"m": {"__tag__": "off"}})
+        );
+        let bare = SerializeOptions {
+            bareword_strings: true,
+            ..Default::default()
+        };
+        assert_eq!(serialize_with(&json!({"m": "@off"}), &bare), r#"m="@off""#);
+
+        assert!(parse_with_options("m = @ {a = 1}", &opts).is_err());
+        assert!(parse_with_options("m = @x {__tag__ = \"y\"}", &opts).is_err());
     }
 
     #[test]
-    fn negative_hex_literal() {
-        assert_eq!(parse("n=-0xff").unwrap(), json!({"n": -255}));
+    fn semicolon_comments_are_opt_in() {
+        let opts = ParseOptions {
+            semicolon_comments: true,
+            ..Default::default()
+        };
+        let text = "; INI-style header\nname = \"a;b\" ; trailing\nraw = r\"x;y\";\nport = 80;note\nlist = [\n  1 ; one\n  'c;d'\n]";
+        assert_eq!(
+            parse_with_options(text, &opts).unwrap(),
+            json!({"name": "a;b", "raw": "x;y", "port": 80, "list": [1, "c;d"]})
+        );
+        assert!(parse(text).is_err());
+        assert_eq!(
+            parse_with_options("; only comments\n;", &opts).unwrap(),
+            json!(null)
+        );
+
+        let barewords = ParseOptions {
+            bareword_values: true,
+            ..opts
+        };
+        assert_eq!(
+            parse_with_options("path = /usr/local; install prefix", &barewords).unwrap(),
+            json!({"path": "/usr/local"})
+        );
+
+        // `bareword_strings` quotes `;` so such a reader gets it back whole.
+        let value = json!({"p": "a;b", "q": "/usr"});
+        let out = serialize_with(
+            &value,
+            &SerializeOptions {
+                bareword_strings: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(out, r#"p="a;b",q=/usr"#);
+        assert_eq!(parse_with_options(&out, &barewords).unwrap(), value);
+
+        // Keys are quoted when they start with `;`; inside, it is harmless.
+        let value = json!({";a": 1, "b;": {"c;d": 2}});
+        let out = serialize(&value);
+        assert_eq!(out, r#"";a"=1,b;={c;d=2}"#);
+        assert_eq!(parse_with_options(&out, &opts).unwrap(), value);
     }
 
     #[test]
-    fn positive_with_plus_prefix_is_error() {
-        assert!(parse("n=+5").is_err());
+    fn dotted_keys_to_nested_builds_objects() {
+        let opts = ParseOptions {
+            dotted_keys_to_nested: true,
+            ..Default::default()
+        };
+        let text = "server.host = \"localhost\"\nserver.port = 8080\nserver.tls = {cert = \"c\"}\nserver.tls.key = \"k\"\nlist = [{a.b = 1}]";
+        assert_eq!(
+            parse_with_options(text, &opts).unwrap(),
+            json!({
+                "server": {"host": "localhost", "port": 8080, "tls": {"cert": "c", "key": "k"}},
+                "list": [{"a": {"b": 1}}]
+            })
+        );
+        assert_eq!(
+            parse(text).unwrap()["server.host"],
+            json!("localhost"),
+            "off by default"
+        );
+
+        for (text, key) in [
+            ("server = 1\nserver.port = 80", "server.port"),
+            ("server.port = 80\nserver.port.x = 1", "server.port.x"),
+            ("a.b = 1\na = {b = 2}", "a"),
+            ("a..b = 1", "a..b"),
+        ] {
+            match parse_with_options(text, &opts) {
+                Err(JhonError::InvalidValue { key: k, .. }) => assert_eq!(k, key, "{}", text),
+                other => panic!("{}: expected InvalidValue, got {:?}", text, other),
+            }
+        }
     }
 
     #[test]
-    fn uppercase_hex_prefix_is_error() {
-        assert!(parse("n=0Xff").is_err());
+    fn empty_document_behavior_is_configurable() {
+        let with = |empty_document| ParseOptions {
+            empty_document,
+            ..Default::default()
+        };
+        for text in ["", "  \n\t", "// nothing here\n/* at all */\n"] {
+            assert_eq!(parse(text).unwrap(), Value::Null);
+            assert_eq!(
+                parse_with_options(text, &with(EmptyBehavior::Null)).unwrap(),
+                Value::Null
+            );
+            assert_eq!(
+                parse_with_options(text, &with(EmptyBehavior::EmptyObject)).unwrap(),
+                json!({})
+            );
+            let err = parse_with_options(text, &with(EmptyBehavior::Error)).unwrap_err();
+            assert!(matches!(err, JhonError::Eof { .. }), "{text:?}: {err}");
+        }
+        assert_eq!(
+            parse_with_options("\n  ", &with(EmptyBehavior::Error))
+                .unwrap_err()
+                .to_string(),
+            "unexpected end of input at 2:3: empty document"
+        );
+        assert_eq!(
+            parse_with_options("a = 1", &with(EmptyBehavior::Error)).unwrap(),
+            json!({"a": 1})
+        );
     }
 
     #[test]
-    fn uppercase_octal_prefix_is_error() {
-        assert!(parse("n=0O77").is_err());
+    fn reject_top_level_values_explains_missing_key() {
+        let opts = ParseOptions {
+            reject_top_level_values: true,
+            ..Default::default()
+        };
+        assert_eq!(parse("\"hello\"").unwrap(), json!(["hello"]));
+
+        for (text, line, col) in [("\"hello\"", 1, 1), ("\n  42", 2, 3), ("{a = 1}", 1, 1)] {
+            let err = parse_with_options(text, &opts).unwrap_err();
+            assert_eq!(
+                err,
+                JhonError::Syntax {
+                    line,
+                    col,
+                    msg: "top-level value found; did you mean to add a key, as in `key = value`?"
+                        .to_string(),
+                },
+                "{text:?}"
+            );
+        }
+        assert_eq!(
+            parse_with_options("greeting = \"hello\"", &opts).unwrap()["greeting"],
+            "hello"
+        );
+        assert_eq!(parse_with_options("", &opts).unwrap(), Value::Null);
     }
 
     #[test]
-    fn uppercase_binary_prefix_is_error() {
-        assert!(parse("n=0B10").is_err());
+    fn parse_reader_handles_invalid_utf8() {
+        let strict = ParseOptions::default();
+        let lossy = ParseOptions {
+            lossy_utf8: true,
+            ..Default::default()
+        };
+        let good = "name = \"café\"\nport = 80";
+        assert_eq!(
+            parse_reader(good.as_bytes(), &strict).unwrap(),
+            parse(good).unwrap()
+        );
+
+        let bad: &[u8] = b"name = \"ok\"\ntag = \"\xFF\xFEx\", n = 1";
+        assert_eq!(
+            parse_reader(bad, &strict).unwrap_err(),
+            JhonError::Syntax {
+                line: 2,
+                col: 8,
+                msg: "invalid UTF-8 at byte 19".to_string(),
+            }
+        );
+        assert_eq!(
+            parse_reader(bad, &lossy).unwrap(),
+            json!({"name": "ok", "tag": "\u{FFFD}\u{FFFD}x", "n": 1})
+        );
+        // A truncated multi-byte sequence at the end, e.g. a cut-off file.
+        let cut: &[u8] = b"a = 1\n\xE2\x82";
+        assert!(parse_reader(cut, &strict).is_err());
+        assert!(
+            parse_reader(cut, &lossy).is_err(),
+            "U+FFFD alone is not a valid item"
+        );
     }
 
     #[test]
-    fn number_type_suffix_is_error() {
-        assert!(parse("n=5u8").is_err());
-    }
+    fn heredoc_strings_capture_lines_verbatim() {
+        let opts = ParseOptions {
+            heredoc_strings: true,
+            ..Default::default()
+        };
+        assert!(parse("script = <<END\necho hi\nEND").is_err());
+
+        let text = "script = <<END\necho \"a\\tb\" // not a comment\nmake all\nEND\nname = \"x\"";
+        assert_eq!(
+            parse_with_options(text, &opts).unwrap(),
+            json!({"script": "echo \"a\\tb\" // not a comment\nmake all", "name": "x"})
+        );
+
+        // The marker ends the heredoc only as a whole line of its own.
+        let text = "t = <<EOF\nsee EOF here\n EOF\nEOFX\n\nEOF\nn = 1";
+        assert_eq!(
+            parse_with_options(text, &opts).unwrap(),
+            json!({"t": "see EOF here\n EOF\nEOFX\n", "n": 1})
+        );
+
+        assert_eq!(
+            parse_with_options("a = [<<X\r\nline\r\nX\r\n<<X\nX\n]", &opts).unwrap(),
+            json!({"a": ["line", ""]})
+        );
 
-    #[test]
-    fn leading_underscore_is_error() {
-        assert!(parse("n=_5").is_err());
-    }
+        assert!(parse_with_options("t = <<END\nno terminator", &opts).is_err());
+        assert!(parse_with_options("t = <<END trailing\nEND", &opts).is_err());
+        assert!(parse_with_options("t = <<\nx\n", &opts).is_err());
+        assert!(parse_with_options("t = <END\nEND", &opts).is_err());
 
-    #[test]
-    fn trailing_underscore_is_error() {
-        assert!(parse("n=5_").is_err());
+        // With barewords also on, `<<` still opens a heredoc.
+        let both = ParseOptions {
+            bareword_values: true,
+            ..opts
+        };
+        assert_eq!(
+            parse_with_options("a = <<END\nhi\nEND\nb = <x", &both).unwrap(),
+            json!({"a": "hi", "b": "<x"})
+        );
+
+        // The body is one string to the `reject_*` scan too.
+        let strict = ParseOptions {
+            reject_nul: true,
+            reject_comments: true,
+            reject_single_quotes: true,
+            ..opts
+        };
+        assert_eq!(
+            parse_with_options("msg = <<EOT\nit's here // and /* here\nEOT", &strict).unwrap(),
+            json!({"msg": "it's here // and /* here"})
+        );
+        assert!(parse_with_options("msg = <<EOT\nhi\nEOT // note", &strict).is_err());
     }
 
     #[test]
-    fn adjacent_underscores_are_error() {
-        assert!(parse("n=5__5").is_err());
-    }
+    fn reject_nul_flags_nul_outside_strings() {
+        let opts = ParseOptions {
+            reject_nul: true,
+            ..Default::default()
+        };
+        // Spec default: NUL is just another bare-key character.
+        assert_eq!(parse("a\0b=1").unwrap(), json!({"a\0b": 1}));
 
-    // =========================================================================
-    // §5 — Objects
-    // =========================================================================
+        let err = parse_with_options("x=1\na\0b=1", &opts).unwrap_err();
+        assert_eq!(
+            err,
+            JhonError::Syntax {
+                line: 2,
+                col: 2,
+                msg: "unexpected NUL byte".to_string()
+            }
+        );
+        assert_eq!(err.to_string(), "parse error at 2:2: unexpected NUL byte");
+        assert!(parse_with_options("a=\0", &opts).is_err());
+        assert!(parse_with_options("a=1 // \0", &opts).is_err());
 
-    #[test]
-    fn basic_key_value_pairs() {
+        // Raw strings keep NUL verbatim; regular strings reject it anyway.
         assert_eq!(
-            parse(r#"a="hello", b=123.45"#).unwrap(),
-            json!({"a": "hello", "b": 123.45})
+            parse_with_options("a=r\"\0\"", &opts).unwrap(),
+            json!({"a": "\0"})
+        );
+        assert!(parse_with_options("a=\"\0\"", &opts).is_err());
+        assert_eq!(
+            parse_with_options("a=\"\\u0000\"", &opts).unwrap(),
+            json!({"a": "\0"})
         );
     }
 
     #[test]
-    fn nested_object() {
+    fn json_strict_accepts_json() {
+        let strict = ParseOptions::json_strict();
+        let text = r#"{
+            "name": "app",
+            "ports": [80, 443],
+            "tls": {"enabled": true, "cert": null},
+            "ratio": -1.5e3,
+            "empty": {}
+        }"#;
+        let mut expected: Value = serde_json::from_str(text).unwrap();
+        let mut value = parse_with_options(text, &strict).unwrap();
+        // By value: under the `decimal` feature, number equality compares
+        // text, and `-1.5e3` is stored as `-1500.0`.
         assert_eq!(
-            parse(r#"server={host="localhost", port=8080}"#).unwrap(),
-            json!({"server": {"host": "localhost", "port": 8080}})
+            value["ratio"].take().as_f64(),
+            expected["ratio"].take().as_f64()
+        );
+        assert_eq!(value, expected);
+        assert_eq!(
+            parse_with_options("[1, 2]", &strict).unwrap(),
+            json!([1, 2])
         );
+        assert_eq!(parse_with_options(" 42 ", &strict).unwrap(), json!(42));
     }
 
     #[test]
-    fn whitespace_around_equals_is_insignificant() {
-        let a = parse(r#"name = "x""#).unwrap();
-        let b = parse(r#"name="x""#).unwrap();
-        let c = parse(r#"name ="x""#).unwrap();
-        let d = parse(r#"name= "x""#).unwrap();
-        assert_eq!(a, b);
-        assert_eq!(b, c);
-        assert_eq!(c, d);
+    fn json_strict_rejects_jhon_only_features() {
+        let strict = ParseOptions::json_strict();
+        for (text, needle) in [
+            (r#"{'a': 1}"#, "single-quoted"),
+            (r#"{"a": 'x'}"#, "single-quoted"),
+            (r#"{"a": 1,}"#, "trailing comma"),
+            ("[1, 2, ]", "trailing comma"),
+            ("[1, 2] // c", "comments"),
+            (r##"{"a": r"x"}"##, "raw strings"),
+            ("{\"a\": 1\n\"b\": 2}", "separated by a comma"),
+            (r#"{"a" = 1}"#, "Expected ':'"),
+            (r#"{a: 1}"#, "must be quoted"),
+            ("[1] [2]", "end of input"),
+        ] {
+            let err = parse_with_options(text, &strict).unwrap_err();
+            assert!(err.to_string().contains(needle), "{text:?}: {err}");
+        }
+        assert!(matches!(
+            parse_with_options("[1,\n 2,\n]", &strict).unwrap_err(),
+            JhonError::Syntax {
+                line: 2,
+                col: 3,
+                ..
+            }
+        ));
     }
 
     #[test]
-    fn duplicate_keys_at_top_level_are_error() {
-        assert!(parse("a=1, a=2").is_err());
+    fn colon_separator_without_strict_mode() {
+        let opts = ParseOptions {
+            key_value_separator: KeyValueSeparator::Either,
+            ..Default::default()
+        };
+        assert_eq!(
+            parse_with_options("a: 1\nb = {c:2}", &opts).unwrap(),
+            json!({"a": 1, "b": {"c": 2}})
+        );
     }
 
+    // =========================================================================
+    // Value helpers
+    // =========================================================================
+
     #[test]
-    fn duplicate_keys_in_nested_object_are_error() {
-        assert!(parse(r#"outer={a=1, a=2}"#).is_err());
+    fn loose_equality_ignores_int_float_representation() {
+        assert!(values_equal_loose(&json!(30), &json!(30.0)));
+        assert!(values_equal_loose(
+            &json!({"age": 30, "tags": [1, 2.0]}),
+            &json!({"tags": [1.0, 2], "age": 30.0})
+        ));
     }
 
     #[test]
-    fn key_order_is_preserved() {
-        let result = parse(r#"z=1, a=2, m=3"#).unwrap();
-        let keys: Vec<&String> = result.as_object().unwrap().keys().collect();
-        assert_eq!(keys, vec!["z", "a", "m"]);
+    fn loose_equality_still_detects_differences() {
+        assert!(!values_equal_loose(&json!(30), &json!(31)));
+        assert!(!values_equal_loose(
+            &json!({"a": 1}),
+            &json!({"a": 1, "b": 2})
+        ));
+        assert!(!values_equal_loose(&json!(u64::MAX), &json!(-1)));
+        assert!(!values_equal_loose(&json!("30"), &json!(30)));
     }
 
-    // =========================================================================
-    // §5.3 — Separators
-    // =========================================================================
-
     #[test]
-    fn same_line_comma_separated() {
-        assert_eq!(
-            parse("a=1, b=2, c=3").unwrap(),
-            json!({"a": 1, "b": 2, "c": 3})
-        );
+    fn parse_into_accumulates_fragments() {
+        let mut config = parse(COMPLEX_EXAMPLE).unwrap();
+        parse_into(&mut config, r#"database = { port = 6543, pool = 10 }"#).unwrap();
+        parse_into(
+            &mut config,
+            r#"debug = true, database = { host = "db.internal" }, features = ["auth"]"#,
+        )
+        .unwrap();
+
+        assert_eq!(config["debug"], true);
+        assert_eq!(config["database"]["host"], "db.internal");
+        assert_eq!(config["database"]["port"], 6543);
+        assert_eq!(config["database"]["pool"], 10);
+        assert_eq!(config["database"]["credentials"][1]["user"], "admin");
+        assert_eq!(config["features"], json!(["auth"]));
+        assert_eq!(config["app_name"], "ocean-note");
+
+        let before = config.clone();
+        assert!(parse_into(&mut config, "debug = ").is_err());
+        assert!(parse_into(&mut config, "[1, 2]").is_err());
+        parse_into(&mut config, "").unwrap();
+        assert_eq!(config, before);
     }
 
     #[test]
-    fn newline_separated_multiline() {
+    fn merge_tracked_records_the_layer_of_each_key() {
+        let (config, origin) = merge_tracked(vec![
+            ("base.jhon".to_string(), parse(COMPLEX_EXAMPLE).unwrap()),
+            (
+                "override.jhon".to_string(),
+                parse("database = { port = 5433 }, limits = 0, features = [\"auth\"]").unwrap(),
+            ),
+        ]);
+        assert_eq!(config["database"]["port"], 5433);
+        assert_eq!(origin["database.port"], "override.jhon");
+        assert_eq!(origin["database.host"], "base.jhon");
+        assert_eq!(origin["database.credentials"], "base.jhon");
+        assert_eq!(origin["features"], "override.jhon");
+        // A replaced subtree takes its descendants' provenance with it.
+        assert_eq!(origin["limits"], "override.jhon");
+        assert!(!origin.contains_key("limits.max_files_per_user"));
+        assert_eq!(origin.len(), 8);
+
+        let (_, origin) = merge_tracked(vec![
+            ("a".to_string(), json!({"x": 1})),
+            ("b".to_string(), json!({"x": {"y": 2, "z": {}}})),
+        ]);
         assert_eq!(
-            parse("a=1\nb=2\nc=3").unwrap(),
-            json!({"a": 1, "b": 2, "c": 3})
+            origin,
+            BTreeMap::from([
+                ("x.y".to_string(), "b".to_string()),
+                ("x.z".to_string(), "b".to_string()),
+            ])
         );
     }
 
     #[test]
-    fn mixed_comma_and_newline_separators() {
+    fn entry_fragments_append_to_a_document() {
+        let mut text = String::new();
+        for (key, value) in [
+            ("name", json!("say \"hi\"\n")),
+            ("", json!(null)),
+            ("true", json!({})),
+            ("list", json!([1, {"a b": [true]}])),
+        ] {
+            text.push_str(&entry(key, &value));
+            text.push('\n');
+        }
         assert_eq!(
-            parse("a=1,\nb=2,\nc=3").unwrap(),
-            json!({"a": 1, "b": 2, "c": 3})
+            parse(&text).unwrap(),
+            json!({
+                "name": "say \"hi\"\n",
+                "": null,
+                "true": {},
+                "list": [1, {"a b": [true]}]
+            })
         );
     }
 
     #[test]
-    fn trailing_comma_at_top_level() {
-        assert_eq!(parse("a=1, b=2,").unwrap(), json!({"a": 1, "b": 2}));
+    fn serialize_stable_sorts_keys_but_keeps_array_order() {
+        let value = json!({
+            "zeta": [3, 1, 2],
+            "alpha": {"b": 1, "a": [{"y": 1, "x": 2}, {"w": 0}]},
+            "Beta": null,
+            "émoji": true,
+            "10": 1,
+            "9": 2
+        });
+        assert_eq!(
+            serialize_stable(&value),
+            r#""10"=1,"9"=2,Beta=null,alpha={a=[{x=2,y=1},{w=0}],b=1},zeta=[3,1,2],émoji=true"#
+        );
+        // Insertion order does not matter; the original is untouched.
+        let reordered = parse(&serialize_stable(&value)).unwrap();
+        assert_eq!(serialize_stable(&reordered), serialize_stable(&value));
+        assert!(serialize(&value).starts_with("zeta="));
+        assert_eq!(
+            serialize_stable(&json!([{"b": 1, "a": 2}, 0])),
+            "{a=2,b=1},0"
+        );
     }
 
     #[test]
-    fn trailing_comma_in_braced_object() {
-        // Per SPEC.md §2: top-level `{...}` is a single-element array.
-        assert_eq!(parse("{a=1, b=2,}").unwrap(), json!([{"a": 1, "b": 2}]));
+    fn normalize_makes_reordered_configs_with_whole_floats_equal() {
+        let mut a =
+            parse("name = \"app\"\nserver = {port = 8080, timeout = 30.0}\nratios = [1.0, 0.5, 2]")
+                .unwrap();
+        let mut b =
+            parse("server = {timeout = 30, port = 8080.0}\nratios = [1, 0.5, 2.0]\nname = \"app\"")
+                .unwrap();
+        assert_ne!(a, b);
+        normalize(&mut a);
+        normalize(&mut b);
+        assert_eq!(a, b);
+        assert_eq!(a["server"]["timeout"], json!(30));
+        assert!(a["server"]["timeout"].is_i64());
+        assert_eq!(a["ratios"], json!([1, 0.5, 2]));
+        let keys: Vec<&String> = a.as_object().unwrap().keys().collect();
+        assert_eq!(keys, ["name", "ratios", "server"]);
     }
 
     #[test]
-    fn trailing_comma_in_array() {
-        assert_eq!(parse("k=[1, 2, 3,]").unwrap()["k"], json!([1, 2, 3]));
+    fn content_hash_ignores_key_order_but_not_values() {
+        let a = parse("name = \"app\"\ndb = {host = \"h\", port = 5432}").unwrap();
+        let b = parse("db = {port = 5432.0, host = \"h\"}\nname = \"app\"").unwrap();
+        let c = parse("name = \"app\"\ndb = {host = \"h\", port = 5433}").unwrap();
+        assert_eq!(content_hash(&a), content_hash(&b));
+        assert_ne!(content_hash(&a), content_hash(&c));
+        assert_eq!(content_hash(&json!(null)), 0xcbf2_9ce4_8422_2325);
     }
 
     #[test]
-    fn whitespace_around_comma_is_insignificant() {
-        let a = parse("a=1,b=2").unwrap();
-        let b = parse("a=1, b=2").unwrap();
-        let c = parse("a=1 ,b=2").unwrap();
-        let d = parse("a=1 , b=2").unwrap();
+    fn to_sorted_json_is_byte_stable_across_key_order() {
+        let a = to_sorted_json("b = [3, 1], a = { y = \"\\u00e9\", x = null }, c = 1.5 // note\n")
+            .unwrap();
+        let b = to_sorted_json("a = {\n  x = null\n  y = 'é'\n}\nc = 1.5\nb = [3, 1]").unwrap();
         assert_eq!(a, b);
-        assert_eq!(b, c);
-        assert_eq!(c, d);
+        assert_eq!(a, r#"{"a":{"x":null,"y":"é"},"b":[3,1],"c":1.5}"#);
+        assert_eq!(to_sorted_json("").unwrap(), "null");
+        assert!(to_sorted_json("a = ").is_err());
     }
 
     #[test]
-    fn same_line_space_only_separator_is_error() {
-        assert!(parse("a=1 b=2").is_err());
+    fn merge_with_by_key_upserts_credentials() {
+        let mut config = parse(COMPLEX_EXAMPLE).unwrap();
+        let overlay = parse(
+            r#"database = { credentials = [
+                { user = "admin", password = "rotated" }
+                { user = "ci", password = "ci_pass" }
+                { password = "orphan" }
+                "not a record"
+            ] }"#,
+        )
+        .unwrap();
+        merge_with(
+            &mut config,
+            overlay.clone(),
+            &ArrayMerge::ByKey("user".to_string()),
+        );
+        assert_eq!(
+            config["database"]["credentials"],
+            json!([
+                {"user": "root", "password": "hunter2"},
+                {"user": "admin", "password": "rotated"},
+                {"user": "ci", "password": "ci_pass"},
+                {"password": "orphan"},
+                "not a record"
+            ])
+        );
+        assert_eq!(config["database"]["host"], "localhost");
+
+        // `Replace` behaves exactly like `merge`.
+        let mut replaced = parse(COMPLEX_EXAMPLE).unwrap();
+        let mut merged = replaced.clone();
+        merge_with(&mut replaced, overlay.clone(), &ArrayMerge::Replace);
+        merge(&mut merged, overlay);
+        assert_eq!(replaced, merged);
+
+        // Matched elements merge deeply, nested arrays by the same key.
+        let mut base = json!([{"id": 1, "tags": [{"id": "a", "on": false}]}]);
+        merge_with(
+            &mut base,
+            json!([{"id": 1, "tags": [{"id": "a", "on": true}, {"id": "b"}]}]),
+            &ArrayMerge::ByKey("id".to_string()),
+        );
+        assert_eq!(
+            base,
+            json!([{"id": 1, "tags": [{"id": "a", "on": true}, {"id": "b"}]}])
+        );
     }
 
     #[test]
-    fn same_line_tab_only_separator_is_error() {
-        assert!(parse("a=1\tb=2").is_err());
+    fn merge_reporting_surfaces_differing_scalars() {
+        let (config, conflicts) = merge_reporting(
+            parse(COMPLEX_EXAMPLE).unwrap(),
+            parse("database = { host = \"localhost\", port = 5433 }, features = [\"auth\"]")
+                .unwrap(),
+        );
+        assert_eq!(config["database"]["port"], 5433);
+        assert_eq!(
+            conflicts,
+            vec![Conflict {
+                path: "database.port".to_string(),
+                base: json!(5432),
+                overlay: json!(5433),
+            }]
+        );
+
+        // Container replacements and new keys are not conflicts.
+        let (_, conflicts) = merge_reporting(
+            json!({"a": 1, "b": [1]}),
+            json!({"a": {}, "b": [2], "c": 3}),
+        );
+        assert!(conflicts.is_empty());
+        let (_, conflicts) = merge_reporting(json!({"a": null}), json!({"a": false}));
+        assert_eq!(conflicts.len(), 1);
     }
 
     #[test]
-    fn array_same_line_no_commas_is_error() {
-        assert!(parse("k=[1 2 3]").is_err());
+    fn builder_constructs_nested_config() {
+        let config = JhonBuilder::new()
+            .key("app_name", "ocean-note")
+            .key("debug", false)
+            .object("database", |b| {
+                b.key("host", "localhost")
+                    .key("port", 5432)
+                    .object("credentials", |b| {
+                        b.key("user", "admin").key("password", None::<&str>)
+                    })
+            })
+            .array("features", ["auth", "sync"])
+            .key("ratio", 0.5)
+            .key("debug", true)
+            .build();
+        assert_eq!(
+            serialize_pretty(&config, "  "),
+            r#"app_name = "ocean-note"
+debug = true
+database = {
+  host = "localhost"
+  port = 5432
+  credentials = {
+    user = "admin"
+    password = null
+  }
+}
+features = [
+  "auth"
+  "sync"
+]
+ratio = 0.5"#
+        );
+        assert_eq!(parse(&serialize(&config)).unwrap(), config);
+        assert_eq!(JhonBuilder::new().build(), json!({}));
     }
 
-    // =========================================================================
-    // §6 — Arrays
-    // =========================================================================
-
     #[test]
-    fn empty_array() {
-        assert_eq!(parse("k=[]").unwrap()["k"], json!([]));
+    fn parse_profile_merges_common_with_selected_section() {
+        let text = r#"
+common = {
+  app_name = "ocean-note"
+  database = { host = "localhost", port = 5432 }
+}
+dev = {
+  debug = true
+  database = { host = "dev.local" }
+}
+prod = {
+  database = { host = "db.internal", pool = 20 }
+  replicas = 3
+}
+"#;
+        assert_eq!(
+            parse_profile(text, "prod").unwrap(),
+            json!({
+                "app_name": "ocean-note",
+                "database": {"host": "db.internal", "port": 5432, "pool": 20},
+                "replicas": 3
+            })
+        );
+        assert_eq!(parse_profile(text, "dev").unwrap()["debug"], true);
+        assert!(parse_profile(text, "prod").unwrap().get("debug").is_none());
+
+        assert!(matches!(
+            parse_profile(text, "staging"),
+            Err(JhonError::InvalidValue { key, .. }) if key == "staging"
+        ));
+        assert!(matches!(
+            parse_profile("common = 1, prod = {}", "prod"),
+            Err(JhonError::WrongType { key, .. }) if key == "common"
+        ));
+        assert_eq!(
+            parse_profile("prod = {a = 1}", "prod").unwrap(),
+            json!({"a": 1})
+        );
     }
 
     #[test]
-    fn array_of_strings() {
+    fn to_env_flattens_nested_config() {
+        let config = parse(
+            r#"
+server = {
+  database = { port = 5432, "log-level" = "warn", ratio = 0.5 }
+  debug = false
+}
+tags = ["web", "api"]
+token = null
+empty = {}
+"#,
+        )
+        .unwrap();
+        let env = to_env(&config, "app");
+        let expected: BTreeMap<String, String> = [
+            ("APP_SERVER_DATABASE_PORT", "5432"),
+            ("APP_SERVER_DATABASE_LOG_LEVEL", "warn"),
+            ("APP_SERVER_DATABASE_RATIO", "0.5"),
+            ("APP_SERVER_DEBUG", "false"),
+            ("APP_TAGS_0", "web"),
+            ("APP_TAGS_1", "api"),
+            ("APP_TOKEN", ""),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        assert_eq!(env, expected);
+
         assert_eq!(
-            parse(r#"k=["a", "b", "c"]"#).unwrap()["k"],
-            json!(["a", "b", "c"])
+            to_env(&json!({"a": {"b": 1}}), ""),
+            BTreeMap::from([("A_B".to_string(), "1".to_string())])
         );
     }
 
     #[test]
-    fn array_mixed_types() {
+    fn serialize_env_style_writes_flat_scalars() {
+        let config = parse(
+            r#"app_name = "ocean-note", log-level = "debug", port = 8080, ratio = 0.5
+debug = false, proxy = null, url = "https://example.com/a?b=1"
+motd = "Hello, $USER\n\"welcome\"", empty = """#,
+        )
+        .unwrap();
         assert_eq!(
-            parse(r#"k=[1, "two", true, null]"#).unwrap()["k"],
-            json!([1, "two", true, null])
+            serialize_env_style(&config).unwrap(),
+            r#"APP_NAME=ocean-note
+LOG_LEVEL=debug
+PORT=8080
+RATIO=0.5
+DEBUG=false
+PROXY=
+URL="https://example.com/a?b=1"
+MOTD="Hello, \$USER\n\"welcome\""
+EMPTY=""
+"#
         );
+        assert_eq!(serialize_env_style(&Value::Null).unwrap(), "");
+
+        let err = serialize_env_style(&parse("name = \"x\", server = { port = 80 }").unwrap());
+        assert!(matches!(err, Err(JhonError::WrongType { key, .. }) if key == "server"));
+        let err = serialize_env_style(&parse("tags = []").unwrap());
+        assert!(matches!(err, Err(JhonError::WrongType { key, .. }) if key == "tags"));
+        assert!(serialize_env_style(&json!([1, 2])).is_err());
     }
 
     #[test]
-    fn nested_arrays() {
+    fn dotenv_round_trips_flat_and_nested_configs() {
+        let flat = json!({
+            "HOST": "localhost",
+            "MOTD": "Hello, $USER\n\"welcome\" # not a comment",
+            "PATH_LIST": "/bin:/usr/bin",
+            "EMPTY": ""
+        });
+        let text = to_dotenv(&flat);
         assert_eq!(
-            parse("k=[[1, 2], [3, 4]]").unwrap()["k"],
-            json!([[1, 2], [3, 4]])
+            text,
+            "EMPTY=\nHOST=localhost\nMOTD=\"Hello, \\$USER\\n\\\"welcome\\\" # not a comment\"\nPATH_LIST=/bin:/usr/bin\n"
         );
+        assert_eq!(from_dotenv(&text).unwrap(), flat);
+
+        // Nested values come back flattened, as to_env names them.
+        let nested = parse(COMPLEX_EXAMPLE).unwrap();
+        let env = from_dotenv(&to_dotenv(&nested)).unwrap();
+        let expected: Map<String, Value> = to_env(&nested, "")
+            .into_iter()
+            .map(|(k, v)| (k, Value::String(v)))
+            .collect();
+        assert_eq!(env, Value::Object(expected));
+        assert_eq!(env["DATABASE_CREDENTIALS_1_USER"], "admin");
+        assert_eq!(env["LIMITS_MAX_FILE_SIZE"], "1048576");
     }
 
     #[test]
-    fn multiline_array_newline_separated() {
-        assert_eq!(parse("k=[\n1\n2\n3\n]").unwrap()["k"], json!([1, 2, 3]));
+    fn from_dotenv_reads_common_syntax() {
+        let text = "# comment\n\nexport A=1\nB = two words # note\nD='$HOME \\n'\nE=\"x\" # tail\nA=3\nF=a#b\n";
+        assert_eq!(
+            from_dotenv(text).unwrap(),
+            json!({"A": "3", "B": "two words", "D": "$HOME \\n", "E": "x", "F": "a#b"})
+        );
+        for (text, line) in [
+            ("A=1\nnot a pair", 2),
+            ("X=\"open", 1),
+            ("=1", 1),
+            ("A B=1", 1),
+            ("A=1\nC='it''s'", 2),
+        ] {
+            match from_dotenv(text) {
+                Err(JhonError::Syntax { line: l, .. }) => assert_eq!(l, line, "{}", text),
+                other => panic!("{}: expected syntax error, got {:?}", text, other),
+            }
+        }
     }
 
     #[test]
-    fn unbalanced_array_is_error() {
-        assert!(parse("k=[1, 2").is_err());
-    }
+    fn semantically_equal_ignores_formatting() {
+        let reordered = r#"
+features = ['auth', 'logging', 'caching']
+limits = { max_files_per_user = 100, max_file_size = 1_048_576 }
+database = {
+  credentials = [
+    { password = "hunter2", user = "root" },
+    { password = "s3cret", user = "admin" },
+  ]
+  port = 0x1538
+  host = r"localhost"
+}
+debug = false, version = "2.0.0", app_name = "ocean-note"
+"#;
+        assert!(semantically_equal(COMPLEX_EXAMPLE, reordered).unwrap());
+        assert!(
+            semantically_equal(
+                COMPLEX_EXAMPLE,
+                &serialize(&parse(COMPLEX_EXAMPLE).unwrap())
+            )
+            .unwrap()
+        );
 
-    // =========================================================================
-    // §7 — Serialization
-    // =========================================================================
+        // Array order is data, not formatting.
+        let swapped = COMPLEX_EXAMPLE.replace(r#"["auth", "logging""#, r#"["logging", "auth""#);
+        assert!(!semantically_equal(COMPLEX_EXAMPLE, &swapped).unwrap());
+        let changed = COMPLEX_EXAMPLE.replace("5432", "5433");
+        assert!(!semantically_equal(COMPLEX_EXAMPLE, &changed).unwrap());
+        assert!(!semantically_equal("a=1", "a=1, b=null").unwrap());
 
-    #[test]
-    fn compact_serialize_no_spaces_around_equals() {
-        let value = json!({"name": "John", "age": 30});
-        assert_eq!(serialize(&value), r#"name="John",age=30"#);
+        assert!(!semantically_equal("n=[1, 2.5]", "n=[1.0, 2.5]").unwrap());
+        assert!(semantically_equal_loose("n=[1, 2.5]", "n=[1.0, 2.5]").unwrap());
+        assert!(semantically_equal("a=1", "a=").is_err());
     }
 
     #[test]
-    fn compact_serialize_nested_object() {
-        let value = json!({"server": {"host": "localhost", "port": 8080}});
-        assert_eq!(serialize(&value), r#"server={host="localhost",port=8080}"#);
+    fn get_path_navigates_objects_and_arrays() {
+        let config = parse(COMPLEX_EXAMPLE).unwrap();
+        assert_eq!(get_path(&config, "database.port").unwrap(), 5432);
+        assert_eq!(
+            get_path(&config, "database.credentials[1].user").unwrap(),
+            "admin"
+        );
+        assert_eq!(get_path(&config, "features.2").unwrap(), "caching");
+        assert_eq!(get_path(&config, "").unwrap(), &config);
+        assert!(get_path(&config, "database.missing").is_none());
+        assert!(get_path(&config, "features[9]").is_none());
+        assert!(get_path(&config, "version.major").is_none());
     }
 
     #[test]
-    fn compact_serialize_top_level_array() {
-        // Top-level arrays serialize bare (no surrounding []).
-        let value = json!([{"a": 1}, {"b": 2}]);
-        assert_eq!(serialize(&value), r#"{a=1},{b=2}"#);
-    }
+    fn sort_objects_by_orders_array_elements_by_field() {
+        let mut config = parse(COMPLEX_EXAMPLE).unwrap();
+        sort_objects_by(&mut config, "database.credentials", "user").unwrap();
+        let users: Vec<_> = config["database"]["credentials"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["user"].as_str().unwrap())
+            .collect();
+        assert_eq!(users, ["admin", "root"]);
+
+        let mut mixed = json!([{"k": "b"}, {}, {"k": 10}, {"k": "a"}, {"k": 2}]);
+        sort_objects_by(&mut mixed, "", "k").unwrap();
+        assert_eq!(
+            mixed,
+            json!([{"k": 2}, {"k": 10}, {"k": "a"}, {"k": "b"}, {}])
+        );
 
-    #[test]
-    fn compact_serialize_has_no_trailing_comma() {
-        let value = json!({"a": 1, "b": 2});
-        let s = serialize(&value);
-        assert!(!s.ends_with(','));
-    }
+        let mut odd = json!([
+            {"k": true}, {"k": 1.5}, 7, {"k": null}, {"k": "x"},
+            {"k": -3}, {"k": [1]}, {"k": u64::MAX}, {"k": -0.5}
+        ]);
+        sort_objects_by(&mut odd, "", "k").unwrap();
+        assert_eq!(
+            odd,
+            json!([
+                {"k": -3}, {"k": -0.5}, {"k": 1.5}, {"k": u64::MAX}, {"k": "x"},
+                {"k": true}, 7, {"k": null}, {"k": [1]}
+            ])
+        );
 
-    #[test]
-    fn pretty_serialize_spaces_around_equals_no_trailing_commas() {
-        let value = json!({"name": "John", "age": 30});
-        assert_eq!(serialize_pretty(&value, "  "), "name = \"John\"\nage = 30");
+        assert!(matches!(
+            sort_objects_by(&mut config, "database.missing", "user"),
+            Err(JhonError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            sort_objects_by(&mut config, "database.port", "user"),
+            Err(JhonError::WrongType { .. })
+        ));
     }
 
     #[test]
-    fn pretty_serialize_nested_object() {
-        let value = json!({"server": {"host": "localhost", "port": 8080}});
+    fn serialize_path_pretty_dumps_only_the_sub_value() {
+        let config = parse(COMPLEX_EXAMPLE).unwrap();
         assert_eq!(
-            serialize_pretty(&value, "  "),
-            "server = {\n  host = \"localhost\"\n  port = 8080\n}"
+            serialize_path_pretty(&config, "database.credentials", "  ").unwrap(),
+            "{\n  user = \"root\"\n  password = \"hunter2\"\n}\n{\n  user = \"admin\"\n  password = \"s3cret\"\n}"
         );
+        assert!(serialize_path_pretty(&config, "database.nope", "  ").is_none());
     }
 
     #[test]
-    fn pretty_serialize_deeply_nested() {
-        // Mixed nesting stress test: arrays of objects containing arrays,
-        // some with nested objects; deep object chains; arrays of arrays
-        // containing objects. Verifies byte-level output across impls.
-        let value = json!({
-            "a1": [
-                {"b1": ["c1"]},
-                {"b1": ["c1", {"d1": 4}]},
-                {"b1": ["c1"]},
-                {"b1": ["c1"]},
-                {"b1": ["c1"]},
-                {"b1": ["c1"]}
-            ],
-            "a2": {
-                "b2": {
-                    "c2": {"d2": "hahaha"},
-                    "c3": {"d3": "hohohoh"}
-                }
-            },
-            "a3": [
-                ["b4", "b5", "b6", {"c4": ["d1", "d3"]}]
-            ]
-        });
-        let expected = r#"a1 = [
-	{ b1 = [ "c1" ] }
-	{ b1 = [ "c1", { d1 = 4 } ] }
-	{ b1 = [ "c1" ] }
-	{ b1 = [ "c1" ] }
-	{ b1 = [ "c1" ] }
-	{ b1 = [ "c1" ] }
-]
-a2 = {
-	b2 = {
-		c2 = { d2 = "hahaha" }
-		c3 = { d3 = "hohohoh" }
-	}
-}
-a3 = [
-	[
-		"b4", "b5", "b6", { c4 = [ "d1", "d3" ] }
-	]
-]"#;
+    fn get_duration_units() {
+        let config = parse(r#"a="1.5h", b="250ms", c="2d", d=0.5, e="10us""#).unwrap();
         assert_eq!(
-            serialize_pretty_with_options(
-                &value,
-                &PrettyOptions {
-                    indent: "\t".to_string(),
-                    max_inline_width: 44,
-                }
-            ),
-            expected
+            get_duration(&config, "a").unwrap(),
+            Duration::from_secs(5400)
+        );
+        assert_eq!(
+            get_duration(&config, "b").unwrap(),
+            Duration::from_millis(250)
+        );
+        assert_eq!(
+            get_duration(&config, "c").unwrap(),
+            Duration::from_secs(172_800)
+        );
+        assert_eq!(
+            get_duration(&config, "d").unwrap(),
+            Duration::from_millis(500)
+        );
+        assert_eq!(
+            get_duration(&config, "e").unwrap(),
+            Duration::from_micros(10)
         );
     }
 
     #[test]
-    fn pretty_serialize_array_no_trailing_commas() {
-        // Top-level arrays serialize bare: one element per line, no [].
-        let value = json!([1, 2, 3, "hello"]);
-        assert_eq!(serialize_pretty(&value, "  "), "1\n2\n3\n\"hello\"");
+    fn get_bytesize_units() {
+        let config = parse(r#"a="2MiB", b="1MB", c="512B", d="0.5KiB", e="1.0001KB""#).unwrap();
+        assert_eq!(get_bytesize(&config, "a").unwrap(), 2_097_152);
+        assert_eq!(get_bytesize(&config, "b").unwrap(), 1_000_000);
+        assert_eq!(get_bytesize(&config, "c").unwrap(), 512);
+        assert_eq!(get_bytesize(&config, "d").unwrap(), 512);
+        assert!(matches!(
+            get_bytesize(&config, "e").unwrap_err(),
+            JhonError::InvalidValue { .. }
+        ));
+    }
+
+    #[test]
+    fn unit_accessors_reject_bad_values() {
+        let config = parse(r#"t="30 s", u="5parsecs", v=".5s", w=true, x="-1s", y="3mb""#).unwrap();
+        for name in ["t", "u", "v", "w", "x", "missing"] {
+            assert!(
+                matches!(get_duration(&config, name), Err(JhonError::InvalidValue { ref key, .. }) if key == name),
+                "{name}"
+            );
+        }
+        let err = get_bytesize(&config, "y").unwrap_err();
+        assert!(matches!(err, JhonError::InvalidValue { ref key, .. } if key == "y"));
+        assert!(err.to_string().contains("unknown byte size unit"));
     }
 
     #[test]
-    fn round_trip_compact_preserves_value() {
-        let original = json!({"name": "John", "age": 30, "active": true});
-        let parsed = parse(&serialize(&original)).unwrap();
-        assert_eq!(original, parsed);
+    fn units_parse_standalone_strings() {
+        use units::{parse_bytesize, parse_duration};
+
+        assert_eq!(parse_duration("30s").unwrap(), 30.0);
+        assert_eq!(parse_duration("500ms").unwrap(), 0.5);
+        assert_eq!(parse_duration("1h").unwrap(), 3600.0);
+        assert_eq!(parse_bytesize("10MB").unwrap(), 10_000_000);
+        assert_eq!(parse_bytesize("2GiB").unwrap(), 2 << 30);
+
+        for bad in [
+            "", "30", "s", "30 s", "-1s", "1.s", "1..5s", "30S", "3 weeks",
+        ] {
+            let err = parse_duration(bad).unwrap_err();
+            assert!(
+                matches!(&err, JhonError::InvalidValue { key, .. } if key == bad),
+                "{bad}: {err}"
+            );
+        }
+        for bad in ["", "10", "10mb", "10 MB", "0.3B", "99999999PiB"] {
+            assert!(parse_bytesize(bad).is_err(), "{bad}");
+        }
+        assert_eq!(
+            parse_bytesize("10XB").unwrap_err().to_string(),
+            r#"invalid value for "10XB": unknown byte size unit "XB""#
+        );
     }
 
     #[test]
-    fn round_trip_pretty_preserves_value() {
-        let original = json!({"name": "John", "tags": ["a", "b"]});
-        let parsed = parse(&serialize_pretty(&original, "  ")).unwrap();
-        assert_eq!(original, parsed);
+    fn get_typed_port_and_tags() {
+        let config = parse(r#"port=8080, tags=["a", "b"], big=70000, name="x""#).unwrap();
+        assert_eq!(get_typed::<u16>(&config, "port").unwrap(), 8080);
+        assert_eq!(
+            get_typed::<Vec<String>>(&config, "tags").unwrap(),
+            vec!["a".to_string(), "b".to_string()]
+        );
+
+        for path in ["big", "name", "tags"] {
+            let err = get_typed::<u16>(&config, path).unwrap_err();
+            assert!(
+                matches!(&err, JhonError::WrongType { key, .. } if key == path),
+                "{path}: {err}"
+            );
+        }
+        assert!(matches!(
+            get_typed::<Vec<String>>(&config, "port").unwrap_err(),
+            JhonError::WrongType { key, .. } if key == "port"
+        ));
+        assert!(matches!(
+            get_typed::<u16>(&config, "missing").unwrap_err(),
+            JhonError::InvalidValue { key, .. } if key == "missing"
+        ));
     }
 
     #[test]
-    fn hex_octal_binary_serialize_as_decimal() {
-        assert_eq!(serialize(&json!({"n": 255})), "n=255");
-        assert_eq!(serialize(&json!({"n": 511})), "n=511");
-        assert_eq!(serialize(&json!({"n": 10})), "n=10");
+    fn node_count_and_max_depth_of_fixture() {
+        let value = parse(COMPLEX_EXAMPLE).unwrap();
+        // root + 3 scalars + database{host, port, credentials[2 × {user, password}]}
+        // + limits{2} + features[3]
+        assert_eq!(node_count(&value), 1 + 3 + (1 + 2 + (1 + 2 * 3)) + 3 + 4);
+        // root → database → credentials → {user, password}
+        assert_eq!(max_depth(&value), 4);
+        assert_eq!(max_depth(&value["limits"]), 1);
+        assert_eq!(node_count(&Value::Null), 1);
+        assert_eq!(max_depth(&json!([])), 1);
     }
 
     // =========================================================================
-    // §8 — Required Parse Errors (cross-cutting)
+    // Spanned values (source-faithful parse)
     // =========================================================================
 
     #[test]
-    fn missing_value_is_error() {
-        assert!(parse("key=").is_err());
-        assert!(parse("key=,").is_err());
-        assert!(parse("a=1, key=, b=2").is_err());
+    fn spanned_single_quoted_string_round_trips_with_single_quotes() {
+        let doc = parse_spanned(r#"x='It\'s'"#).unwrap();
+        assert_eq!(serialize_spanned(&doc), r#"x='It\'s'"#);
     }
 
     #[test]
-    fn unbalanced_braces_are_error() {
-        assert!(parse("{a=1").is_err());
-        assert!(parse("a=1}").is_err());
+    fn spanned_preserves_mixed_quote_styles() {
+        let doc = parse_spanned(r#"a="say 'hi'", b='say "hi"', c=[1, 'x']"#).unwrap();
+        assert_eq!(
+            serialize_spanned(&doc),
+            r#"a="say 'hi'",b='say "hi"',c=[1,'x']"#
+        );
     }
 
-    // =========================================================================
-    // Serde Integration (orthogonal to syntax spec)
-    // =========================================================================
-
     #[test]
-    fn serde_round_trip_struct() {
-        use serde::{Deserialize, Serialize};
-
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct Person {
-            name: String,
-            age: u32,
-            email: String,
+    fn spanned_raw_string_whitespace_round_trips_verbatim() {
+        let raw = "r#\"  \tindented\n\ttab\r\n  \"quoted\"\n\n  \"#";
+        let text = format!("script = {raw}\nnested = {{ steps = [{raw}] }}");
+        let doc = parse_spanned(&text).unwrap();
+        for out in [
+            serialize_spanned(&doc),
+            serialize_spanned_pretty(&doc, "  "),
+        ] {
+            assert_eq!(out.matches(raw).count(), 2, "{out:?}");
+            assert_eq!(parse(&out).unwrap(), parse(&text).unwrap());
         }
+    }
 
-        let original = Person {
-            name: "Alice".to_string(),
-            age: 25,
-            email: "alice@example.com".to_string(),
+    #[test]
+    fn spanned_raw_string_keeps_its_hash_count() {
+        let text = r###"a=r#"a"b"#,b=r"C:\dir",c=[r##"x"#y"##]"###;
+        let doc = parse_spanned(text).unwrap();
+        let Node::Object(entries) = &doc.node else {
+            panic!("expected object")
         };
-
-        let serialized = to_string(&original).unwrap();
-        let deserialized: Person = from_str(&serialized).unwrap();
-        assert_eq!(original, deserialized);
+        assert_eq!(
+            entries[0].1.node,
+            Node::String {
+                value: r#"a"b"#.to_string(),
+                kind: StringKind::Raw(1)
+            }
+        );
+        assert!(matches!(
+            entries[1].1.node,
+            Node::String {
+                kind: StringKind::Raw(0),
+                ..
+            }
+        ));
+        assert_eq!(serialize_spanned(&doc), text);
+        assert_eq!(doc.to_value(), parse(text).unwrap());
+
+        // A hash count too small for an edited value is raised as needed.
+        let edited = SpannedValue {
+            span: 0..0,
+            node: Node::String {
+                value: r##"q"#"##.to_string(),
+                kind: StringKind::Raw(0),
+            },
+            trailing_comment: None,
+            doc: None,
+            leading_comments: Vec::new(),
+        };
+        let out = serialize_spanned(&edited);
+        assert_eq!(out, r###"r##"q"#"##"###);
+        assert_eq!(parse(&out).unwrap(), json!([r##"q"#"##]));
     }
 
     #[test]
-    fn serde_nested_struct_round_trip() {
-        use serde::{Deserialize, Serialize};
+    fn spanned_array_element_comments_survive_formatting() {
+        let text = "features = [ // core\n  \"auth\", // audited\n  /* optional */\n  // second\n  'logging'\n]";
+        let doc = parse_spanned(text).unwrap();
+        let Node::Object(entries) = &doc.node else {
+            panic!("expected object")
+        };
+        let Node::Array(items) = &entries[0].1.node else {
+            panic!("expected array")
+        };
+        assert_eq!(items[0].leading_comments, ["// core"]);
+        assert_eq!(items[0].trailing_comment.as_deref(), Some("audited"));
+        assert_eq!(items[1].leading_comments, ["/* optional */", "// second"]);
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct ServerConfig {
-            host: String,
-            port: u16,
-        }
+        let formatted = serialize_spanned_pretty(&doc, "  ");
+        assert_eq!(
+            formatted,
+            "features = [\n  // core\n  \"auth\" // audited\n  /* optional */\n  // second\n  'logging'\n]"
+        );
+        // Formatting is stable once comments sit on their own lines.
+        let reparsed = parse_spanned(&formatted).unwrap();
+        assert_eq!(serialize_spanned_pretty(&reparsed, "  "), formatted);
+        assert_eq!(reparsed.to_value(), parse(text).unwrap());
+    }
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct AppConfig {
-            name: String,
-            server: ServerConfig,
-        }
+    #[test]
+    fn element_comments_are_keyed_by_index_and_re_emitted() {
+        let text = "features=[ // core\n \"auth\", \"logging\"]";
+        let (value, comments) = parse_with_element_comments(text).unwrap();
+        assert_eq!(value, json!({"features": ["auth", "logging"]}));
+        assert_eq!(
+            comments,
+            HashMap::from([("features[0]".to_string(), "core".to_string())])
+        );
+        assert_eq!(
+            serialize_spanned_pretty(&parse_spanned(text).unwrap(), "  "),
+            "features = [\n  // core\n  \"auth\"\n  \"logging\"\n]"
+        );
 
-        let config = AppConfig {
-            name: "MyApp".to_string(),
-            server: ServerConfig {
-                host: "localhost".to_string(),
-                port: 8080,
-            },
+        let (_, comments) =
+            parse_with_element_comments("[1, /* two */ 2]\n// first\n// row\n[3]").unwrap();
+        assert_eq!(comments["[0][1]"], "two");
+        assert_eq!(comments["[1]"], "first\nrow");
+        assert_eq!(comments.len(), 2);
+    }
+
+    #[test]
+    fn spanned_inline_block_comment_leads_next_element() {
+        let doc = parse_spanned("[1, /* two */ 2]\n// three\n3").unwrap();
+        let Node::Array(top) = &doc.node else {
+            panic!("expected array")
         };
+        let Node::Array(inner) = &top[0].node else {
+            panic!("expected array")
+        };
+        assert!(inner[0].trailing_comment.is_none());
+        assert_eq!(inner[1].leading_comments, ["/* two */"]);
+        assert_eq!(top[1].leading_comments, ["// three"]);
+        assert_eq!(
+            serialize_spanned_pretty(&doc, "  "),
+            "[\n  1\n  /* two */\n  2\n]\n// three\n3"
+        );
+    }
 
-        let jhon_string = to_string(&config).unwrap();
+    #[test]
+    fn is_formatted_accepts_canonical_files_only() {
+        let canonical = "/// Service name\nname = \"app\"\nserver = {\n  host = 'localhost'\n  ports = [\n    80\n    443 // tls\n  ]\n}";
+        assert!(is_formatted(canonical, "  ").unwrap());
+        assert!(is_formatted(&format!("{}\n", canonical), "  ").unwrap());
+        assert!(!is_formatted(&format!("{}\n\n", canonical), "  ").unwrap());
+        assert!(!is_formatted(canonical, "    ").unwrap());
+
+        let messy = "/// Service name\nname=\"app\"\nserver = { host = 'localhost', ports = [80, 443 // tls\n] }";
+        assert!(!is_formatted(messy, "  ").unwrap());
+        // A comment the formatter drops means reformatting changes the file.
+        assert!(!is_formatted("// header\nname = \"app\"", "  ").unwrap());
+        assert!(is_formatted("name = ", "  ").is_err());
+    }
+
+    #[test]
+    fn spanned_to_value_matches_parse() {
+        let text = "name='x'\nserver={host=\"h\", port=80}\ntags=['a', \"b\"]";
         assert_eq!(
-            jhon_string,
-            r#"name="MyApp",server={host="localhost",port=8080}"#
+            parse_spanned(text).unwrap().to_value(),
+            parse(text).unwrap()
         );
-        let decoded: AppConfig = from_str(&jhon_string).unwrap();
-        assert_eq!(config, decoded);
     }
 
     #[test]
-    fn serde_struct_with_arrays_round_trip() {
-        use serde::{Deserialize, Serialize};
+    fn trailing_comment_captured_for_fixture_key() {
+        let (value, comments) = parse_with_trailing_comments(COMPLEX_EXAMPLE).unwrap();
+        assert_eq!(value, parse(COMPLEX_EXAMPLE).unwrap());
+        assert_eq!(comments["limits.max_file_size"], "1MB in bytes");
+        assert_eq!(comments.len(), 1);
+    }
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct Config {
-            tags: Vec<String>,
-            scores: Vec<i32>,
-        }
+    #[test]
+    fn trailing_comment_forms() {
+        let text =
+            "a = 1, // after comma\nb = [1 /* first */\n2] // list\nc = 3 /* leads d */, d = 4";
+        let (_, comments) = parse_with_trailing_comments(text).unwrap();
+        assert_eq!(comments["a"], "after comma");
+        assert_eq!(comments["b[0]"], "first");
+        assert_eq!(comments["b"], "list");
+        assert!(!comments.contains_key("c"));
+        assert!(!comments.contains_key("d"));
+    }
 
-        let config = Config {
-            tags: vec!["rust".to_string(), "web".to_string()],
-            scores: vec![100, 95, 88],
-        };
+    #[test]
+    fn doc_comment_captured_for_key() {
+        let (value, docs) = parse_with_doc_comments("/// The server port\nport=8080").unwrap();
+        assert_eq!(value, json!({"port": 8080}));
+        assert_eq!(docs["port"], "The server port");
+        assert_eq!(docs.len(), 1);
+    }
 
-        let jhon_string = to_string(&config).unwrap();
-        assert_eq!(jhon_string, r#"tags=["rust","web"],scores=[100,95,88]"#);
-        let decoded: Config = from_str(&jhon_string).unwrap();
-        assert_eq!(config, decoded);
+    #[test]
+    fn doc_comment_blocks_and_ordinary_comments() {
+        let text = "\
+// not a doc comment
+name = \"app\" // trailing
+/// Connection settings.
+///
+/// Used at startup.
+server = {
+    /// Listen port
+    port = 80, /// stays with port's line
+    host = \"h\"
+    /// Detached by the blank line below
+
+    timeout = 5
+    //// four slashes are ordinary
+    retries = 3
+}";
+        let (_, docs) = parse_with_doc_comments(text).unwrap();
+        assert_eq!(docs["server"], "Connection settings.\n\nUsed at startup.");
+        assert_eq!(docs["server.port"], "Listen port");
+        assert_eq!(docs.len(), 2, "{docs:?}");
+
+        let (_, trailing) = parse_with_trailing_comments(text).unwrap();
+        assert_eq!(trailing["name"], "trailing");
     }
 
     #[test]
-    fn serde_option_some_and_none() {
-        use serde::{Deserialize, Serialize};
+    fn spanned_records_value_spans() {
+        let doc = parse_spanned("a='xy'").unwrap();
+        let Node::Object(entries) = &doc.node else {
+            panic!("expected object");
+        };
+        assert_eq!(entries[0].1.span, 2..6);
+    }
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct Config {
-            name: String,
-            description: Option<String>,
-        }
+    // =========================================================================
+    // Limits
+    // =========================================================================
 
-        let with_some = Config {
-            name: "Test".to_string(),
-            description: Some("A test config".to_string()),
+    fn deep_arrays(depth: usize) -> Value {
+        (0..depth).fold(json!(1), |inner, _| json!([inner]))
+    }
+
+    #[test]
+    fn serialize_with_limits_rejects_deep_values() {
+        let limits = Limits {
+            max_depth: 10,
+            ..Default::default()
         };
+        let ok = deep_arrays(10);
+        assert_eq!(serialize_with_limits(&ok, &limits).unwrap(), serialize(&ok));
         assert_eq!(
-            to_string(&with_some).unwrap(),
-            r#"name="Test",description="A test config""#
+            serialize_with_limits(&deep_arrays(11), &limits),
+            Err(JhonError::LimitExceeded {
+                name: "max_depth",
+                max: 10
+            })
         );
+        // Far deeper than the limit: only `max_depth + 1` levels are visited.
+        assert!(serialize_with_limits(&deep_arrays(1_000), &limits).is_err());
+    }
 
-        let decoded: Config = from_str(r#"name="Test""#).unwrap();
-        assert_eq!(decoded.name, "Test");
-        assert_eq!(decoded.description, None);
+    #[test]
+    fn serialize_with_limits_counts_keys_and_bytes() {
+        let value = parse(COMPLEX_EXAMPLE).unwrap();
+        let keys = |max_keys| Limits {
+            max_keys,
+            ..Default::default()
+        };
+        // 6 top-level + 3 database + 2 × 2 credentials + 2 limits
+        assert!(serialize_with_limits(&value, &keys(15)).is_ok());
+        assert_eq!(
+            serialize_with_limits(&value, &keys(14)),
+            Err(JhonError::LimitExceeded {
+                name: "max_keys",
+                max: 14
+            })
+        );
+        let len = Limits {
+            max_len: 16,
+            ..Default::default()
+        };
+        assert_eq!(
+            serialize_with_limits(&value, &len),
+            Err(JhonError::OutputTooLarge { limit: 16 })
+        );
     }
 
     #[test]
-    fn serde_jhon_wrapper_round_trip() {
-        use serde::{Deserialize, Serialize};
+    fn parse_with_limits_enforces_the_same_policy() {
+        let limits = Limits {
+            max_depth: 4,
+            max_keys: 15,
+            max_len: COMPLEX_EXAMPLE.len(),
+        };
+        let value = parse_with_limits(COMPLEX_EXAMPLE, &limits).unwrap();
+        assert_eq!(
+            serialize_with_limits(&value, &limits).unwrap(),
+            serialize(&value)
+        );
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct Point {
-            x: i32,
-            y: i32,
+        let depth = Limits {
+            max_depth: 3,
+            ..limits
+        };
+        assert_eq!(
+            parse_with_limits(COMPLEX_EXAMPLE, &depth),
+            Err(JhonError::LimitExceeded {
+                name: "max_depth",
+                max: 3
+            })
+        );
+        let deep = format!("a={}1{}", "[".repeat(100_000), "]".repeat(100_000));
+        assert!(matches!(
+            parse_with_limits(&deep, &Limits::default()),
+            Err(JhonError::LimitExceeded {
+                name: "max_depth",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_with_limits(
+                COMPLEX_EXAMPLE,
+                &Limits {
+                    max_keys: 14,
+                    ..limits
+                }
+            ),
+            Err(JhonError::LimitExceeded {
+                name: "max_keys",
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse_with_limits(
+                COMPLEX_EXAMPLE,
+                &Limits {
+                    max_len: 10,
+                    ..limits
+                }
+            ),
+            Err(JhonError::LimitExceeded {
+                name: "max_len",
+                ..
+            })
+        ));
+        assert_eq!(
+            parse_with_limits(
+                "// nothing",
+                &Limits {
+                    max_depth: 0,
+                    ..limits
+                }
+            )
+            .unwrap(),
+            Value::Null
+        );
+    }
+
+    // =========================================================================
+    // Schema validation
+    // =========================================================================
+
+    fn fixture_schema() -> Schema {
+        Schema {
+            fields: vec![
+                SchemaField::required("app_name"),
+                SchemaField::required("version"),
+                SchemaField::optional("debug"),
+                SchemaField {
+                    schema: Some(Schema {
+                        fields: vec![
+                            SchemaField::required("host"),
+                            SchemaField::required("port"),
+                            SchemaField::optional("credentials"),
+                        ],
+                        ..Default::default()
+                    }),
+                    ..SchemaField::required("database")
+                },
+                SchemaField::optional("limits"),
+                SchemaField::optional("features"),
+            ],
+            ..Default::default()
         }
+    }
 
-        let point = Point { x: 10, y: 20 };
-        let jhon_string = Jhon::to_string(&point).unwrap();
-        assert_eq!(jhon_string, "x=10,y=20");
-        let decoded: Point = Jhon::from_str(&jhon_string).unwrap();
-        assert_eq!(point, decoded);
+    #[test]
+    fn fixture_conforms_to_schema() {
+        let config = parse(COMPLEX_EXAMPLE).unwrap();
+        assert_eq!(validate(&config, &fixture_schema()), []);
     }
 
     #[test]
-    fn serde_enum_representation() {
-        use serde::{Deserialize, Serialize};
+    fn mistyped_key_is_unknown_with_suggestion() {
+        let config = parse(&COMPLEX_EXAMPLE.replace("debug", "debbug")).unwrap();
+        let problems = validate(&config, &fixture_schema());
+        assert_eq!(
+            problems,
+            [SchemaViolation::UnknownKey {
+                path: String::new(),
+                key: "debbug".to_string(),
+                suggestion: Some("debug".to_string()),
+            }]
+        );
+        assert_eq!(
+            problems[0].to_string(),
+            "unknown key 'debbug' at <root> (did you mean 'debug'?)"
+        );
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        enum Status {
-            Active,
-            Inactive,
-            Pending,
-        }
+        let config = parse(&COMPLEX_EXAMPLE.replace("port", "prot")).unwrap();
+        let messages: Vec<String> = validate(&config, &fixture_schema())
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "unknown key 'prot' at database (did you mean 'port'?)",
+                "missing required key 'port' at database",
+            ]
+        );
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct Task {
-            name: String,
-            status: Status,
-        }
+        let config =
+            parse("app_name=\"x\", version=\"1\", database={host=\"h\", port=1}, zzz=1").unwrap();
+        assert_eq!(
+            validate(&config, &fixture_schema()),
+            [SchemaViolation::UnknownKey {
+                path: String::new(),
+                key: "zzz".to_string(),
+                suggestion: None,
+            }]
+        );
+    }
 
-        let task = Task {
-            name: "Task1".to_string(),
-            status: Status::Active,
-        };
+    #[test]
+    fn allow_unknown_permits_extra_keys() {
+        let mut schema = fixture_schema();
+        schema.allow_unknown = true;
+        let config = parse(&COMPLEX_EXAMPLE.replace("debug", "debbug")).unwrap();
+        assert_eq!(validate(&config, &schema), []);
 
-        let jhon_string = to_string(&task).unwrap();
-        assert_eq!(jhon_string, r#"name="Task1",status="Active""#);
-        let decoded: Task = from_str(&jhon_string).unwrap();
-        assert_eq!(decoded, task);
+        // The flag is per object: the nested database schema stays strict.
+        let config = parse(&COMPLEX_EXAMPLE.replace("host", "hots")).unwrap();
+        assert_eq!(validate(&config, &schema).len(), 2);
     }
 
     #[test]
-    fn serde_pretty_print_no_trailing_commas() {
-        use serde::{Deserialize, Serialize};
+    fn nested_schema_requires_an_object() {
+        let config = parse(r#"app_name="x", version="1", database="localhost""#).unwrap();
+        assert_eq!(
+            validate(&config, &fixture_schema()),
+            [SchemaViolation::NotAnObject {
+                path: "database".to_string()
+            }]
+        );
+    }
 
-        #[derive(Debug, Serialize, Deserialize, PartialEq)]
-        struct Config {
-            name: String,
-            age: u32,
-        }
+    // =========================================================================
+    // Borrowed values (zero-copy parse)
+    // =========================================================================
 
-        let config = Config {
-            name: "John".to_string(),
-            age: 30,
+    #[test]
+    fn borrowed_into_owned_outlives_source() {
+        let source = COMPLEX_EXAMPLE.to_string();
+        let doc = parse_borrowed(&source).unwrap();
+        let owned = doc.into_owned();
+        drop(source);
+        assert_eq!(owned, parse(COMPLEX_EXAMPLE).unwrap());
+        assert_eq!(owned["database"]["credentials"][1]["user"], "admin");
+    }
+
+    #[test]
+    fn borrowed_strings_borrow_unless_escaped() {
+        let text = r#"bare="plain", 'quoted key'=r"C:\raw", esc="a\nb", list=[1, true, null, 'x']"#;
+        let BorrowedValue::Object(entries) = parse_borrowed(text).unwrap() else {
+            panic!("expected an object")
         };
+        assert!(matches!(entries[0].0, Cow::Borrowed("bare")));
+        assert!(matches!(
+            entries[0].1,
+            BorrowedValue::String(Cow::Borrowed("plain"))
+        ));
+        assert!(matches!(entries[1].0, Cow::Borrowed("quoted key")));
+        assert!(matches!(
+            entries[1].1,
+            BorrowedValue::String(Cow::Borrowed(r"C:\raw"))
+        ));
+        assert!(matches!(&entries[2].1, BorrowedValue::String(Cow::Owned(s)) if s == "a\nb"));
+        assert_eq!(
+            entries[3].1,
+            BorrowedValue::Array(vec![
+                BorrowedValue::Number(1.into()),
+                BorrowedValue::Bool(true),
+                BorrowedValue::Null,
+                BorrowedValue::String(Cow::Borrowed("x")),
+            ])
+        );
 
+        assert_eq!(parse_borrowed("  // empty").unwrap(), BorrowedValue::Null);
         assert_eq!(
-            to_string_pretty(&config, "  ").unwrap(),
-            "name = \"John\"\nage = 30"
+            parse_borrowed("1, {a=2}").unwrap().into_owned(),
+            json!([1, {"a": 2}])
         );
+        assert!(matches!(
+            parse_borrowed("a=1, a=2"),
+            Err(JhonError::DuplicateKey { .. })
+        ));
+        let many: String = (0..500).map(|i| format!("k{i} = {i}\n")).collect();
+        assert!(matches!(
+            parse_borrowed(&format!("{many}\"k\\u0031\" = 0")),
+            Err(JhonError::DuplicateKey { key, .. }) if key == "k1"
+        ));
+        assert!(matches!(
+            parse_borrowed("a="),
+            Err(JhonError::MissingValue { .. })
+        ));
+    }
+
+    // =========================================================================
+    // Round-trip properties
+    // =========================================================================
+
+    use proptest::prelude::*;
+
+    fn arb_value() -> impl Strategy<Value = Value> {
+        let leaf = prop_oneof![
+            Just(Value::Null),
+            any::<bool>().prop_map(Value::Bool),
+            any::<i64>().prop_map(Value::from),
+            any::<u64>().prop_map(Value::from),
+            any::<f64>()
+                .prop_filter("finite", |f| f.is_finite())
+                .prop_map(Value::from),
+            any::<String>().prop_map(Value::String),
+        ];
+        leaf.prop_recursive(4, 48, 6, |inner| {
+            prop_oneof![
+                prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+                prop::collection::vec((any::<String>(), inner), 0..6)
+                    .prop_map(|entries| Value::Object(entries.into_iter().collect())),
+            ]
+        })
+    }
+
+    /// Documents that round-trip per SPEC.md §7: a non-empty object or array
+    /// at the top level (empty and scalar roots intentionally do not).
+    fn arb_document() -> impl Strategy<Value = Value> {
+        arb_value().prop_filter("non-empty container root", |v| match v {
+            Value::Object(map) => !map.is_empty(),
+            Value::Array(arr) => !arr.is_empty(),
+            _ => false,
+        })
+    }
+
+    proptest! {
+        #[test]
+        fn compact_round_trip(value in arb_document()) {
+            let text = serialize(&value);
+            let parsed = parse(&text).map_err(|e| TestCaseError::fail(format!("{e}\n{text}")))?;
+            prop_assert!(values_equal_loose(&parsed, &value), "{}", text);
+        }
+
+        #[test]
+        fn pretty_round_trip(value in arb_document()) {
+            let text = serialize_pretty(&value, "  ");
+            let parsed = parse(&text).map_err(|e| TestCaseError::fail(format!("{e}\n{text}")))?;
+            prop_assert!(values_equal_loose(&parsed, &value), "{}", text);
+        }
     }
 }